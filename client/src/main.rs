@@ -5,13 +5,22 @@ use eframe::{
 	egui::{self, epaint},
 	epi,
 };
-use game::prelude::*;
-use server::Client;
-use std::net::TcpStream;
+use game::{grid::GridUpdate, prelude::*};
+use server::{
+	chat::Message, encoding::Encoding, Client, ErrorChain, Status, Update,
+};
+use std::{
+	net::TcpStream,
+	sync::mpsc::{self, Receiver, TryRecvError},
+	thread,
+};
 
 /// Print grid into stdout when available.
 const DEBUG_GRID: bool = false;
 
+/// How many chat messages are shown.
+const CHAT_HISTORY: usize = 50;
+
 fn main() {
 	let matches = CliApp::new("Snake Game Client by Mark")
         .about("Allows connecting to some multiplayer server")
@@ -63,8 +72,32 @@ pub struct GuiApp {
 	/// Server stream.
 	stream: Option<TcpStream>,
 
+	/// Encoding agreed on with the server.
+	encoding: Encoding,
+
+	/// Updates pushed by the server and read in a separate thread.
+	updates: Option<Receiver<Update>>,
+
 	/// Game grid.
 	grid: Option<Grid>,
+
+	/// Server status.
+	status: Option<Status>,
+
+	/// Names and lengths of snakes.
+	scoreboard: Vec<(String, usize)>,
+
+	/// Flag which determines does client watch the game instead of playing.
+	spectate: bool,
+
+	/// Name of the player to follow while watching the game.
+	follow: String,
+
+	/// Last chat messages.
+	messages: Vec<Message>,
+
+	/// Chat message being typed.
+	chat_input: String,
 }
 
 impl Client for GuiApp {
@@ -76,10 +109,10 @@ impl Client for GuiApp {
 		self.stream.as_mut()
 	}
 
-	fn stream_clone(&self) -> Option<TcpStream> {
-		self.stream
-			.as_ref()
-			.map(|stream| stream.try_clone().unwrap())
+	fn stream_clone(&self) -> server::Result<TcpStream> {
+		let stream =
+			self.stream.as_ref().ok_or(server::ServerError::NoStream)?;
+		Ok(stream.try_clone()?)
 	}
 
 	fn set_id(&mut self, id: Option<String>) {
@@ -89,6 +122,14 @@ impl Client for GuiApp {
 	fn id(&self) -> Option<String> {
 		self.id.clone()
 	}
+
+	fn set_encoding(&mut self, encoding: Encoding) {
+		self.encoding = encoding
+	}
+
+	fn encoding(&self) -> Encoding {
+		self.encoding
+	}
 }
 
 impl GuiApp {
@@ -106,23 +147,102 @@ where {
 			make_connection,
 			connection_status: String::new(),
 			stream: None,
+			encoding: Encoding::Json,
+			updates: None,
 			grid: None,
+			status: None,
+			scoreboard: vec![],
+			spectate: false,
+			follow: String::new(),
+			messages: vec![],
+			chat_input: String::new(),
 		}
 	}
 
-	/// Connect to the server.
+	/// Connect to the server as a player or as a spectator if
+	/// `self.spectate` is true.
 	///
 	/// # Panic
 	/// Panics if `self.address` or `self.name` is none.
 	fn connect(&mut self) {
 		let address = self.address.clone().unwrap();
 		self.make_connection = false;
-		match <Self as Client>::connect(self, address) {
+		let result = match self.spectate {
+			true => {
+				let follow = Some(self.follow.clone())
+					.filter(|follow| !follow.is_empty());
+				self.spectate(address, follow)
+			}
+			false => <Self as Client>::connect(self, address),
+		};
+		let result = result.and_then(|_| self.subscribe_updates());
+		match result {
 			Ok(_) => self.connection_status = String::from("Success"),
-			Err(e) => self.connection_status = format!("Error: {}", e),
+			Err(e) => {
+				self.connection_status = format!("Error: {}", ErrorChain(&e))
+			}
 		}
 	}
 
+	/// Subscribe to updates pushed by the server and read them in a separate
+	/// thread.
+	fn subscribe_updates(&mut self) -> server::Result<()> {
+		self.subscribe(None)?;
+		let mut stream = self.stream_clone()?;
+		// Nothing is pushed while the game is paused.
+		stream.set_read_timeout(None)?;
+		let encoding = self.encoding;
+		let max_size = self.max_response_size();
+		let (sender, receiver) = mpsc::channel();
+		thread::spawn(move || {
+			while let Ok(update) = Update::read(&mut stream, encoding, max_size)
+			{
+				if sender.send(update).is_err() {
+					break;
+				}
+			}
+		});
+		self.updates = Some(receiver);
+		Ok(())
+	}
+
+	/// Apply updates pushed by the server since the previous frame. Return
+	/// false if the connection is lost.
+	fn receive_updates(&mut self) -> bool {
+		let updates = match &self.updates {
+			Some(updates) => updates,
+			None => return true,
+		};
+		let mut outdated = false;
+		let connected = loop {
+			let update = match updates.try_recv() {
+				Ok(update) => update,
+				Err(TryRecvError::Empty) => break true,
+				Err(TryRecvError::Disconnected) => break false,
+			};
+			match update.grid {
+				GridUpdate::Full(grid) => self.grid = Some(grid),
+				delta => {
+					if let Some(grid) = &mut self.grid {
+						outdated |= grid.apply(delta).is_err();
+					}
+				}
+			}
+			self.status = Some(update.status);
+			self.scoreboard = update.scoreboard;
+			self.messages.extend(update.messages);
+			let overflow = self.messages.len().saturating_sub(CHAT_HISTORY);
+			self.messages.drain(..overflow);
+		};
+		// Grid has missed some changes, so subscribe again to get the whole
+		// one.
+		if connected && outdated {
+			self.grid = None;
+			return self.subscribe(None).is_ok();
+		}
+		connected
+	}
+
 	/// Disconnect from the server.
 	///
 	/// # Panic
@@ -133,9 +253,15 @@ where {
 		match <Self as Client>::disconnect(self) {
 			Ok(_) => {
 				self.stream = None;
+				self.updates = None;
+				self.grid = None;
+				self.status = None;
+				self.messages.clear();
 				self.connection_status = String::from("Disconnected")
 			}
-			Err(e) => self.connection_status = format!("Error: {}", e),
+			Err(e) => {
+				self.connection_status = format!("Error: {}", ErrorChain(&e))
+			}
 		}
 	}
 
@@ -144,6 +270,13 @@ where {
 		self.disconnect();
 		self.connect();
 	}
+
+	/// Show the error of a request in the connection status.
+	fn report(&mut self, result: server::Result<()>) {
+		if let Err(e) = result {
+			self.connection_status = format!("Error: {}", ErrorChain(&e));
+		}
+	}
 }
 
 impl epi::App for GuiApp {
@@ -186,24 +319,39 @@ impl epi::App for GuiApp {
 				self.initial_id = Some(initial_id.clone());
 				self.id = Some(initial_id);
 
-				if ui.button("Connect").clicked()
-					|| ctx.input().key_pressed(egui::Key::Enter)
-				{
-					self.connection_status =
-						String::from("Try connecting to server");
-					self.make_connection = true;
-				};
+				ui.label("Player to follow when watching:");
+				ui.text_edit_singleline(&mut self.follow);
+
+				ui.horizontal(|ui| {
+					let connect = ui.button("Connect").clicked()
+						|| ctx.input().key_pressed(egui::Key::Enter);
+					let watch = ui.button("Watch").clicked();
+					if connect || watch {
+						self.connection_status =
+							String::from("Try connecting to server");
+						self.spectate = watch;
+						self.make_connection = true;
+					}
+				});
 				ui.label(self.connection_status.clone());
 			});
 		} else {
-			let mut grid = self.request_grid();
-			while grid.is_err() {
-				grid = self.request_grid();
+			if !self.receive_updates() {
+				self.stream = None;
+				self.updates = None;
+				self.grid = None;
+				self.status = None;
+				self.messages.clear();
+				self.connection_status = String::from("Connection is lost");
+				return;
 			}
-			self.grid = Some(grid.unwrap());
 
 			egui::CentralPanel::default().show(ctx, |ui| {
-                let grid = self.grid.clone().unwrap();
+                // The first update hasn't been pushed yet.
+                let grid = match self.grid.clone() {
+                    Some(grid) => grid,
+                    None => return,
+                };
 
                 if DEBUG_GRID {
                     println!(
@@ -221,14 +369,25 @@ impl epi::App for GuiApp {
 
                 let mut shapes: Vec<egui::Shape> = Vec::new();
 
-                let grid = self.grid.clone().unwrap();
+                // Pointy-top hexagon radius which makes hexagon `cell` wide.
+                let radius = cell / 3f32.sqrt();
+                let (width, height) = match grid.topology {
+                    Topology::Square => (
+                        grid.size.0 as f32 * cell,
+                        grid.size.1 as f32 * cell,
+                    ),
+                    Topology::Hex => (
+                        grid.size.0 as f32 * cell + cell / 2.0,
+                        (grid.size.1 as f32 - 1.0) * radius * 1.5 + radius * 2.0,
+                    ),
+                };
 
                 shapes.push(egui::Shape::Rect(epaint::RectShape::stroke(
                     epaint::Rect {
                         min: egui::pos2(offset - frame, offset - frame),
                         max: egui::pos2(
-                            (grid.size.0 as f32 * cell) + frame + cell * 2.0,
-                            (grid.size.1 as f32 * cell) + frame + cell,
+                            width + frame + cell * 2.0,
+                            height + frame + cell,
                         ),
                     },
                     0.0,
@@ -237,11 +396,20 @@ impl epi::App for GuiApp {
 
                 let offset = offset + frame / 2.0;
 
-                for point in grid.data {
+                for point in grid.points() {
                     let (x, y) = (
                         point.coordinates.x as f32,
                         (grid.size.1 as i32 - point.coordinates.y) as f32,
                     );
+                    if grid.topology == Topology::Hex {
+                        let shift = (point.coordinates.y % 2 == 0) as i32 as f32;
+                        let center = egui::pos2(
+                            cell * x + offset - cell / 2.0 + shift * cell / 2.0,
+                            radius * 1.5 * (y - 1.0) + offset + radius,
+                        );
+                        shapes.push(hexagon(center, radius, color32(point.color)));
+                        continue;
+                    }
                     shapes.push(egui::Shape::Rect(epaint::RectShape::filled(
                         epaint::Rect {
                             min: egui::pos2(cell * x + offset - cell, cell * y + offset - cell),
@@ -256,14 +424,52 @@ impl epi::App for GuiApp {
             });
 			ctx.request_repaint();
 
-			if ctx.input().key_pressed(egui::Key::W) {
-				self.change_direction(Direction::Up).unwrap();
-			} else if ctx.input().key_pressed(egui::Key::S) {
-				self.change_direction(Direction::Down).unwrap();
-			} else if ctx.input().key_pressed(egui::Key::A) {
-				self.change_direction(Direction::Left).unwrap();
-			} else if ctx.input().key_pressed(egui::Key::D) {
-				self.change_direction(Direction::Right).unwrap();
+			let hex = self.grid.as_ref().map(|grid| grid.topology)
+				== Some(Topology::Hex);
+			let keys: &[(egui::Key, Direction)] = match hex {
+				true => &[
+					(egui::Key::W, Direction::UpLeft),
+					(egui::Key::E, Direction::UpRight),
+					(egui::Key::A, Direction::Left),
+					(egui::Key::D, Direction::Right),
+					(egui::Key::Z, Direction::DownLeft),
+					(egui::Key::X, Direction::DownRight),
+				],
+				false => &[
+					(egui::Key::W, Direction::Up),
+					(egui::Key::S, Direction::Down),
+					(egui::Key::A, Direction::Left),
+					(egui::Key::D, Direction::Right),
+				],
+			};
+			// E key changes direction on the hexagonal grid.
+			let turn_right = ctx.input().key_pressed(egui::Key::ArrowRight)
+				|| !hex && ctx.input().key_pressed(egui::Key::E);
+			let turn_left = ctx.input().key_pressed(egui::Key::ArrowLeft)
+				|| ctx.input().key_pressed(egui::Key::Q);
+
+			// Spectators can't control snakes or vote.
+			let playing = !self.spectate;
+
+			if ctx.wants_keyboard_input() {
+				// Keys are typed into the chat.
+			} else if let Some(&(_, direction)) = keys
+				.iter()
+				.find(|(key, _)| ctx.input().key_pressed(*key))
+				.filter(|_| playing)
+			{
+				let result = self.change_direction(direction);
+				self.report(result);
+			} else if playing && turn_left {
+				let result = self.turn(Turn::Left);
+				self.report(result);
+			} else if playing && turn_right {
+				let result = self.turn(Turn::Right);
+				self.report(result);
+			} else if playing && ctx.input().key_pressed(egui::Key::P) {
+				let paused = self.status.as_ref().map(|status| status.paused);
+				let result = self.vote_pause(paused != Some(true));
+				self.report(result);
 			} else if ctx.input().key_pressed(egui::Key::R) {
 				self.reconnect();
 			} else if ctx.input().key_pressed(egui::Key::Escape) {
@@ -275,7 +481,63 @@ impl epi::App for GuiApp {
 					if ui.button("Disconnect").clicked() {
 						self.disconnect();
 					};
+					ui.label(self.connection_status.clone());
+					if let Some(status) = self.status.clone() {
+						ui.label(format!("Tick {}", status.tick));
+						let (label, votes) = match status.paused {
+							true => ("Vote resume", status.resume_votes),
+							false => ("Vote pause", status.pause_votes),
+						};
+						if status.paused {
+							ui.label("Paused");
+						}
+						ui.label(format!("{}/{} votes", votes, status.players));
+						if playing && ui.button(label).clicked() {
+							let result = self.vote_pause(!status.paused);
+							self.report(result);
+						}
+					}
+
+					ui.separator();
+					if !playing {
+						ui.label("Watching");
+					}
+					for (name, length) in &self.scoreboard {
+						let line = format!("{}: {}", name, length);
+						match *name == self.follow && !playing {
+							true => ui.colored_label(egui::Color32::YELLOW, line),
+							false => ui.label(line),
+						};
+					}
 				});
+
+			egui::SidePanel::new(egui::panel::Side::Right, "chat_panel").show(
+				ctx,
+				|ui| {
+					ui.heading("Chat");
+					egui::ScrollArea::vertical().show(ui, |ui| {
+						for message in &self.messages {
+							match message.author {
+								Some(_) => ui.label(message.to_string()),
+								None => ui.colored_label(
+									egui::Color32::GRAY,
+									message.to_string(),
+								),
+							};
+						}
+					});
+					ui.separator();
+					let input = ui.text_edit_singleline(&mut self.chat_input);
+					let send = ui.button("Send").clicked()
+						|| input.lost_focus()
+							&& ctx.input().key_pressed(egui::Key::Enter);
+					if send && !self.chat_input.trim().is_empty() {
+						let text = std::mem::take(&mut self.chat_input);
+						let result = self.say(&text);
+						self.report(result);
+					}
+				},
+			);
 		}
 	}
 
@@ -286,6 +548,22 @@ impl epi::App for GuiApp {
 	}
 }
 
+/// Return pointy-top hexagon shape.
+fn hexagon(
+	center: egui::Pos2,
+	radius: f32,
+	color: egui::Color32,
+) -> egui::Shape {
+	let points = (0..6)
+		.map(|i| {
+			let angle = std::f32::consts::PI / 3.0 * i as f32
+				+ std::f32::consts::PI / 6.0;
+			center + radius * egui::vec2(angle.cos(), angle.sin())
+		})
+		.collect();
+	egui::Shape::convex_polygon(points, color, epaint::Stroke::none())
+}
+
 fn color32(color: Color) -> egui::Color32 {
 	egui::Color32::from_rgba_premultiplied(color.r, color.g, color.b, color.a)
 }
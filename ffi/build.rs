@@ -0,0 +1,14 @@
+fn main() {
+	println!("cargo:rerun-if-changed=src");
+	println!("cargo:rerun-if-changed=cbindgen.toml");
+
+	// The header is checked in, it's regenerated only on request.
+	#[cfg(feature = "header")]
+	{
+		let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+
+		cbindgen::generate(&crate_dir)
+			.expect("generating C header")
+			.write_to_file(format!("{}/include/snake_game.h", crate_dir));
+	}
+}
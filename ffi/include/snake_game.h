@@ -0,0 +1,202 @@
+#ifndef SNAKE_GAME_H
+#define SNAKE_GAME_H
+
+/* Generated by cbindgen from the ffi crate, don't edit it manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Kind of the object located in a grid cell.
+typedef enum SgCellKind {
+  SG_CELL_KIND_EMPTY,
+  SG_CELL_KIND_SNAKE_PART,
+  SG_CELL_KIND_APPLE,
+} SgCellKind;
+
+// Snake direction. Diagonal directions are used only on the hexagonal grid.
+typedef enum SgDirection {
+  SG_DIRECTION_UP,
+  SG_DIRECTION_DOWN,
+  SG_DIRECTION_LEFT,
+  SG_DIRECTION_RIGHT,
+  SG_DIRECTION_UP_LEFT,
+  SG_DIRECTION_UP_RIGHT,
+  SG_DIRECTION_DOWN_LEFT,
+  SG_DIRECTION_DOWN_RIGHT,
+} SgDirection;
+
+// Error codes returned by the API.
+typedef enum SgError {
+  // No error.
+  SG_ERROR_OK = 0,
+  // Required pointer argument is null.
+  SG_ERROR_NULL_POINTER,
+  // String argument isn't valid UTF-8.
+  SG_ERROR_INVALID_STRING,
+  // Buffer passed by the caller is too small.
+  SG_ERROR_BUFFER_TOO_SMALL,
+  // See [`GameError::SnakeNotFound`].
+  SG_ERROR_SNAKE_NOT_FOUND,
+  // See [`GameError::TooMuchSnakes`].
+  SG_ERROR_TOO_MUCH_SNAKES,
+  // See [`GameError::TooMuchApples`].
+  SG_ERROR_TOO_MUCH_APPLES,
+  // See [`GameError::EmptySnake`].
+  SG_ERROR_EMPTY_SNAKE,
+  // See [`GameError::NonUniqueName`].
+  SG_ERROR_NON_UNIQUE_NAME,
+  // See [`GameError::ChangeDirectionToOpposite`].
+  SG_ERROR_CHANGE_DIRECTION_TO_OPPOSITE,
+  // See [`GameError::DeltaBaseMismatch`].
+  SG_ERROR_DELTA_BASE_MISMATCH,
+  // Any other error.
+  SG_ERROR_UNKNOWN,
+  // See [`GameError::UnsupportedDirection`].
+  SG_ERROR_UNSUPPORTED_DIRECTION,
+} SgError;
+
+// Shape of the grid cells, see [`Topology`].
+typedef enum SgTopology {
+  SG_TOPOLOGY_SQUARE,
+  SG_TOPOLOGY_HEX,
+} SgTopology;
+
+// Opaque game handle.
+typedef struct SgGame SgGame;
+
+// Game settings, see [`Settings`].
+typedef struct SgSettings {
+  // Maximum number of snakes, zero means unlimited.
+  size_t snakes_amount;
+  // Maximum number of apples, zero means unlimited.
+  size_t apples_amount;
+  // How many steps snake does when it goes.
+  int32_t snake_step;
+  // How many parts are added to snake when it eats an apple.
+  size_t snake_increment_size;
+  // Initial snake length.
+  size_t snake_length;
+  // Use random initial direction for every new snake.
+  bool random_direction;
+  // Initial snake direction if it's not random.
+  enum SgDirection snake_direction;
+  // Shape of the grid cells.
+  enum SgTopology topology;
+} SgSettings;
+
+// Coordinates of a grid cell. The bottom left cell is `(1, 1)`.
+typedef struct SgCoordinates {
+  int32_t x;
+  int32_t y;
+} SgCoordinates;
+
+// One cell of the grid.
+typedef struct SgCell {
+  enum SgCellKind kind;
+  uint8_t r;
+  uint8_t g;
+  uint8_t b;
+  uint8_t a;
+} SgCell;
+
+// Return static null-terminated description of the `error`.
+const char *sg_error_message(enum SgError error);
+
+// Return default game settings.
+struct SgSettings sg_settings_default(void);
+
+// Create a new game with the grid of `width` and `height`. If `settings` is
+// null, default settings are used. Return null if the grid size is zero or
+// the game can't be played with `settings`.
+//
+// # Safety
+// `settings` must be null or point to valid [`SgSettings`].
+struct SgGame *sg_game_new(size_t width, size_t height, const struct SgSettings *settings);
+
+// Destroy the game. Does nothing if `game` is null.
+//
+// # Safety
+// `game` must be null or returned by [`sg_game_new`] and not freed yet.
+void sg_game_free(struct SgGame *game);
+
+// Seed random number generator of the game to make it reproducible.
+//
+// # Safety
+// `game` must be null or a valid game handle.
+enum SgError sg_game_set_seed(struct SgGame *game, uint64_t seed);
+
+// Add a new snake with `name` to the game. If `coords` is null, random
+// coordinates of its leading part are used.
+//
+// # Safety
+// `game` must be null or a valid game handle, `name` must be null or a
+// null-terminated string, `coords` must be null or point to valid
+// [`SgCoordinates`].
+enum SgError sg_game_spawn_snake(struct SgGame *game,
+                                 const char *name,
+                                 const struct SgCoordinates *coords);
+
+// Remove snake with `name` from the game.
+//
+// # Safety
+// `game` must be null or a valid game handle, `name` must be null or a
+// null-terminated string.
+enum SgError sg_game_kill_snake(struct SgGame *game, const char *name);
+
+// Change direction of snake with `name`.
+//
+// # Safety
+// `game` must be null or a valid game handle, `name` must be null or a
+// null-terminated string.
+enum SgError sg_game_change_direction(struct SgGame *game,
+                                      const char *name,
+                                      enum SgDirection direction);
+
+// Advance the game on one tick. If `killed` isn't null, write amount of
+// snakes died during the tick into it.
+//
+// # Safety
+// `game` must be null or a valid game handle, `killed` must be null or point
+// to writable `size_t`.
+enum SgError sg_game_advance(struct SgGame *game, size_t *killed);
+
+// Return current tick of the game or zero if `game` is null.
+//
+// # Safety
+// `game` must be null or a valid game handle.
+uint64_t sg_game_tick(const struct SgGame *game);
+
+// Return amount of snakes in the game or zero if `game` is null.
+//
+// # Safety
+// `game` must be null or a valid game handle.
+size_t sg_game_snakes(const struct SgGame *game);
+
+// Write length of snake with `name` into `length`.
+//
+// # Safety
+// `game` must be null or a valid game handle, `name` must be null or a
+// null-terminated string, `length` must be null or point to writable
+// `size_t`.
+enum SgError sg_game_snake_length(const struct SgGame *game, const char *name, size_t *length);
+
+// Write grid `width` and `height` into the arguments.
+//
+// # Safety
+// `game` must be null or a valid game handle, `width` and `height` must be
+// null or point to writable `size_t`.
+enum SgError sg_game_grid_size(const struct SgGame *game, size_t *width, size_t *height);
+
+// Copy grid into `buffer` of `len` cells. Cells go row by row starting from
+// the bottom left one, so cell `(x, y)` has `(y - 1) * width + x - 1` index.
+// `len` must be at least `width * height`.
+//
+// # Safety
+// `game` must be null or a valid game handle, `buffer` must be null or point
+// to `len` writable [`SgCell`]s.
+enum SgError sg_game_read_grid(const struct SgGame *game, struct SgCell *buffer, size_t len);
+
+#endif  /* SNAKE_GAME_H */
@@ -0,0 +1,607 @@
+//! # Snake Game C API
+//! This crate exposes the game engine through a C ABI so that it can be
+//! embedded into non-Rust frontends. `include/snake_game.h` header is
+//! generated by cbindgen, build the crate with `header` feature to regenerate
+//! it after changing the API.
+//!
+//! # Memory ownership
+//! - [`SgGame`] is created with [`sg_game_new`] and must be destroyed with
+//!   [`sg_game_free`] exactly once.
+//! - Strings passed into functions are borrowed null-terminated UTF-8 strings,
+//!   the library never keeps pointers to them.
+//! - Grid is copied into the buffer allocated by the caller, see
+//!   [`sg_game_read_grid`].
+//! - Strings returned by [`sg_error_message`] are static and must not be
+//!   freed.
+//!
+//! # Errors
+//! Every fallible function returns [`SgError`]. [`SgError::Ok`] means success,
+//! other codes map to [`GameError`] variants or to misuse of the API.
+//!
+//! # Example
+//! ```c
+//! #include "snake_game.h"
+//!
+//! SgGame *game = sg_game_new(20, 10, NULL);
+//! sg_game_spawn_snake(game, "player", NULL);
+//! sg_game_change_direction(game, "player", SG_DIRECTION_UP);
+//! sg_game_advance(game, NULL);
+//!
+//! size_t width, height;
+//! sg_game_grid_size(game, &width, &height);
+//! SgCell *cells = malloc(width * height * sizeof(SgCell));
+//! sg_game_read_grid(game, cells, width * height);
+//! /* draw cells */
+//! free(cells);
+//! sg_game_free(game);
+//! ```
+
+use game::{
+	error::GameError, grid::GameObject, prelude::*, snake::SnakeLength,
+};
+use std::ffi::{c_char, CStr};
+
+/// Error codes returned by the API.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SgError {
+	/// No error.
+	Ok = 0,
+
+	/// Required pointer argument is null.
+	NullPointer,
+
+	/// String argument isn't valid UTF-8.
+	InvalidString,
+
+	/// Buffer passed by the caller is too small.
+	BufferTooSmall,
+
+	/// See [`GameError::SnakeNotFound`].
+	SnakeNotFound,
+
+	/// See [`GameError::TooMuchSnakes`].
+	TooMuchSnakes,
+
+	/// See [`GameError::TooMuchApples`].
+	TooMuchApples,
+
+	/// See [`GameError::EmptySnake`].
+	EmptySnake,
+
+	/// See [`GameError::NonUniqueName`].
+	NonUniqueName,
+
+	/// See [`GameError::ChangeDirectionToOpposite`].
+	ChangeDirectionToOpposite,
+
+	/// See [`GameError::DeltaBaseMismatch`].
+	DeltaBaseMismatch,
+
+	/// Any other error.
+	Unknown,
+
+	/// See [`GameError::UnsupportedDirection`].
+	UnsupportedDirection,
+}
+
+impl From<GameError> for SgError {
+	fn from(e: GameError) -> Self {
+		match e {
+			GameError::SnakeNotFound(_) => Self::SnakeNotFound,
+			GameError::TooMuchSnakes(_) => Self::TooMuchSnakes,
+			GameError::TooMuchApples(_) => Self::TooMuchApples,
+			GameError::EmptySnake(_) => Self::EmptySnake,
+			GameError::NonUniqueName(_) => Self::NonUniqueName,
+			GameError::ChangeDirectionToOpposite(_) => {
+				Self::ChangeDirectionToOpposite
+			}
+			GameError::DeltaBaseMismatch(..) => Self::DeltaBaseMismatch,
+			GameError::UnsupportedDirection(..) => Self::UnsupportedDirection,
+			GameError::Json(_)
+			| GameError::InvalidSettings(_)
+			| GameError::GridTooLarge(_) => Self::Unknown,
+		}
+	}
+}
+
+impl From<game::Result<()>> for SgError {
+	fn from(result: game::Result<()>) -> Self {
+		match result {
+			Ok(()) => Self::Ok,
+			Err(e) => e.into(),
+		}
+	}
+}
+
+/// Return static null-terminated description of the `error`.
+#[no_mangle]
+pub extern "C" fn sg_error_message(error: SgError) -> *const c_char {
+	let message: &'static [u8] = match error {
+		SgError::Ok => b"no error\0",
+		SgError::NullPointer => b"required pointer argument is null\0",
+		SgError::InvalidString => b"string argument isn't valid UTF-8\0",
+		SgError::BufferTooSmall => b"buffer is too small\0",
+		SgError::SnakeNotFound => b"snake not found\0",
+		SgError::TooMuchSnakes => {
+			b"maximum amount of snakes in the game is reached\0"
+		}
+		SgError::TooMuchApples => {
+			b"maximum amount of apples in the game is reached\0"
+		}
+		SgError::EmptySnake => b"snake has no parts\0",
+		SgError::NonUniqueName => b"snake with such name already exists\0",
+		SgError::ChangeDirectionToOpposite => b"snake can't turn 180 degrees\0",
+		SgError::DeltaBaseMismatch => {
+			b"grid delta is made since another tick\0"
+		}
+		SgError::Unknown => b"unknown error\0",
+		SgError::UnsupportedDirection => {
+			b"snake can't go this direction on this grid\0"
+		}
+	};
+	message.as_ptr() as *const c_char
+}
+
+/// Snake direction. Diagonal directions are used only on the hexagonal grid.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SgDirection {
+	Up,
+	Down,
+	Left,
+	Right,
+	UpLeft,
+	UpRight,
+	DownLeft,
+	DownRight,
+}
+
+impl From<SgDirection> for Direction {
+	fn from(direction: SgDirection) -> Self {
+		match direction {
+			SgDirection::Up => Self::Up,
+			SgDirection::Down => Self::Down,
+			SgDirection::Left => Self::Left,
+			SgDirection::Right => Self::Right,
+			SgDirection::UpLeft => Self::UpLeft,
+			SgDirection::UpRight => Self::UpRight,
+			SgDirection::DownLeft => Self::DownLeft,
+			SgDirection::DownRight => Self::DownRight,
+		}
+	}
+}
+
+/// Shape of the grid cells, see [`Topology`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SgTopology {
+	Square,
+	Hex,
+}
+
+impl From<SgTopology> for Topology {
+	fn from(topology: SgTopology) -> Self {
+		match topology {
+			SgTopology::Square => Self::Square,
+			SgTopology::Hex => Self::Hex,
+		}
+	}
+}
+
+/// Coordinates of a grid cell. The bottom left cell is `(1, 1)`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SgCoordinates {
+	pub x: i32,
+	pub y: i32,
+}
+
+/// Game settings, see [`Settings`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SgSettings {
+	/// Maximum number of snakes, zero means unlimited.
+	pub snakes_amount: usize,
+
+	/// Maximum number of apples, zero means unlimited.
+	pub apples_amount: usize,
+
+	/// How many steps snake does when it goes.
+	pub snake_step: i32,
+
+	/// How many parts are added to snake when it eats an apple.
+	pub snake_increment_size: usize,
+
+	/// Initial snake length.
+	pub snake_length: usize,
+
+	/// Use random initial direction for every new snake.
+	pub random_direction: bool,
+
+	/// Initial snake direction if it's not random.
+	pub snake_direction: SgDirection,
+
+	/// Shape of the grid cells.
+	pub topology: SgTopology,
+}
+
+impl From<SgSettings> for Settings {
+	fn from(settings: SgSettings) -> Self {
+		Self {
+			snakes_amount: settings.snakes_amount,
+			apples_amount: settings.apples_amount,
+			snake_step: settings.snake_step,
+			snake_increment_size: settings.snake_increment_size,
+			snake_length: SnakeLength::Fixed(settings.snake_length),
+			snake_direction: match settings.random_direction {
+				true => None,
+				false => Some(settings.snake_direction.into()),
+			},
+			topology: settings.topology.into(),
+		}
+	}
+}
+
+/// Return default game settings.
+#[no_mangle]
+pub extern "C" fn sg_settings_default() -> SgSettings {
+	SgSettings {
+		snakes_amount: Settings::SNAKES_AMOUNT,
+		apples_amount: Settings::APPLES_AMOUNT,
+		snake_step: Settings::SNAKE_STEP,
+		snake_increment_size: Settings::SNAKE_INCREMENT_SIZE,
+		snake_length: Settings::SNAKE_LENGTH.get(),
+		random_direction: Settings::SNAKE_DIRECTION.is_none(),
+		snake_direction: SgDirection::Right,
+		topology: SgTopology::Square,
+	}
+}
+
+/// Kind of the object located in a grid cell.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SgCellKind {
+	#[default]
+	Empty,
+	SnakePart,
+	Apple,
+}
+
+/// One cell of the grid.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SgCell {
+	pub kind: SgCellKind,
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8,
+}
+
+/// Opaque game handle.
+pub struct SgGame(GameData);
+
+/// Create a new game with the grid of `width` and `height`. If `settings` is
+/// null, default settings are used. Return null if the grid size is zero or
+/// the game can't be played with `settings`.
+///
+/// # Safety
+/// `settings` must be null or point to valid [`SgSettings`].
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_new(
+	width: usize,
+	height: usize,
+	settings: *const SgSettings,
+) -> *mut SgGame {
+	let settings: Settings = match settings.as_ref() {
+		Some(settings) => (*settings).into(),
+		None => Settings::default(),
+	};
+	if settings.validate((width, height)).is_err() {
+		return std::ptr::null_mut();
+	}
+	let gamedata = GameData::new(Some((width, height)), settings);
+	Box::into_raw(Box::new(SgGame(gamedata)))
+}
+
+/// Destroy the game. Does nothing if `game` is null.
+///
+/// # Safety
+/// `game` must be null or returned by [`sg_game_new`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_free(game: *mut SgGame) {
+	if !game.is_null() {
+		drop(Box::from_raw(game));
+	}
+}
+
+/// Seed random number generator of the game to make it reproducible.
+///
+/// # Safety
+/// `game` must be null or a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_set_seed(
+	game: *mut SgGame,
+	seed: u64,
+) -> SgError {
+	match game.as_mut() {
+		Some(game) => {
+			game.0.set_seed(seed);
+			SgError::Ok
+		}
+		None => SgError::NullPointer,
+	}
+}
+
+/// Add a new snake with `name` to the game. If `coords` is null, random
+/// coordinates of its leading part are used.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `name` must be null or a
+/// null-terminated string, `coords` must be null or point to valid
+/// [`SgCoordinates`].
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_spawn_snake(
+	game: *mut SgGame,
+	name: *const c_char,
+	coords: *const SgCoordinates,
+) -> SgError {
+	let (game, name) = match (game.as_mut(), to_str(name)) {
+		(Some(game), Ok(name)) => (game, name),
+		(None, _) => return SgError::NullPointer,
+		(_, Err(e)) => return e,
+	};
+	let coords = coords.as_ref().map(|c| Coordinates::new(c.x, c.y));
+	game.0.spawn_snake(name, coords, None, None).into()
+}
+
+/// Remove snake with `name` from the game.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `name` must be null or a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_kill_snake(
+	game: *mut SgGame,
+	name: *const c_char,
+) -> SgError {
+	match (game.as_mut(), to_str(name)) {
+		(Some(game), Ok(name)) => game.0.kill_snake(name).map(drop).into(),
+		(None, _) => SgError::NullPointer,
+		(_, Err(e)) => e,
+	}
+}
+
+/// Change direction of snake with `name`.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `name` must be null or a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_change_direction(
+	game: *mut SgGame,
+	name: *const c_char,
+	direction: SgDirection,
+) -> SgError {
+	match (game.as_mut(), to_str(name)) {
+		(Some(game), Ok(name)) => game
+			.0
+			.snake_mut(name)
+			.and_then(|snake| snake.change_direction(direction.into()))
+			.into(),
+		(None, _) => SgError::NullPointer,
+		(_, Err(e)) => e,
+	}
+}
+
+/// Advance the game on one tick. If `killed` isn't null, write amount of
+/// snakes died during the tick into it.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `killed` must be null or point
+/// to writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_advance(
+	game: *mut SgGame,
+	killed: *mut usize,
+) -> SgError {
+	let game = match game.as_mut() {
+		Some(game) => game,
+		None => return SgError::NullPointer,
+	};
+	match game.0.advance() {
+		Ok(dead) => {
+			if let Some(killed) = killed.as_mut() {
+				*killed = dead.len();
+			}
+			SgError::Ok
+		}
+		Err(e) => e.into(),
+	}
+}
+
+/// Return current tick of the game or zero if `game` is null.
+///
+/// # Safety
+/// `game` must be null or a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_tick(game: *const SgGame) -> u64 {
+	game.as_ref().map_or(0, |game| game.0.tick())
+}
+
+/// Return amount of snakes in the game or zero if `game` is null.
+///
+/// # Safety
+/// `game` must be null or a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_snakes(game: *const SgGame) -> usize {
+	game.as_ref().map_or(0, |game| game.0.snakes())
+}
+
+/// Write length of snake with `name` into `length`.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `name` must be null or a
+/// null-terminated string, `length` must be null or point to writable
+/// `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_snake_length(
+	game: *const SgGame,
+	name: *const c_char,
+	length: *mut usize,
+) -> SgError {
+	let (game, length) = match (game.as_ref(), length.as_mut()) {
+		(Some(game), Some(length)) => (game, length),
+		_ => return SgError::NullPointer,
+	};
+	let name = match to_str(name) {
+		Ok(name) => name,
+		Err(e) => return e,
+	};
+	match game.0.snake(name) {
+		Ok(snake) => {
+			*length = snake.len();
+			SgError::Ok
+		}
+		Err(e) => e.into(),
+	}
+}
+
+/// Write grid `width` and `height` into the arguments.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `width` and `height` must be
+/// null or point to writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_grid_size(
+	game: *const SgGame,
+	width: *mut usize,
+	height: *mut usize,
+) -> SgError {
+	match (game.as_ref(), width.as_mut(), height.as_mut()) {
+		(Some(game), Some(width), Some(height)) => {
+			(*width, *height) = game.0.grid().size;
+			SgError::Ok
+		}
+		_ => SgError::NullPointer,
+	}
+}
+
+/// Copy grid into `buffer` of `len` cells. Cells go row by row starting from
+/// the bottom left one, so cell `(x, y)` has `(y - 1) * width + x - 1` index.
+/// `len` must be at least `width * height`.
+///
+/// # Safety
+/// `game` must be null or a valid game handle, `buffer` must be null or point
+/// to `len` writable [`SgCell`]s.
+#[no_mangle]
+pub unsafe extern "C" fn sg_game_read_grid(
+	game: *const SgGame,
+	buffer: *mut SgCell,
+	len: usize,
+) -> SgError {
+	let game = match game.as_ref() {
+		Some(game) if !buffer.is_null() => game,
+		_ => return SgError::NullPointer,
+	};
+	let grid = game.0.grid();
+	let (width, height) = grid.size;
+	if len < width * height {
+		return SgError::BufferTooSmall;
+	}
+
+	let cells = std::slice::from_raw_parts_mut(buffer, width * height);
+	for (i, cell) in cells.iter_mut().enumerate() {
+		let coords =
+			Coordinates::new((i % width) as i32 + 1, (i / width) as i32 + 1);
+		*cell = match grid.get(coords) {
+			Some(point) => SgCell {
+				kind: match point.object_kind {
+					GameObject::SnakePart => SgCellKind::SnakePart,
+					GameObject::Apple => SgCellKind::Apple,
+				},
+				r: point.color.r,
+				g: point.color.g,
+				b: point.color.b,
+				a: point.color.a,
+			},
+			None => SgCell::default(),
+		};
+	}
+
+	SgError::Ok
+}
+
+/// Convert borrowed C string into Rust one.
+unsafe fn to_str<'a>(string: *const c_char) -> Result<&'a str, SgError> {
+	if string.is_null() {
+		return Err(SgError::NullPointer);
+	}
+	CStr::from_ptr(string)
+		.to_str()
+		.map_err(|_| SgError::InvalidString)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::ptr;
+
+	#[test]
+	fn game_lifecycle() {
+		unsafe {
+			let settings = SgSettings {
+				apples_amount: 0,
+				..sg_settings_default()
+			};
+			let game = sg_game_new(5, 5, &settings);
+			let name = c"snake".as_ptr();
+			let coords = SgCoordinates { x: 2, y: 2 };
+
+			assert_eq!(sg_game_spawn_snake(game, name, &coords), SgError::Ok);
+			assert_eq!(
+				sg_game_spawn_snake(game, name, ptr::null()),
+				SgError::NonUniqueName
+			);
+			assert_eq!(
+				sg_game_change_direction(game, name, SgDirection::Up),
+				SgError::Ok
+			);
+
+			let mut killed = 1;
+			assert_eq!(sg_game_advance(game, &mut killed), SgError::Ok);
+			assert_eq!(killed, 0);
+			assert_eq!(sg_game_tick(game), 1);
+
+			let mut cells = vec![SgCell::default(); 25];
+			assert_eq!(
+				sg_game_read_grid(game, cells.as_mut_ptr(), 24),
+				SgError::BufferTooSmall
+			);
+			assert_eq!(
+				sg_game_read_grid(game, cells.as_mut_ptr(), 25),
+				SgError::Ok
+			);
+			// Snake went up from (2, 2) to (2, 3).
+			assert_eq!(cells[2 * 5 + 1].kind, SgCellKind::SnakePart);
+			assert_eq!(cells[5 + 1].kind, SgCellKind::Empty);
+
+			assert_eq!(sg_game_kill_snake(game, name), SgError::Ok);
+			assert_eq!(sg_game_kill_snake(game, name), SgError::SnakeNotFound);
+			assert_eq!(
+				sg_game_kill_snake(game, ptr::null()),
+				SgError::NullPointer
+			);
+
+			sg_game_free(game);
+		}
+	}
+
+	#[test]
+	fn zero_grid() {
+		unsafe {
+			assert!(sg_game_new(0, 5, ptr::null()).is_null());
+		}
+	}
+}
@@ -9,7 +9,7 @@ use std::{fmt, ops, str::FromStr};
 ///
 /// Note that this coordinates system is same as in math, so (0, 0) point is the
 /// bottom left corner of the screen.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct Coordinates {
 	/// Coordinate relative to the abscissa axis.
@@ -24,6 +24,22 @@ impl Coordinates {
 	pub fn new(x: i32, y: i32) -> Self {
 		Self { x, y }
 	}
+
+	/// Return coordinates moved on `step` points towards `direction` on the
+	/// square grid. Diagonal directions change both coordinates. See
+	/// [`Topology::moved`] to move on any grid.
+	pub fn moved(self, direction: Direction, step: i32) -> Self {
+		match direction {
+			Direction::Up => self + Self::new(0, step),
+			Direction::Down => self + Self::new(0, -step),
+			Direction::Left => self + Self::new(-step, 0),
+			Direction::Right => self + Self::new(step, 0),
+			Direction::UpLeft => self + Self::new(-step, step),
+			Direction::UpRight => self + Self::new(step, step),
+			Direction::DownLeft => self + Self::new(-step, -step),
+			Direction::DownRight => self + Self::new(step, -step),
+		}
+	}
 }
 
 impl From<Coordinates> for (i32, i32) {
@@ -75,9 +91,33 @@ pub enum Direction {
 
 	/// Right.
 	Right,
+
+	/// Up and left. Used only on the hexagonal grid.
+	UpLeft,
+
+	/// Up and right. Used only on the hexagonal grid.
+	UpRight,
+
+	/// Down and left. Used only on the hexagonal grid.
+	DownLeft,
+
+	/// Down and right. Used only on the hexagonal grid.
+	DownRight,
 }
 
 impl Direction {
+	/// All directions.
+	pub const ALL: [Direction; 8] = [
+		Self::Up,
+		Self::Down,
+		Self::Left,
+		Self::Right,
+		Self::UpLeft,
+		Self::UpRight,
+		Self::DownLeft,
+		Self::DownRight,
+	];
+
 	/// Return opposite direction.
 	pub fn opposite(self) -> Self {
 		match self {
@@ -85,6 +125,48 @@ impl Direction {
 			Self::Down => Self::Up,
 			Self::Left => Self::Right,
 			Self::Right => Self::Left,
+			Self::UpLeft => Self::DownRight,
+			Self::UpRight => Self::DownLeft,
+			Self::DownLeft => Self::UpRight,
+			Self::DownRight => Self::UpLeft,
+		}
+	}
+
+	/// Return direction rotated 90 degrees counterclockwise. See
+	/// [`Topology::turn`] to turn on any grid.
+	pub fn turn_left(self) -> Self {
+		match self {
+			Self::Up => Self::Left,
+			Self::Left => Self::Down,
+			Self::Down => Self::Right,
+			Self::Right => Self::Up,
+			Self::UpLeft => Self::DownLeft,
+			Self::DownLeft => Self::DownRight,
+			Self::DownRight => Self::UpRight,
+			Self::UpRight => Self::UpLeft,
+		}
+	}
+
+	/// Return direction rotated 90 degrees clockwise. See [`Topology::turn`]
+	/// to turn on any grid.
+	pub fn turn_right(self) -> Self {
+		match self {
+			Self::Up => Self::Right,
+			Self::Right => Self::Down,
+			Self::Down => Self::Left,
+			Self::Left => Self::Up,
+			Self::UpRight => Self::DownRight,
+			Self::DownRight => Self::DownLeft,
+			Self::DownLeft => Self::UpLeft,
+			Self::UpLeft => Self::UpRight,
+		}
+	}
+
+	/// Return direction after making a relative `turn` on the square grid.
+	pub fn turn(self, turn: Turn) -> Self {
+		match turn {
+			Turn::Left => self.turn_left(),
+			Turn::Right => self.turn_right(),
 		}
 	}
 }
@@ -97,8 +179,17 @@ impl Default for Direction {
 
 impl fmt::Display for Direction {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let lower_case = format!("{:?}", self).to_lowercase();
-		write!(f, "{}", lower_case)
+		let name = match self {
+			Self::Up => "up",
+			Self::Down => "down",
+			Self::Left => "left",
+			Self::Right => "right",
+			Self::UpLeft => "up_left",
+			Self::UpRight => "up_right",
+			Self::DownLeft => "down_left",
+			Self::DownRight => "down_right",
+		};
+		write!(f, "{}", name)
 	}
 }
 
@@ -111,6 +202,10 @@ impl FromStr for Direction {
 			"down" => Ok(Self::Down),
 			"right" => Ok(Self::Right),
 			"left" => Ok(Self::Left),
+			"up_left" => Ok(Self::UpLeft),
+			"up_right" => Ok(Self::UpRight),
+			"down_left" => Ok(Self::DownLeft),
+			"down_right" => Ok(Self::DownRight),
 			_ => Err(ParseDirectionError),
 		}
 	}
@@ -124,6 +219,156 @@ impl ops::Neg for Direction {
 	}
 }
 
+/// Turn relative to the current direction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Turn {
+	/// Turn 90 degrees counterclockwise.
+	Left,
+
+	/// Turn 90 degrees clockwise.
+	Right,
+}
+
+impl fmt::Display for Turn {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let lower_case = format!("{:?}", self).to_lowercase();
+		write!(f, "{}", lower_case)
+	}
+}
+
+impl FromStr for Turn {
+	type Err = ParseTurnError;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"left" => Ok(Self::Left),
+			"right" => Ok(Self::Right),
+			_ => Err(ParseTurnError),
+		}
+	}
+}
+
+/// Shape of the grid cells.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+	/// Square cells, snake goes up, down, left and right.
+	#[default]
+	Square,
+
+	/// Hexagonal cells with pointy tops, snake goes left, right and in four
+	/// diagonal directions. Rows with even ordinate are shifted right on half
+	/// of a cell.
+	Hex,
+}
+
+impl Topology {
+	/// Return directions snake can go on the grid.
+	pub fn directions(self) -> &'static [Direction] {
+		match self {
+			Self::Square => &Direction::ALL[..4],
+			Self::Hex => &[
+				Direction::Right,
+				Direction::UpRight,
+				Direction::UpLeft,
+				Direction::Left,
+				Direction::DownLeft,
+				Direction::DownRight,
+			],
+		}
+	}
+
+	/// Return true if snake can go towards `direction` on the grid.
+	pub fn supports(self, direction: Direction) -> bool {
+		self.directions().contains(&direction)
+	}
+
+	/// Return coordinates moved on `step` cells towards `direction`.
+	pub fn moved(
+		self,
+		coords: Coordinates,
+		direction: Direction,
+		step: i32,
+	) -> Coordinates {
+		if self == Self::Square || step == 0 {
+			return coords.moved(direction, step);
+		}
+		let direction = match step < 0 {
+			true => -direction,
+			false => direction,
+		};
+		let mut coords = coords;
+		for _ in 0..step.abs() {
+			// Diagonal neighbors of a shifted row are on the right.
+			let shift = (coords.y % 2 == 0) as i32;
+			coords = match direction {
+				Direction::UpLeft => (coords.x - 1 + shift, coords.y + 1),
+				Direction::UpRight => (coords.x + shift, coords.y + 1),
+				Direction::DownLeft => (coords.x - 1 + shift, coords.y - 1),
+				Direction::DownRight => (coords.x + shift, coords.y - 1),
+				direction => coords.moved(direction, 1).into(),
+			}
+			.into();
+		}
+		coords
+	}
+
+	/// Return direction after making a relative `turn`. Snake turns 90
+	/// degrees on the square grid and 60 degrees on the hexagonal one.
+	pub fn turn(self, direction: Direction, turn: Turn) -> Direction {
+		let directions = self.directions();
+		match directions.iter().position(|&d| d == direction) {
+			Some(i) => {
+				let n = directions.len();
+				match (self, turn) {
+					// Square directions aren't ordered by angle.
+					(Self::Square, _) => direction.turn(turn),
+					(Self::Hex, Turn::Left) => directions[(i + 1) % n],
+					(Self::Hex, Turn::Right) => directions[(i + n - 1) % n],
+				}
+			}
+			None => direction,
+		}
+	}
+
+	/// Return amount of steps between two cells.
+	pub fn distance(self, a: Coordinates, b: Coordinates) -> usize {
+		match self {
+			Self::Square => {
+				let d = a - b;
+				(d.x.unsigned_abs() + d.y.unsigned_abs()) as usize
+			}
+			Self::Hex => {
+				// Convert offset coordinates into axial ones.
+				let axial = |c: Coordinates| (c.x - (c.y + (c.y & 1)) / 2, c.y);
+				let ((aq, ar), (bq, br)) = (axial(a), axial(b));
+				let (dq, dr) = (aq - bq, ar - br);
+				((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as usize
+			}
+		}
+	}
+}
+
+impl fmt::Display for Topology {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let lower_case = format!("{:?}", self).to_lowercase();
+		write!(f, "{}", lower_case)
+	}
+}
+
+impl FromStr for Topology {
+	type Err = ParseTopologyError;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"square" => Ok(Self::Square),
+			"hex" => Ok(Self::Hex),
+			_ => Err(ParseTopologyError),
+		}
+	}
+}
+
 /// A color in the sRGB color space.
 #[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -248,6 +493,16 @@ mod tests {
 		assert_eq!(coords1 - coords2, coords3);
 	}
 
+	#[test]
+	fn coords_moved() {
+		let coords = Coordinates::new(3, 4);
+
+		assert_eq!(coords.moved(Direction::Up, 2), (3, 6).into());
+		assert_eq!(coords.moved(Direction::Down, 1), (3, 3).into());
+		assert_eq!(coords.moved(Direction::Left, 3), (0, 4).into());
+		assert_eq!(coords.moved(Direction::Right, -1), (2, 4).into());
+	}
+
 	#[test]
 	fn dir_neg() {
 		assert_eq!(Direction::Left, -Direction::Right);
@@ -263,4 +518,47 @@ mod tests {
 		assert_eq!(Direction::Left, "left".parse().unwrap());
 		assert_eq!(Direction::Right, "right".parse().unwrap());
 	}
+
+	#[test]
+	fn dir_turn() {
+		for direction in Direction::ALL {
+			assert_eq!(direction.turn_left().turn_right(), direction);
+			assert_eq!(direction.turn_right().turn_right(), -direction);
+			assert_eq!(direction.turn(Turn::Left), direction.turn_left());
+		}
+		assert_eq!(Direction::Up.turn_left(), Direction::Left);
+		assert_eq!(Direction::Up.turn_right(), Direction::Right);
+	}
+
+	#[test]
+	fn hex_moved() {
+		let hex = Topology::Hex;
+		let (even, odd) = (Coordinates::new(2, 2), Coordinates::new(2, 1));
+
+		assert_eq!(hex.moved(even, Direction::UpLeft, 1), (2, 3).into());
+		assert_eq!(hex.moved(even, Direction::UpRight, 1), (3, 3).into());
+		assert_eq!(hex.moved(odd, Direction::UpLeft, 1), (1, 2).into());
+		assert_eq!(hex.moved(odd, Direction::DownRight, 1), (2, 0).into());
+		assert_eq!(hex.moved(even, Direction::UpRight, 2), (3, 4).into());
+		assert_eq!(hex.moved(even, Direction::UpRight, -1), (2, 1).into());
+	}
+
+	#[test]
+	fn hex_turn_and_distance() {
+		let hex = Topology::Hex;
+		let mut direction = Direction::Right;
+		for _ in 0..6 {
+			direction = hex.turn(direction, Turn::Left);
+			assert!(hex.supports(direction));
+		}
+		assert_eq!(direction, Direction::Right);
+
+		let origin = Coordinates::new(2, 2);
+		for &direction in hex.directions() {
+			let coords = hex.moved(origin, direction, 1);
+			assert_eq!(hex.distance(origin, coords), 1);
+			let coords = hex.moved(coords, direction, 2);
+			assert_eq!(hex.distance(origin, coords), 3);
+		}
+	}
 }
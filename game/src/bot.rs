@@ -0,0 +1,188 @@
+//! AI-controlled snakes.
+
+use crate::{
+	aux::{Coordinates, Direction},
+	error::ParseDifficultyError,
+	path, GameData,
+};
+use rand::{seq::SliceRandom, Rng};
+use std::{fmt, str::FromStr};
+
+/// How smart a [`Bot`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Difficulty {
+	/// Bot walks randomly, avoiding only moves which kill it right away.
+	Random,
+
+	/// Bot goes straight to the nearest apple.
+	#[default]
+	Greedy,
+
+	/// Bot goes to the nearest apple but prefers moves which leave it more
+	/// space to survive.
+	Survival,
+}
+
+impl fmt::Display for Difficulty {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let lower_case = format!("{:?}", self).to_lowercase();
+		write!(f, "{}", lower_case)
+	}
+}
+
+impl FromStr for Difficulty {
+	type Err = ParseDifficultyError;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"random" => Ok(Self::Random),
+			"greedy" => Ok(Self::Greedy),
+			"survival" => Ok(Self::Survival),
+			_ => Err(ParseDifficultyError),
+		}
+	}
+}
+
+/// Snake which is driven by the game itself instead of a client.
+#[derive(Debug, Clone)]
+pub struct Bot {
+	name: String,
+	difficulty: Difficulty,
+}
+
+impl Bot {
+	/// Return a new [`Bot`] which drives snake with `name`.
+	pub fn new(name: impl Into<String>, difficulty: Difficulty) -> Self {
+		Self {
+			name: name.into(),
+			difficulty,
+		}
+	}
+
+	/// Return name of the bot's snake.
+	pub fn name(&self) -> String {
+		self.name.clone()
+	}
+
+	/// Return bot's difficulty.
+	pub fn difficulty(&self) -> Difficulty {
+		self.difficulty
+	}
+
+	/// Choose direction bot's snake should go on the next tick using `rng`
+	/// random number generator. Return none if bot's snake isn't in the game.
+	pub fn think(
+		&self,
+		gamedata: &GameData,
+		rng: &mut impl Rng,
+	) -> Option<Direction> {
+		let snake = gamedata.snake(&self.name).ok()?;
+		let current = snake.direction();
+		let grid = &gamedata.grid;
+		let moves = path::safe_moves(grid, snake, gamedata.settings.snake_step);
+
+		let apple_distance = |coords: Coordinates| {
+			path::nearest_apple(grid, coords)
+				.map_or(usize::MAX, |(_, distance)| distance)
+		};
+
+		let choice = match self.difficulty {
+			Difficulty::Random => moves.choose(rng),
+			Difficulty::Greedy => moves
+				.iter()
+				.min_by_key(|&&(_, coords)| apple_distance(coords)),
+			Difficulty::Survival => {
+				let areas: Vec<usize> = moves
+					.iter()
+					.map(|&(_, coords)| path::flood_fill(grid, coords))
+					.collect();
+				let best = areas.iter().copied().max().unwrap_or(0);
+				moves
+					.iter()
+					.zip(areas)
+					.filter(|&(_, area)| area == best)
+					.map(|(m, _)| m)
+					.min_by_key(|&&(_, coords)| apple_distance(coords))
+			}
+		};
+
+		Some(choice.map_or(current, |&(direction, _)| direction))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Settings;
+
+	#[test]
+	fn difficulty_from_str() {
+		assert_eq!(Difficulty::Random, "random".parse().unwrap());
+		assert_eq!(Difficulty::Greedy, "greedy".parse().unwrap());
+		assert_eq!(Difficulty::Survival, "survival".parse().unwrap());
+		assert!("smart".parse::<Difficulty>().is_err());
+	}
+
+	#[test]
+	fn greedy_goes_to_apple() -> crate::Result<()> {
+		let mut gd = game(Difficulty::Greedy, (4, 5))?;
+		gd.spawn_apple((5, 9).into(), None)?;
+		gd.grid = gd.build_grid();
+
+		assert_eq!(think(&gd), Some(Direction::Up));
+
+		Ok(())
+	}
+
+	#[test]
+	fn avoids_walls() -> crate::Result<()> {
+		for difficulty in
+			[Difficulty::Random, Difficulty::Greedy, Difficulty::Survival]
+		{
+			let mut gd = game(difficulty, (9, 1))?;
+			gd.grid = gd.build_grid();
+
+			assert_eq!(think(&gd), Some(Direction::Up));
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn survival_avoids_dead_ends() -> crate::Result<()> {
+		let mut gd = game(Difficulty::Survival, (7, 5))?;
+		let up = Some(Some(Direction::Up));
+		// Wall off the right column so that going right leads to a pocket
+		// with an apple inside.
+		gd.spawn_snake("wall 1", Some((9, 1).into()), up, Some(4))?;
+		gd.spawn_snake("wall 2", Some((9, 6).into()), up, Some(5))?;
+		gd.spawn_apple((10, 5).into(), None)?;
+		gd.grid = gd.build_grid();
+
+		assert_ne!(think(&gd), Some(Direction::Right));
+
+		Ok(())
+	}
+
+	/// Return game with a bot going right whose tail is at `coords`.
+	fn game(
+		difficulty: Difficulty,
+		coords: (i32, i32),
+	) -> crate::Result<GameData> {
+		let mut gd = GameData::new(Some((10, 10)), settings());
+		gd.add_bot(Bot::new("bot", difficulty), Some(coords.into()))?;
+		Ok(gd)
+	}
+
+	fn settings() -> Settings {
+		Settings {
+			apples_amount: 0,
+			snake_length: crate::snake::SnakeLength::Fixed(2),
+			..Default::default()
+		}
+	}
+
+	fn think(gd: &GameData) -> Option<Direction> {
+		gd.bots[0].think(gd, &mut rand::thread_rng())
+	}
+}
@@ -0,0 +1,677 @@
+//! Reinforcement learning environment over the game.
+//!
+//! [`Env`] works like a gym environment: [`reset`](Env::reset) starts a new
+//! game and returns observations of all agents, [`step`](Env::step) applies
+//! agents' actions, advances the game on one tick and returns new
+//! observations, rewards and whether the game is over. Agents are snakes driven
+//! by the caller, they may play against [`bots`](crate::bot::Bot).
+
+use crate::{
+	aux::{Coordinates, Direction, Topology, Turn},
+	bot::{Bot, Difficulty},
+	path,
+	snake::DeathCause,
+	GameData, Result, Settings,
+};
+use std::fmt;
+
+/// Environment configuration.
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+	/// Size of the game grid.
+	pub grid_size: (usize, usize),
+
+	/// Game settings. Maximum amount of snakes is always set to the amount of
+	/// agents and opponents.
+	pub settings: Settings,
+
+	/// Amount of agents driven by the caller.
+	pub agents: usize,
+
+	/// Difficulties of bots agents play against.
+	pub opponents: Vec<Difficulty>,
+
+	/// How to encode observations.
+	pub encoder: Encoder,
+
+	/// Maximum amount of ticks in one game.
+	pub max_ticks: u64,
+}
+
+impl EnvConfig {
+	/// Default maximum amount of ticks in one game.
+	pub const MAX_TICKS: u64 = 1000;
+}
+
+impl Default for EnvConfig {
+	fn default() -> Self {
+		Self {
+			grid_size: GameData::GRID_SIZE,
+			settings: Settings {
+				snake_direction: None,
+				..Default::default()
+			},
+			agents: 1,
+			opponents: vec![],
+			encoder: Default::default(),
+			max_ticks: Self::MAX_TICKS,
+		}
+	}
+}
+
+/// How observation of an agent is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Encoder {
+	/// Square window with the agent's leading part in the center, rotated so
+	/// that the agent always goes up on the square grid. Window isn't rotated
+	/// on the hexagonal grid. Has `[4, 2 * radius + 1, 2 * radius + 1]`
+	/// shape, channels are: own parts, parts of other snakes, apples, cells
+	/// out of grid bounds. The first row is the farthest one in front of the
+	/// agent.
+	Window {
+		/// Amount of cells visible in every direction from the leading part.
+		radius: usize,
+	},
+
+	/// Whole grid with `[4, height, width]` shape, channels are: own parts,
+	/// parts of other snakes, apples, own leading part. The first row is the
+	/// top one.
+	Board,
+
+	/// Vector of [`FEATURES`](Self::FEATURES) values: danger straight ahead,
+	/// to the left and to the right; whether the nearest apple is ahead,
+	/// behind, to the left and to the right; current direction as one-hot
+	/// in [`Direction::ALL`] order; length divided by amount of grid cells.
+	#[default]
+	Features,
+}
+
+impl Encoder {
+	/// Amount of values in the [`Features`](Self::Features) observation.
+	pub const FEATURES: usize = 8 + Direction::ALL.len();
+
+	/// Return shape of the observation on the grid of `grid_size`.
+	pub fn shape(&self, grid_size: (usize, usize)) -> Vec<usize> {
+		match *self {
+			Self::Window { radius } => vec![4, 2 * radius + 1, 2 * radius + 1],
+			Self::Board => vec![4, grid_size.1, grid_size.0],
+			Self::Features => vec![Self::FEATURES],
+		}
+	}
+}
+
+/// Encoded observation of an agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+	/// Dimensions of the observation.
+	pub shape: Vec<usize>,
+
+	/// Flattened observation in row-major order.
+	pub data: Vec<f32>,
+}
+
+impl Observation {
+	/// Return observation of `shape` filled with zeros.
+	fn zeros(shape: Vec<usize>) -> Self {
+		let len = shape.iter().product();
+		Self {
+			shape,
+			data: vec![0.0; len],
+		}
+	}
+}
+
+/// What happened with an agent during one step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+	/// How many parts agent's snake got.
+	pub grown: usize,
+
+	/// Cause of death if agent's snake died during the step.
+	pub death: Option<DeathCause>,
+
+	/// Whether agent's snake is the last one alive.
+	pub won: bool,
+}
+
+/// Function which turns agent's [`Transition`] into a reward.
+pub trait RewardFunction {
+	/// Return reward for the `transition`.
+	fn reward(&self, transition: &Transition) -> f32;
+}
+
+impl<F: Fn(&Transition) -> f32> RewardFunction for F {
+	fn reward(&self, transition: &Transition) -> f32 {
+		self(transition)
+	}
+}
+
+/// Default reward function with configurable weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+	/// Reward for every part agent's snake grows.
+	pub apple: f32,
+
+	/// Reward for dying.
+	pub death: f32,
+
+	/// Reward for every step agent's snake survives.
+	pub step: f32,
+
+	/// Reward for being the last snake alive.
+	pub win: f32,
+}
+
+impl Default for Rewards {
+	fn default() -> Self {
+		Self {
+			apple: 1.0,
+			death: -1.0,
+			step: -0.01,
+			win: 1.0,
+		}
+	}
+}
+
+impl RewardFunction for Rewards {
+	fn reward(&self, transition: &Transition) -> f32 {
+		let mut reward = self.apple * transition.grown as f32;
+		match transition.death {
+			Some(_) => reward += self.death,
+			None => reward += self.step,
+		}
+		if transition.won {
+			reward += self.win;
+		}
+		reward
+	}
+}
+
+/// Additional information about a step.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Info {
+	/// Game tick after the step.
+	pub tick: u64,
+
+	/// Lengths of agents' snakes, zero if a snake is dead.
+	pub lengths: Vec<usize>,
+
+	/// Causes of agents' deaths happened during the step.
+	pub deaths: Vec<Option<DeathCause>>,
+
+	/// Index of the agent which won or none if nobody did.
+	pub winner: Option<usize>,
+}
+
+/// Result of a [`step`](Env::step).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+	/// Observations of all agents.
+	pub observations: Vec<Observation>,
+
+	/// Rewards of all agents.
+	pub rewards: Vec<f32>,
+
+	/// Whether the game is over.
+	pub done: bool,
+
+	/// Additional information.
+	pub info: Info,
+}
+
+/// Gym-like environment.
+pub struct Env {
+	config: EnvConfig,
+	gamedata: GameData,
+	reward: Box<dyn RewardFunction>,
+	done: bool,
+}
+
+impl Env {
+	/// Return a new [`Env`] with [`default rewards`](Rewards). Call
+	/// [`reset`](Self::reset) to start a game.
+	pub fn new(config: EnvConfig) -> Self {
+		Self {
+			config,
+			gamedata: Default::default(),
+			reward: Box::new(Rewards::default()),
+			done: true,
+		}
+	}
+
+	/// Replace reward function.
+	pub fn set_reward_function(
+		&mut self,
+		reward: impl RewardFunction + 'static,
+	) {
+		self.reward = Box::new(reward);
+	}
+
+	/// Start a new game seeded with `seed` and return observations of all
+	/// agents.
+	pub fn reset(&mut self, seed: u64) -> Result<Vec<Observation>> {
+		let settings = Settings {
+			snakes_amount: self.config.agents + self.config.opponents.len(),
+			..self.config.settings.clone()
+		};
+		self.gamedata = GameData::new(Some(self.config.grid_size), settings);
+		self.gamedata.set_seed(seed);
+
+		for agent in 0..self.config.agents {
+			self.gamedata
+				.spawn_snake(agent_name(agent), None, None, None)?;
+		}
+		for (i, &difficulty) in self.config.opponents.iter().enumerate() {
+			let bot = Bot::new(format!("{} {}", difficulty, i + 1), difficulty);
+			self.gamedata.add_bot(bot, None)?;
+		}
+		self.gamedata.check_apples()?;
+		self.gamedata.grid = self.gamedata.build_grid();
+		self.done = false;
+
+		Ok(self.observations())
+	}
+
+	/// Apply `actions` of agents and advance the game on one tick. Action is a
+	/// direction agent's snake should go or none to keep going the same way.
+	/// Actions of dead agents are ignored.
+	pub fn step(&mut self, actions: &[Option<Direction>]) -> Result<Step> {
+		let agents = self.config.agents;
+		let snakes_before = self.gamedata.snakes();
+		let lengths_before = self.lengths();
+
+		for (agent, action) in actions.iter().enumerate().take(agents) {
+			if let (Some(direction), Ok(snake)) =
+				(action, self.gamedata.snake_mut(agent_name(agent)))
+			{
+				// Turning 180 degrees is the same as going straight.
+				let _ = snake.change_direction(*direction);
+			}
+		}
+
+		// Kill snakes and let them eat apples right after moving, so that
+		// rewards aren't delayed on one step.
+		let mut killed = self.gamedata.advance()?;
+		killed.extend(self.gamedata.kill_dead_snakes());
+		self.gamedata.check_apples()?;
+		self.gamedata.grid = self.gamedata.build_grid();
+
+		let mut info = Info {
+			tick: self.gamedata.tick(),
+			lengths: self.lengths(),
+			deaths: vec![None; agents],
+			winner: None,
+		};
+		for (snake, cause) in killed {
+			if let Some(agent) = agent_index(&snake.name(), agents) {
+				info.deaths[agent] = Some(cause);
+			}
+		}
+
+		let alive = self.gamedata.snakes();
+		if alive == 1 && snakes_before > 1 {
+			let name = &self.gamedata.snakes[0].name;
+			info.winner = agent_index(name, agents);
+		}
+
+		let rewards = (0..agents)
+			.map(|agent| {
+				if lengths_before[agent] == 0 {
+					return 0.0;
+				}
+				let death = info.deaths[agent].clone();
+				let grown = match death {
+					Some(_) => 0,
+					None => info.lengths[agent]
+						.saturating_sub(lengths_before[agent]),
+				};
+				self.reward.reward(&Transition {
+					grown,
+					death,
+					won: info.winner == Some(agent),
+				})
+			})
+			.collect();
+
+		let agents_alive = info.lengths.iter().any(|&length| length > 0);
+		self.done = !agents_alive
+			|| info.winner.is_some()
+			|| (alive <= 1 && snakes_before > 1)
+			|| info.tick >= self.config.max_ticks;
+
+		Ok(Step {
+			observations: self.observations(),
+			rewards,
+			done: self.done,
+			info,
+		})
+	}
+
+	/// Return true if the game is over and [`reset`](Self::reset) should be
+	/// called.
+	pub fn is_done(&self) -> bool {
+		self.done
+	}
+
+	/// Return underlying game data.
+	pub fn gamedata(&self) -> &GameData {
+		&self.gamedata
+	}
+
+	/// Return environment configuration.
+	pub fn config(&self) -> &EnvConfig {
+		&self.config
+	}
+
+	/// Return lengths of agents' snakes, zero if a snake is dead.
+	fn lengths(&self) -> Vec<usize> {
+		(0..self.config.agents)
+			.map(|agent| {
+				self.gamedata
+					.snake(agent_name(agent))
+					.map_or(0, |snake| snake.len())
+			})
+			.collect()
+	}
+
+	/// Return encoded observations of all agents.
+	fn observations(&self) -> Vec<Observation> {
+		(0..self.config.agents)
+			.map(|agent| self.observe(&agent_name(agent)))
+			.collect()
+	}
+
+	/// Return encoded observation of the agent with `name`.
+	fn observe(&self, name: &str) -> Observation {
+		let grid_size = self.gamedata.grid.size;
+		let mut observation =
+			Observation::zeros(self.config.encoder.shape(grid_size));
+		let snake = match self.gamedata.snake(name) {
+			Ok(snake) => snake,
+			Err(_) => return observation,
+		};
+		let head = match snake.head() {
+			Some(head) => head,
+			None => return observation,
+		};
+		let cell = |coords: Coordinates| self.cell(name, coords);
+		let topology = self.gamedata.grid.topology;
+
+		match self.config.encoder {
+			Encoder::Window { radius } => {
+				let n = 2 * radius + 1;
+				let (forward, right) = match topology {
+					Topology::Square => {
+						(snake.direction(), snake.direction().turn_right())
+					}
+					Topology::Hex => (Direction::Up, Direction::Right),
+				};
+				for row in 0..n {
+					for col in 0..n {
+						let coords = head
+							.moved(forward, radius as i32 - row as i32)
+							.moved(right, col as i32 - radius as i32);
+						let channel = match cell(coords) {
+							Cell::Own => 0,
+							Cell::Other => 1,
+							Cell::Apple => 2,
+							Cell::Wall => 3,
+							Cell::Empty => continue,
+						};
+						observation.data[(channel * n + row) * n + col] = 1.0;
+					}
+				}
+			}
+			Encoder::Board => {
+				let (w, h) = grid_size;
+				for row in 0..h {
+					for col in 0..w {
+						let coords =
+							Coordinates::new(col as i32 + 1, (h - row) as i32);
+						let channel = match cell(coords) {
+							Cell::Own => 0,
+							Cell::Other => 1,
+							Cell::Apple => 2,
+							_ => continue,
+						};
+						observation.data[(channel * h + row) * w + col] = 1.0;
+					}
+				}
+				let (col, row) = (head.x - 1, h as i32 - head.y);
+				if row >= 0 && col >= 0 && (row as usize) < h {
+					let index = (3 * h + row as usize) * w + col as usize;
+					if let Some(value) = observation.data.get_mut(index) {
+						*value = 1.0;
+					}
+				}
+			}
+			Encoder::Features => {
+				let forward = snake.direction();
+				let right = topology.turn(forward, Turn::Right);
+				let left = topology.turn(forward, Turn::Left);
+				let step = self.gamedata.settings.snake_step;
+				let danger = |direction: Direction| {
+					let coords = topology.moved(head, direction, step);
+					!path::is_passable(&self.gamedata.grid, coords)
+				};
+				let apple = self
+					.gamedata
+					.apples
+					.iter()
+					.map(|apple| apple.coords())
+					.min_by_key(|&apple| topology.distance(head, apple));
+				let toward = |direction: Direction| match apple {
+					Some(apple) => {
+						let d = apple - head;
+						let unit = topology.moved(head, direction, 1) - head;
+						d.x * unit.x + d.y * unit.y > 0
+					}
+					None => false,
+				};
+				let (w, h) = grid_size;
+				let values = [
+					danger(forward),
+					danger(left),
+					danger(right),
+					toward(forward),
+					toward(-forward),
+					toward(left),
+					toward(right),
+				]
+				.into_iter()
+				.chain(Direction::ALL.iter().map(|&d| d == forward));
+				for (i, value) in values.enumerate() {
+					observation.data[i] = value as u8 as f32;
+				}
+				observation.data[Encoder::FEATURES - 1] =
+					snake.len() as f32 / (w * h) as f32;
+			}
+		}
+
+		observation
+	}
+
+	/// Return what is located at `coords` from the point of view of the agent
+	/// with `name`.
+	fn cell(&self, name: &str, coords: Coordinates) -> Cell {
+		if !self.gamedata.grid.contains(coords) {
+			return Cell::Wall;
+		}
+		for snake in &self.gamedata.snakes {
+			if snake.parts.iter().any(|part| part.coords() == coords) {
+				return match snake.name == name {
+					true => Cell::Own,
+					false => Cell::Other,
+				};
+			}
+		}
+		if self
+			.gamedata
+			.apples
+			.iter()
+			.any(|apple| apple.coords() == coords)
+		{
+			return Cell::Apple;
+		}
+		Cell::Empty
+	}
+}
+
+impl fmt::Debug for Env {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Env")
+			.field("config", &self.config)
+			.field("gamedata", &self.gamedata)
+			.field("done", &self.done)
+			.finish()
+	}
+}
+
+/// What is located in a cell from the point of view of an agent.
+enum Cell {
+	Own,
+	Other,
+	Apple,
+	Wall,
+	Empty,
+}
+
+/// Return name of the agent's snake.
+fn agent_name(agent: usize) -> String {
+	format!("agent {}", agent + 1)
+}
+
+/// Return index of the agent with `name` or none if it's not an agent.
+fn agent_index(name: &str, agents: usize) -> Option<usize> {
+	(0..agents).find(|&agent| agent_name(agent) == name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::snake::SnakeLength;
+
+	#[test]
+	fn reset_and_step() -> Result<()> {
+		let mut env = Env::new(EnvConfig {
+			grid_size: (10, 10),
+			opponents: vec![Difficulty::Greedy],
+			..Default::default()
+		});
+		let observations = env.reset(7)?;
+
+		assert_eq!(observations.len(), 1);
+		assert_eq!(observations[0].shape, [Encoder::FEATURES]);
+
+		let step = env.step(&[None])?;
+		assert_eq!(step.info.tick, 1);
+		assert_eq!(step.rewards.len(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn same_seed_same_game() -> Result<()> {
+		let config = EnvConfig {
+			grid_size: (10, 10),
+			agents: 2,
+			encoder: Encoder::Board,
+			..Default::default()
+		};
+		let mut first = Env::new(config.clone());
+		let mut second = Env::new(config);
+
+		assert_eq!(first.reset(3)?, second.reset(3)?);
+		for _ in 0..5 {
+			let actions = [Some(Direction::Up), None];
+			assert_eq!(first.step(&actions)?, second.step(&actions)?);
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn wall_ends_game() -> Result<()> {
+		let mut env = Env::new(EnvConfig {
+			grid_size: (5, 5),
+			..Default::default()
+		});
+		env.reset(0)?;
+
+		let mut step = env.step(&[Some(Direction::Right)])?;
+		for _ in 0..10 {
+			if step.done {
+				break;
+			}
+			step = env.step(&[None])?;
+		}
+
+		assert!(step.done);
+		assert_eq!(step.info.deaths[0], Some(DeathCause::OutOfBounds));
+		assert!(step.rewards[0] < 0.0);
+		assert!(step.observations[0].data.iter().all(|&v| v == 0.0));
+
+		Ok(())
+	}
+
+	#[test]
+	fn apple_reward() -> Result<()> {
+		let mut env = Env::new(EnvConfig {
+			grid_size: (10, 10),
+			settings: Settings {
+				apples_amount: 0,
+				snake_length: SnakeLength::Fixed(1),
+				snake_increment_size: 1,
+				..Default::default()
+			},
+			..Default::default()
+		});
+		env.set_reward_function(|t: &Transition| t.grown as f32);
+		env.reset(0)?;
+
+		let head = env.gamedata.snake(agent_name(0))?.head().unwrap();
+		let target = if head.y < 10 {
+			Direction::Up
+		} else {
+			Direction::Down
+		};
+		env.gamedata.spawn_apple(head.moved(target, 1), None)?;
+
+		let step = env.step(&[Some(target)])?;
+		// Random apples may be spawned on the way too.
+		assert!(step.info.lengths[0] > 1);
+		assert_eq!(step.rewards, [(step.info.lengths[0] - 1) as f32]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn window_is_egocentric() -> Result<()> {
+		let mut env = Env::new(EnvConfig {
+			grid_size: (10, 10),
+			settings: Settings {
+				apples_amount: 0,
+				snake_length: SnakeLength::Fixed(1),
+				..Default::default()
+			},
+			encoder: Encoder::Window { radius: 1 },
+			..Default::default()
+		});
+		env.reset(0)?;
+
+		let snake = env.gamedata.snake_mut(agent_name(0))?;
+		snake.change_direction(Direction::Right)?;
+		let head = snake.head().unwrap();
+		env.gamedata
+			.spawn_apple(head.moved(Direction::Right, 1), None)?;
+
+		let observation = env.observe(&agent_name(0));
+		assert_eq!(observation.shape, [4, 3, 3]);
+		// Own leading part is in the center of the first channel and the
+		// apple is right in front of it in the third one.
+		assert_eq!(observation.data[4], 1.0);
+		assert_eq!(observation.data[2 * 9 + 1], 1.0);
+
+		Ok(())
+	}
+}
@@ -4,7 +4,7 @@ use crate::aux::*;
 use std::{error, fmt};
 
 /// Error type returned by crate's functions.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum GameError {
 	/// Snake with name specified in variant's argument not found.
 	SnakeNotFound(String),
@@ -26,6 +26,25 @@ pub enum GameError {
 	/// Snake with name specified in variant's argument and length greater than
 	/// one tries to turn 180 degrees.
 	ChangeDirectionToOpposite(String),
+
+	/// Snake with name specified in variant's first argument tries to go
+	/// towards direction not supported by the grid topology.
+	UnsupportedDirection(String, Direction),
+
+	/// Grid delta made since the tick specified in variant's second argument
+	/// is applied to the grid built on the tick specified in the first one.
+	DeltaBaseMismatch(u64, u64),
+
+	/// Grid or its update can't be converted from or to json.
+	Json(serde_json::Error),
+
+	/// Game can't be played with settings because of the reason specified in
+	/// variant's argument.
+	InvalidSettings(String),
+
+	/// Grid of the size specified in variant's argument has more than
+	/// [`Grid::MAX_CELLS`](crate::grid::Grid::MAX_CELLS) cells.
+	GridTooLarge((usize, usize)),
 }
 
 impl fmt::Display for GameError {
@@ -39,11 +58,31 @@ impl fmt::Display for GameError {
             Self::EmptySnake(name) => write!(f, "snake with {} name has no parts", name),
             Self::NonUniqueName(name) => write!(f, "snake with {} name already exists", name),
 			Self::ChangeDirectionToOpposite(name) => write!(f, "snake with {} name tries to turn 180 degrees", name),
+			Self::UnsupportedDirection(name, direction) => write!(f, "snake with {} name can't go {} on this grid", name, direction),
+			Self::DeltaBaseMismatch(tick, since) => write!(f,
+				"can't apply grid delta made since tick {} to the grid built on tick {}", since, tick),
+			Self::Json(_) => write!(f, "can't convert json"),
+			Self::InvalidSettings(reason) => write!(f, "invalid settings: {}", reason),
+			Self::GridTooLarge(size) => write!(f,
+				"grid of {}x{} size has more than {} cells", size.0, size.1, crate::grid::Grid::MAX_CELLS),
         }
 	}
 }
 
-impl error::Error for GameError {}
+impl error::Error for GameError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::Json(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<serde_json::Error> for GameError {
+	fn from(e: serde_json::Error) -> Self {
+		Self::Json(e)
+	}
+}
 
 /// Error returned if can't parse [`Direction`] from a string.
 #[derive(Debug, Clone)]
@@ -52,12 +91,38 @@ pub struct ParseDirectionError;
 impl fmt::Display for ParseDirectionError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f,
-"can't parse Direction because parsed string is not \"up\", \"down\", \"left\", \"right\" or \"random\"")
+"can't parse Direction because parsed string is not \"up\", \"down\", \"left\", \"right\", \"up_left\", \"up_right\", \"down_left\", \"down_right\" or \"random\"")
 	}
 }
 
 impl error::Error for ParseDirectionError {}
 
+/// Error returned if can't parse [`Turn`] from a string.
+#[derive(Debug, Clone)]
+pub struct ParseTurnError;
+
+impl fmt::Display for ParseTurnError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f,
+"can't parse Turn because parsed string is not \"left\" or \"right\"")
+	}
+}
+
+impl error::Error for ParseTurnError {}
+
+/// Error returned if can't parse [`Topology`] from a string.
+#[derive(Debug, Clone)]
+pub struct ParseTopologyError;
+
+impl fmt::Display for ParseTopologyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f,
+"can't parse Topology because parsed string is not \"square\" or \"hex\"")
+	}
+}
+
+impl error::Error for ParseTopologyError {}
+
 /// Error returned if can't parse [`SnakeLength`](crate::snake::SnakeLength) from a string.
 #[derive(Debug, Clone)]
 pub struct ParseSnakeLengthError;
@@ -70,3 +135,17 @@ impl fmt::Display for ParseSnakeLengthError {
 }
 
 impl error::Error for ParseSnakeLengthError {}
+
+/// Error returned if can't parse [`Difficulty`](crate::bot::Difficulty) from a
+/// string.
+#[derive(Debug, Clone)]
+pub struct ParseDifficultyError;
+
+impl fmt::Display for ParseDifficultyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f,
+"can't parse Difficulty because parsed string is not \"random\", \"greedy\" or \"survival\"")
+	}
+}
+
+impl error::Error for ParseDifficultyError {}
@@ -5,7 +5,8 @@
 //! from the zero point.
 
 use crate::{
-	aux::{Color, Coordinates},
+	aux::{Color, Coordinates, Topology},
+	error::GameError,
 	Result,
 };
 /// Game grid abstractions.
@@ -14,7 +15,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Abstraction enum with available kinds of game objects.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GameObject {
 	/// A part of a snake.
@@ -25,7 +26,7 @@ pub enum GameObject {
 }
 
 /// Struct which represents one unique point of the grid.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct GridPoint {
 	/// What kind of object is located in this point.
@@ -64,33 +65,115 @@ impl GridPoint {
 	}
 }
 
-/// Game grid. In other words, vector of the [`GridPoint`]s.
+/// Game grid. In other words, dense store of the [`GridPoint`]s indexed by
+/// their coordinates.
+///
+/// Every cell of the grid contains at most one point, so coordinates of the
+/// points are always unique. Grid is serialized as a vector of occupied points
+/// though, see [`RawGrid`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawGrid", into = "RawGrid")]
 pub struct Grid {
-	/// [`Grid`] data itself.
-	pub data: Vec<GridPoint>,
+	/// Grid cells ordered by rows from the bottom left corner.
+	cells: Vec<Option<GridPoint>>,
 
 	/// [`Grid`] size. All values here are inclusive, so if size is 50x50, then
 	/// (50, 50), (50, 49), (49, 50) are all valid points and parts of grid.
 	pub size: (usize, usize),
+
+	/// Number of the game tick this [`Grid`] was built on.
+	pub tick: u64,
+
+	/// Shape of the grid cells.
+	pub topology: Topology,
 }
 
 impl Grid {
 	/// Default size of the grid used with [`Default`](Self::default) trait
 	/// implementation.
 	pub const DEFAULT_SIZE: (usize, usize) = (50, 25);
+	/// Maximum amount of cells in the grid.
+	pub const MAX_CELLS: usize = 1 << 20;
 
-	/// Return a new [`Grid`].
+	/// Return a new [`Grid`] with square cells.
 	pub fn new(size: (usize, usize)) -> Self {
+		Self::with_topology(size, Topology::Square)
+	}
+
+	/// Return a new [`Grid`] with cells of specified `topology`.
+	pub fn with_topology(size: (usize, usize), topology: Topology) -> Self {
 		Self {
-			data: Vec::with_capacity(size.0 * size.1),
+			cells: vec![None; size.0 * size.1],
 			size,
+			tick: 0,
+			topology,
 		}
 	}
 
+	/// Return point located at `coords` if there's one.
+	pub fn get(&self, coords: Coordinates) -> Option<&GridPoint> {
+		self.index(coords).and_then(|i| self.cells[i].as_ref())
+	}
+
+	/// Put `point` into the cell at its coordinates and return point which was
+	/// there before. Points out of grid bounds are ignored.
+	pub fn insert(&mut self, point: GridPoint) -> Option<GridPoint> {
+		let index = self.index(point.coords())?;
+		self.cells[index].replace(point)
+	}
+
+	/// Remove point located at `coords` and return it.
+	pub fn remove(&mut self, coords: Coordinates) -> Option<GridPoint> {
+		let index = self.index(coords)?;
+		self.cells[index].take()
+	}
+
+	/// Return true if `coords` are framed by grid.
+	pub fn contains(&self, coords: Coordinates) -> bool {
+		self.index(coords).is_some()
+	}
+
+	/// Return iterator over coordinates of the cells adjacent to `coords` which
+	/// are framed by grid.
+	pub fn neighbors(
+		&self,
+		coords: Coordinates,
+	) -> impl Iterator<Item = Coordinates> + '_ {
+		self.topology
+			.directions()
+			.iter()
+			.map(move |&direction| self.topology.moved(coords, direction, 1))
+			.filter(|coords| self.contains(*coords))
+	}
+
+	/// Return iterator over occupied cells of the grid.
+	pub fn points(&self) -> impl Iterator<Item = &GridPoint> {
+		self.cells.iter().flatten()
+	}
+
+	/// Return vector of occupied points, it's what the grid is serialized to.
+	pub fn data(&self) -> Vec<GridPoint> {
+		self.points().copied().collect()
+	}
+
+	/// Return number of occupied cells.
+	pub fn len(&self) -> usize {
+		self.points().count()
+	}
+
+	/// Return true if there's no occupied cells.
+	pub fn is_empty(&self) -> bool {
+		self.points().next().is_none()
+	}
+
 	/// Generate random coordinates framed by grid.
 	pub fn random_coords(&self) -> Coordinates {
-		let mut rng = rand::thread_rng();
+		self.random_coords_with(&mut rand::thread_rng())
+	}
+
+	/// Generate random coordinates framed by grid using `rng` random number
+	/// generator.
+	pub fn random_coords_with(&self, rng: &mut impl Rng) -> Coordinates {
 		Coordinates::new(
 			rng.gen_range(1..=self.size.0) as i32,
 			rng.gen_range(1..=self.size.1) as i32,
@@ -106,6 +189,70 @@ impl Grid {
 	pub fn from_string<T: AsRef<str>>(string: T) -> Result<Self> {
 		Ok(serde_json::from_str(string.as_ref())?)
 	}
+
+	/// Return [`GridDelta`] which turns `older` grid into this one.
+	pub fn delta(&self, older: &Grid) -> GridDelta {
+		let mut delta = GridDelta {
+			since: older.tick,
+			tick: self.tick,
+			..Default::default()
+		};
+
+		if older.size != self.size || older.topology != self.topology {
+			delta.removed.extend(older.points());
+			delta.added.extend(self.points());
+			return delta;
+		}
+
+		for (old, new) in older.cells.iter().zip(&self.cells) {
+			if old != new {
+				delta.removed.extend(old);
+				delta.added.extend(new);
+			}
+		}
+
+		delta
+	}
+
+	/// Apply [`GridUpdate`] to the grid.
+	///
+	/// Full snapshot just replaces the grid. Delta may be applied only if it
+	/// was made since the tick this grid was built on.
+	pub fn apply(&mut self, update: GridUpdate) -> Result<()> {
+		match update {
+			GridUpdate::Full(grid) => *self = grid,
+			GridUpdate::Delta(delta) => {
+				if delta.since != self.tick {
+					return Err(GameError::DeltaBaseMismatch(
+						self.tick,
+						delta.since,
+					));
+				}
+				for point in &delta.removed {
+					if self.get(point.coords()) == Some(point) {
+						self.remove(point.coords());
+					}
+				}
+				for point in delta.added {
+					self.insert(point);
+				}
+				self.tick = delta.tick;
+			}
+		}
+		Ok(())
+	}
+
+	/// Return index of the cell at `coords` or none if they're out of bounds.
+	pub(crate) fn index(&self, coords: Coordinates) -> Option<usize> {
+		let (w, h) = (self.size.0 as i32, self.size.1 as i32);
+		let (x, y): (i32, i32) = coords.into();
+
+		if x < 1 || x > w || y < 1 || y > h {
+			None
+		} else {
+			Some(((y - 1) * w + x - 1) as usize)
+		}
+	}
 }
 
 impl Default for Grid {
@@ -114,9 +261,119 @@ impl Default for Grid {
 	}
 }
 
+/// Serialized shape of the [`Grid`]: vector of occupied points with grid size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawGrid {
+	/// Occupied points of the grid.
+	pub data: Vec<GridPoint>,
+
+	/// [`Grid`] size.
+	pub size: (usize, usize),
+
+	/// Number of the game tick grid was built on.
+	#[serde(default)]
+	pub tick: u64,
+
+	/// Shape of the grid cells.
+	#[serde(default)]
+	pub topology: Topology,
+}
+
+impl From<Grid> for RawGrid {
+	fn from(grid: Grid) -> Self {
+		Self {
+			data: grid.data(),
+			size: grid.size,
+			tick: grid.tick,
+			topology: grid.topology,
+		}
+	}
+}
+
+impl TryFrom<RawGrid> for Grid {
+	type Error = GameError;
+
+	/// Build the grid of the raw one. Return error if it has more than
+	/// [`MAX_CELLS`](Grid::MAX_CELLS) cells, so untrusted input can't make it
+	/// allocate too much.
+	fn try_from(raw: RawGrid) -> Result<Self> {
+		match raw.size.0.checked_mul(raw.size.1) {
+			Some(cells) if cells <= Grid::MAX_CELLS => (),
+			_ => return Err(GameError::GridTooLarge(raw.size)),
+		}
+		let mut grid = Grid::with_topology(raw.size, raw.topology);
+		grid.tick = raw.tick;
+		for point in raw.data {
+			grid.insert(point);
+		}
+		Ok(grid)
+	}
+}
+
+impl From<Grid> for Vec<GridPoint> {
+	fn from(grid: Grid) -> Self {
+		RawGrid::from(grid).data
+	}
+}
+
+/// Points which were added to and removed from the [`Grid`] between two ticks.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct GridDelta {
+	/// Tick of the grid this delta should be applied to.
+	pub since: u64,
+
+	/// Tick of the grid this delta results in.
+	pub tick: u64,
+
+	/// Points which appeared since the `since` tick.
+	pub added: Vec<GridPoint>,
+
+	/// Points which disappeared since the `since` tick.
+	pub removed: Vec<GridPoint>,
+}
+
+impl GridDelta {
+	/// Return true if nothing changed between ticks.
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty()
+	}
+}
+
+/// Update of the [`Grid`] sent to clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridUpdate {
+	/// Whole grid. Sent when the client is too far behind to apply a delta.
+	Full(Grid),
+
+	/// Changes since the tick the client already has.
+	Delta(GridDelta),
+}
+
+impl GridUpdate {
+	/// Return tick the grid will be on after applying this update.
+	pub fn tick(&self) -> u64 {
+		match self {
+			Self::Full(grid) => grid.tick,
+			Self::Delta(delta) => delta.tick,
+		}
+	}
+
+	/// Convert [`GridUpdate`] to binary json.
+	pub fn as_bytes(&self) -> Result<Vec<u8>> {
+		Ok(serde_json::to_string(self)?.as_bytes().to_vec())
+	}
+
+	/// Convert json string to [`GridUpdate`].
+	pub fn from_string<T: AsRef<str>>(string: T) -> Result<Self> {
+		Ok(serde_json::from_str(string.as_ref())?)
+	}
+}
+
 impl fmt::Display for Grid {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		for (i, point) in self.data.iter().enumerate() {
+		for (i, point) in self.points().enumerate() {
 			writeln!(
 				f,
 				"{:?}[{}] at {} with rgba{} color",
@@ -145,4 +402,140 @@ mod tests {
 				&& rc.y > 0 && rc.y <= size.1 as i32
 		);
 	}
+
+	#[test]
+	fn deserialize_size() -> Result<()> {
+		let grid = Grid::from_string(r#"{"data": [], "size": [20, 10]}"#)?;
+		assert_eq!(grid.size, (20, 10));
+
+		let sizes = [(usize::MAX, 2), (1 << 11, 1 << 11)];
+		for size in sizes {
+			let json = serde_json::json!({ "data": [], "size": size });
+			let error = serde_json::from_value::<Grid>(json).unwrap_err();
+			assert!(error.to_string().contains("cells"), "{}", error);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn delta() -> Result<()> {
+		let mut older = Grid::new((10, 10));
+		older.insert(snake_part((1, 1)));
+		older.insert(snake_part((2, 1)));
+		older.insert(apple((5, 5)));
+
+		let mut newer = Grid::new((10, 10));
+		newer.tick = 1;
+		newer.insert(snake_part((2, 1)));
+		newer.insert(snake_part((3, 1)));
+		newer.insert(apple((5, 5)));
+
+		let delta = newer.delta(&older);
+
+		assert_eq!(delta.since, 0);
+		assert_eq!(delta.tick, 1);
+		assert_eq!(delta.added, [snake_part((3, 1))]);
+		assert_eq!(delta.removed, [snake_part((1, 1))]);
+
+		older.apply(GridUpdate::Delta(delta))?;
+
+		assert_eq!(older.tick, 1);
+		assert_eq!(older.len(), 3);
+		for point in newer.points() {
+			assert_eq!(older.get(point.coords()), Some(point));
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn apply_delta_with_wrong_base() {
+		let mut grid = Grid::new((10, 10));
+		let delta = GridDelta {
+			since: 5,
+			tick: 6,
+			..Default::default()
+		};
+
+		grid.apply(GridUpdate::Delta(delta))
+			.expect_err("delta base mismatch error expected");
+	}
+
+	#[test]
+	fn insert_and_get() {
+		let mut grid = Grid::new((10, 5));
+
+		assert_eq!(grid.insert(apple((3, 4))), None);
+		assert_eq!(grid.insert(snake_part((3, 4))), Some(apple((3, 4))));
+		assert_eq!(grid.insert(apple((11, 4))), None);
+		assert_eq!(grid.insert(apple((0, 1))), None);
+
+		assert_eq!(grid.get((3, 4).into()), Some(&snake_part((3, 4))));
+		assert_eq!(grid.get((11, 4).into()), None);
+		assert_eq!(grid.len(), 1);
+
+		assert_eq!(grid.remove((3, 4).into()), Some(snake_part((3, 4))));
+		assert!(grid.is_empty());
+	}
+
+	#[test]
+	fn neighbors() {
+		let grid = Grid::new((10, 5));
+
+		let mut corner: Vec<(i32, i32)> =
+			grid.neighbors((1, 1).into()).map(Into::into).collect();
+		corner.sort_unstable();
+		assert_eq!(corner, [(1, 2), (2, 1)]);
+
+		assert_eq!(grid.neighbors((5, 3).into()).count(), 4);
+	}
+
+	#[test]
+	fn hex_neighbors() {
+		let grid = Grid::with_topology((10, 5), Topology::Hex);
+
+		// Row 3 isn't shifted, so its diagonal neighbors are on the left.
+		let mut odd: Vec<(i32, i32)> =
+			grid.neighbors((5, 3).into()).map(Into::into).collect();
+		odd.sort_unstable();
+		assert_eq!(odd, [(4, 2), (4, 3), (4, 4), (5, 2), (5, 4), (6, 3)]);
+
+		// Row 2 is shifted, so its diagonal neighbors are on the right.
+		let mut even: Vec<(i32, i32)> =
+			grid.neighbors((5, 2).into()).map(Into::into).collect();
+		even.sort_unstable();
+		assert_eq!(even, [(4, 2), (5, 1), (5, 3), (6, 1), (6, 2), (6, 3)]);
+
+		for neighbor in grid.neighbors((5, 2).into()) {
+			assert_eq!(grid.topology.distance((5, 2).into(), neighbor), 1);
+		}
+	}
+
+	#[test]
+	fn serialize_as_points() -> Result<()> {
+		let mut grid = Grid::new((10, 5));
+		grid.insert(apple((3, 4)));
+		grid.insert(snake_part((7, 2)));
+
+		let json: serde_json::Value =
+			serde_json::from_slice(&grid.as_bytes()?)?;
+		assert_eq!(json["data"].as_array().map(Vec::len), Some(2));
+		assert_eq!(json["size"], serde_json::json!([10, 5]));
+		assert_eq!(json["topology"], "square");
+
+		let parsed = Grid::from_string(json.to_string())?;
+		assert_eq!(parsed.get((3, 4).into()), Some(&apple((3, 4))));
+		assert_eq!(parsed.get((7, 2).into()), Some(&snake_part((7, 2))));
+		assert!(matches!(Grid::from_string("{"), Err(GameError::Json(_))));
+
+		Ok(())
+	}
+
+	fn snake_part(coords: (i32, i32)) -> GridPoint {
+		GridPoint::new(GameObject::SnakePart, coords.into(), Color::GREEN)
+	}
+
+	fn apple(coords: (i32, i32)) -> GridPoint {
+		GridPoint::new(GameObject::Apple, coords.into(), Color::RED)
+	}
 }
@@ -2,13 +2,16 @@
 
 pub mod apple;
 pub mod aux;
+pub mod bot;
+pub mod env;
 pub mod error;
 pub mod grid;
+pub mod path;
 pub mod snake;
 
 /// This is an alias for standart [`Result`](std::result::Result) type which
-/// represents failure.
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+/// represents failure with [`GameError`].
+pub type Result<T> = std::result::Result<T, GameError>;
 
 /// Common reexports in one place.
 pub mod prelude {
@@ -18,11 +21,14 @@ pub mod prelude {
 }
 
 use apple::Apple;
-use aux::{Color, Coordinates, Direction};
+use aux::{Color, Coordinates, Direction, Topology};
+use bot::Bot;
 use error::GameError;
-use grid::{GameObject, Grid, GridPoint};
+use grid::{GameObject, Grid, GridDelta, GridPoint, GridUpdate};
 use itertools::Itertools;
-use snake::{Snake, SnakeLength};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use snake::{DeathCause, Snake, SnakeLength};
+use std::collections::{BTreeMap, VecDeque};
 
 /// Game settings and data.
 #[derive(Debug, Clone, Default)]
@@ -31,6 +37,12 @@ pub struct GameData {
 	snakes: Vec<Snake>,
 	apples: Vec<Apple>,
 	settings: Settings,
+	bots: Vec<Bot>,
+	rng: GameRng,
+
+	/// Changes of the grid made on previous ticks, the oldest one is the
+	/// first. Used to make [`grid deltas`](grid::GridDelta) since older ticks.
+	history: VecDeque<GridDelta>,
 }
 
 impl GameData {
@@ -38,52 +50,81 @@ impl GameData {
 	/// function or in the [`Default`](Self::default) implementation.
 	pub const GRID_SIZE: (usize, usize) = Grid::DEFAULT_SIZE;
 
+	/// For how many previous ticks grid changes are kept to make deltas from.
+	/// If a client is further behind, it gets the whole grid.
+	pub const GRID_HISTORY: usize = 32;
+
 	/// Return a new [`GameData`].
 	pub fn new(grid_size: Option<(usize, usize)>, settings: Settings) -> Self {
 		Self {
-			grid: Grid::new(grid_size.unwrap_or(Self::GRID_SIZE)),
+			grid: Grid::with_topology(
+				grid_size.unwrap_or(Self::GRID_SIZE),
+				settings.topology,
+			),
 			snakes: Vec::with_capacity(settings.clone().snakes_amount),
 			apples: Vec::with_capacity(settings.clone().apples_amount),
 			settings,
+			bots: vec![],
+			rng: Default::default(),
+			history: VecDeque::with_capacity(Self::GRID_HISTORY),
 		}
 	}
 
-	/// Kill over-bounded or bumped snakes.
-	pub fn kill_dead_snakes(&mut self) {
-		let kill_queue = self.snakes_to_kill();
-		self.snakes
-			.retain(|snake| !kill_queue.contains(&snake.name));
+	/// Advance the game on one tick: kill dead snakes, let snakes eat apples,
+	/// drive bots and move everything. Return killed snakes with causes of
+	/// their deaths.
+	pub fn advance(&mut self) -> Result<Vec<(Snake, DeathCause)>> {
+		let killed = self.kill_dead_snakes();
+		self.check_apples()?;
+		self.drive_bots();
+		self.update_grid()?;
+		Ok(killed)
+	}
+
+	/// Kill over-bounded or bumped snakes. Return killed snakes with causes of
+	/// their deaths.
+	pub fn kill_dead_snakes(&mut self) -> Vec<(Snake, DeathCause)> {
+		let mut killed = vec![];
+		for (name, cause) in self.snakes_to_kill() {
+			if let Ok(snake) = self.kill_snake(name) {
+				killed.push((snake, cause));
+			}
+		}
+		killed
 	}
 
-	/// Return vector of snake names to be killed.
+	/// Return vector of snake names to be killed with causes of their deaths.
 	///
 	/// Snake will be in that vector if it's over-bounded or bumped with other
 	/// snake.
-	fn snakes_to_kill(&self) -> Vec<String> {
-		let mut queue = Vec::with_capacity(self.snakes());
+	fn snakes_to_kill(&self) -> Vec<(String, DeathCause)> {
+		let mut queue: Vec<(String, DeathCause)> =
+			Vec::with_capacity(self.snakes());
+		let queued = |queue: &Vec<(String, DeathCause)>, name: &String| {
+			queue.iter().any(|(queued, _)| queued == name)
+		};
 		for snake in &self.snakes {
-			if snake.parts_bumped().unwrap_or(true) || {
-				let (w, h) = (self.grid.size.0 as i32, self.grid.size.1 as i32);
-				let (x, y): (i32, i32) = snake.lp().unwrap().coords().into();
-
-				x < 0 || x > w || y < 1 || y > h
-			} {
-				queue.push(snake.name());
+			let in_bounds = match snake.head() {
+				Some(head) => self.grid.contains(head),
+				None => false,
+			};
+			if !in_bounds {
+				queue.push((snake.name(), DeathCause::OutOfBounds));
+			} else if snake.parts_bumped().unwrap_or(true) {
+				queue.push((snake.name(), DeathCause::BitItself));
 			}
 		}
 		for perm in self.snakes.iter().permutations(2) {
 			let (s1, s2) = (perm[0], perm[1]);
 			if s1.name == s2.name
-				|| queue.contains(&s1.name)
-				|| queue.contains(&s2.name)
+				|| queued(&queue, &s1.name)
+				|| queued(&queue, &s2.name)
 			{
 				continue;
 			}
 			let s1_lp_coords = s1.lp().unwrap().coords();
-			for s2_part in &s2.parts {
-				if s1_lp_coords == s2_part.coords() {
-					queue.push(s1.name());
-				}
+			if s2.parts.iter().any(|part| part.coords() == s1_lp_coords) {
+				queue.push((s1.name(), DeathCause::Bumped(s2.name())));
 			}
 		}
 		queue
@@ -91,26 +132,40 @@ impl GameData {
 
 	/// Refill [`game grid`](Grid) with a new data and move all snakes.
 	pub fn update_grid(&mut self) -> Result<()> {
-		let mut grid = Grid::new(self.grid.size);
+		for snake in &mut self.snakes {
+			snake.move_parts(self.settings.snake_step)?;
+		}
+		let mut grid = self.build_grid();
+		grid.tick = self.grid.tick + 1;
+		if self.history.len() == Self::GRID_HISTORY {
+			self.history.pop_front();
+		}
+		self.history.push_back(grid.delta(&self.grid));
+		self.grid = grid;
+		Ok(())
+	}
+
+	/// Return a new [`Grid`] filled with current apples and snakes.
+	fn build_grid(&self) -> Grid {
+		let mut grid = Grid::with_topology(self.grid.size, self.grid.topology);
+		grid.tick = self.grid.tick;
 		for apple in &self.apples {
-			grid.data.push(GridPoint::new(
+			grid.insert(GridPoint::new(
 				GameObject::Apple,
 				apple.coords(),
 				Color::RED,
-			))
+			));
 		}
-		for snake in &mut self.snakes {
-			snake.move_parts(self.settings.snake_step)?;
-			for snake_part in &mut snake.parts {
-				grid.data.push(GridPoint::new(
+		for snake in &self.snakes {
+			for snake_part in &snake.parts {
+				grid.insert(GridPoint::new(
 					GameObject::SnakePart,
 					snake_part.coords(),
 					snake_part.color(),
 				));
 			}
 		}
-		self.grid = grid;
-		Ok(())
+		grid
 	}
 
 	/// Add a new snake to the game. `coords` is a coordinates of leading part
@@ -124,26 +179,161 @@ impl GameData {
 		direction: Option<Option<Direction>>,
 		length: Option<usize>,
 	) -> crate::Result<()> {
-		let capacity = self.snakes.capacity();
 		let name = name.into();
-		if capacity != 0 && capacity == self.snakes.len() {
-			Err(Box::new(GameError::TooMuchSnakes(name)))
+		if self.is_bot(&name) {
+			Err(GameError::NonUniqueName(name))
+		} else {
+			self.push_snake(name, coords, direction, length)
+		}
+	}
+
+	/// Add a new snake to the game without checking whether its name is taken
+	/// by a bot. Arguments are the same as in [`spawn_snake`](Self::spawn_snake).
+	fn push_snake(
+		&mut self,
+		name: String,
+		coords: Option<Coordinates>,
+		direction: Option<Option<Direction>>,
+		length: Option<usize>,
+	) -> crate::Result<()> {
+		if self.is_full() {
+			Err(GameError::TooMuchSnakes(name))
 		} else if self.find_snake(name.clone()) {
-			Err(Box::new(GameError::NonUniqueName(name)))
+			Err(GameError::NonUniqueName(name))
 		} else {
+			let rng = &mut self.rng.0;
+			let topology = self.settings.topology;
 			let direction = direction
 				.unwrap_or(self.settings.snake_direction)
-				.unwrap_or_else(rand::random);
-			let length: usize = length
-				.unwrap_or_else(|| self.settings.snake_length.clone().into());
-			let coords = coords.unwrap_or_else(|| self.grid.random_coords());
+				.unwrap_or_else(|| *topology.directions().choose(rng).unwrap());
+			if !topology.supports(direction) {
+				return Err(GameError::UnsupportedDirection(name, direction));
+			}
+			let length: usize = length.unwrap_or_else(|| {
+				self.settings.snake_length.clone().get_with(rng)
+			});
+			let coords =
+				coords.unwrap_or_else(|| self.grid.random_coords_with(rng));
 
 			self.snakes
-				.push(Snake::new(name, coords, direction, length));
+				.push(Snake::new(name, coords, direction, length, topology));
 			Ok(())
 		}
 	}
 
+	/// Add a new bot to the game and spawn its snake. `coords` are used the
+	/// same way as in [`spawn_snake`](Self::spawn_snake).
+	pub fn add_bot(
+		&mut self,
+		bot: Bot,
+		coords: Option<Coordinates>,
+	) -> crate::Result<()> {
+		self.spawn_snake(bot.name(), coords, None, None)?;
+		self.bots.push(bot);
+		Ok(())
+	}
+
+	/// Replace bots of the game with the `bots`. Snakes of removed bots are
+	/// killed and new bots are spawned by [`respawn_bots`](Self::respawn_bots).
+	/// Return error if a new bot's name is taken by a snake of a client.
+	pub fn set_bots(&mut self, bots: Vec<Bot>) -> crate::Result<()> {
+		self.check_bots(&bots)?;
+		let removed: Vec<String> = self
+			.bots
+			.iter()
+			.map(Bot::name)
+			.filter(|name| bots.iter().all(|bot| bot.name() != *name))
+			.collect();
+		for name in removed {
+			// Snake of the bot may be already dead.
+			let _ = self.kill_snake(name);
+		}
+		self.bots = bots;
+		Ok(())
+	}
+
+	/// Return error if the `bots` can't be set, see [`set_bots`](Self::set_bots).
+	pub fn check_bots(&self, bots: &[Bot]) -> crate::Result<()> {
+		let taken = bots.iter().map(Bot::name).find(|name| {
+			!self.is_bot(name.as_str()) && self.find_snake(name.as_str())
+		});
+		match taken {
+			Some(name) => Err(GameError::NonUniqueName(name)),
+			None => Ok(()),
+		}
+	}
+
+	/// Change directions of bots' snakes the same way clients do it.
+	pub fn drive_bots(&mut self) {
+		let mut rng = self.rng.clone();
+		let directions: Vec<(String, Direction)> = self
+			.bots
+			.iter()
+			.filter_map(|bot| Some((bot.name(), bot.think(self, &mut rng.0)?)))
+			.collect();
+		self.rng = rng;
+		for (name, direction) in directions {
+			if let Ok(snake) = self.snake_mut(name) {
+				// Bot may want to turn 180 degrees only if it's trapped, so
+				// it's fine to ignore this error.
+				let _ = snake.change_direction(direction);
+			}
+		}
+	}
+
+	/// Spawn snakes of killed bots while there're free slots in the game.
+	pub fn respawn_bots(&mut self) -> Result<()> {
+		let dead: Vec<String> = self
+			.bots
+			.iter()
+			.map(Bot::name)
+			.filter(|name| !self.find_snake(name))
+			.collect();
+		for name in dead {
+			if self.is_full() {
+				break;
+			}
+			self.push_snake(name, None, None, None)?;
+		}
+		Ok(())
+	}
+
+	/// Kill snake of some bot to free a slot for a client. Return name of the
+	/// evicted bot or none if there's no bots' snakes in the game.
+	pub fn evict_bot(&mut self) -> Option<String> {
+		let name = self
+			.bots
+			.iter()
+			.map(Bot::name)
+			.find(|name| self.find_snake(name))?;
+		self.kill_snake(name.clone()).ok()?;
+		Some(name)
+	}
+
+	/// Return `true` if snake with such `name` is driven by a bot.
+	pub fn is_bot(&self, name: impl Into<String>) -> bool {
+		let name = name.into();
+		self.bots.iter().any(|bot| bot.name() == name)
+	}
+
+	/// Return bots added to the game.
+	pub fn bots(&self) -> &[Bot] {
+		&self.bots
+	}
+
+	/// Return `true` if maximum amount of snakes in the game is reached.
+	pub fn is_full(&self) -> bool {
+		let amount = self.settings.snakes_amount;
+		amount != 0 && self.snakes.len() >= amount
+	}
+
+	/// Return `true` if there're more snakes than allowed. It happens when
+	/// amount of snakes is decreased below amount of clients' ones.
+	pub fn is_overfull(&self) -> bool {
+		let amount = self.settings.snakes_amount;
+		amount != 0 && self.snakes.len() > amount
+	}
+
 	/// Remove snake from the game and return it.
 	pub fn kill_snake<T: Into<String>>(
 		&mut self,
@@ -152,7 +342,7 @@ impl GameData {
 		let name = name.into();
 		match self.snakes.iter().position(|s| s.name() == name) {
 			Some(index) => Ok(self.snakes.remove(index)),
-			None => Err(Box::new(GameError::SnakeNotFound(name))),
+			None => Err(GameError::SnakeNotFound(name)),
 		}
 	}
 
@@ -161,7 +351,7 @@ impl GameData {
 	/// which were eaten. Spawn new apples if there're not any apples in the
 	/// game.
 	pub fn check_apples(&mut self) -> Result<()> {
-		let mut delete_apples = Vec::with_capacity(self.apples.capacity());
+		let mut delete_apples = Vec::with_capacity(self.apples.len());
 
 		for snake in &mut self.snakes {
 			if let Some(lp) = snake.lp() {
@@ -184,8 +374,9 @@ impl GameData {
 			self.apples.swap_remove(index);
 		}
 
-		while self.apples.len() < self.apples.capacity() {
-			self.spawn_apple(self.grid.random_coords(), None)?;
+		while self.apples.len() < self.settings.apples_amount {
+			let coords = self.grid.random_coords_with(&mut self.rng.0);
+			self.spawn_apple(coords, None)?;
 		}
 
 		Ok(())
@@ -202,7 +393,7 @@ impl GameData {
 				return Ok(snake);
 			}
 		}
-		Err(Box::new(GameError::SnakeNotFound(name)))
+		Err(GameError::SnakeNotFound(name))
 	}
 
 	/// Return immutable reference to snake with specified name.
@@ -213,7 +404,7 @@ impl GameData {
 				return Ok(snake);
 			}
 		}
-		Err(Box::new(GameError::SnakeNotFound(name)))
+		Err(GameError::SnakeNotFound(name))
 	}
 
 	/// Return a vector of tuples with snake names and their lengths.
@@ -244,15 +435,25 @@ impl GameData {
 		coords: Coordinates,
 		color: Option<Color>,
 	) -> Result<()> {
-		let capacity = self.apples.capacity();
-		if capacity != 0 && capacity == self.apples.len() {
-			Err(Box::new(GameError::TooMuchApples(coords)))
+		let amount = self.settings.apples_amount;
+		if amount != 0 && self.apples.len() >= amount {
+			Err(GameError::TooMuchApples(coords))
 		} else {
 			self.apples.push(Apple::new(coords, color));
 			Ok(())
 		}
 	}
 
+	/// Return true if there's an apple or a snake part at `coords`.
+	pub fn is_occupied(&self, coords: Coordinates) -> bool {
+		self.apples.iter().any(|apple| apple.coords() == coords)
+			|| self
+				.snakes
+				.iter()
+				.flat_map(|snake| &snake.parts)
+				.any(|part| part.coords() == coords)
+	}
+
 	/// Return number of snakes in the game.
 	pub fn snakes(&self) -> usize {
 		self.snakes.len()
@@ -263,10 +464,105 @@ impl GameData {
 		self.grid.clone()
 	}
 
+	/// Return [`GridUpdate`] which brings grid built on `since` tick to the
+	/// current one. If there's no such grid in the history, return the whole
+	/// current grid.
+	pub fn grid_update(&self, since: u64) -> GridUpdate {
+		match self.grid_delta(since) {
+			Some(delta) => GridUpdate::Delta(delta),
+			None => GridUpdate::Full(self.grid()),
+		}
+	}
+
+	/// Return changes of the grid since the `since` tick or none if it's too
+	/// old to make them.
+	pub fn grid_delta(&self, since: u64) -> Option<GridDelta> {
+		if since == self.grid.tick {
+			return Some(self.grid.delta(&self.grid));
+		}
+		let start =
+			self.history.iter().position(|delta| delta.since == since)?;
+
+		// Undo the deltas from the newest one to find out what cells changed
+		// since the `since` tick contained. Cells are ordered by rows like in
+		// the grid.
+		let mut older = BTreeMap::new();
+		let key = |point: &GridPoint| (point.coords().y, point.coords().x);
+		for delta in self.history.range(start..).rev() {
+			for point in &delta.added {
+				older.insert(key(point), (point.coords(), None));
+			}
+			for point in &delta.removed {
+				older.insert(key(point), (point.coords(), Some(*point)));
+			}
+		}
+
+		let mut delta = GridDelta {
+			since,
+			tick: self.grid.tick,
+			..Default::default()
+		};
+		for (coords, old) in older.into_values() {
+			let new = self.grid.get(coords).copied();
+			if old != new {
+				delta.removed.extend(old);
+				delta.added.extend(new);
+			}
+		}
+		Some(delta)
+	}
+
+	/// Reseed random number generator of the game to make it reproducible.
+	pub fn set_seed(&mut self, seed: u64) {
+		self.rng = GameRng(StdRng::seed_from_u64(seed));
+	}
+
+	/// Return current game tick.
+	pub fn tick(&self) -> u64 {
+		self.grid.tick
+	}
+
 	/// Return game [`settings`](Settings).
 	pub fn settings(&self) -> Settings {
 		self.settings.clone()
 	}
+
+	/// Change settings of the running game. Excess apples are removed and
+	/// missing ones are spawned, bots' snakes are evicted while there're more
+	/// snakes than allowed, clients' snakes are kept, see
+	/// [`is_overfull`](Self::is_overfull). Other settings affect only new
+	/// snakes. Topology can't be changed because it would break the grid.
+	pub fn set_settings(&mut self, settings: Settings) -> Result<()> {
+		self.check_settings(&settings)?;
+		self.settings = settings;
+		self.apples.truncate(self.settings.apples_amount);
+		while self.is_overfull() && self.evict_bot().is_some() {}
+		self.check_apples()
+	}
+
+	/// Return error if the `settings` can't be set, see
+	/// [`set_settings`](Self::set_settings).
+	pub fn check_settings(&self, settings: &Settings) -> Result<()> {
+		settings.validate(self.grid.size)?;
+		if settings.topology != self.settings.topology {
+			return Err(GameError::InvalidSettings(format!(
+				"topology can't be changed from {} to {} in a running game",
+				self.settings.topology, settings.topology
+			)));
+		}
+		Ok(())
+	}
+}
+
+/// Random number generator of the game. It's seeded from the system entropy by
+/// default, see [`GameData::set_seed`] to make it reproducible.
+#[derive(Debug, Clone)]
+struct GameRng(StdRng);
+
+impl Default for GameRng {
+	fn default() -> Self {
+		Self(StdRng::from_entropy())
+	}
 }
 
 /// Game settings.
@@ -300,6 +596,9 @@ pub struct Settings {
 	/// Initial snake direction. If it's none, use random direction for every
 	/// new snake.
 	pub snake_direction: Option<Direction>,
+
+	/// Shape of the grid cells.
+	pub topology: Topology,
 }
 
 impl Settings {
@@ -321,6 +620,75 @@ impl Settings {
 	/// Default initial snake direction. If it's none, use random direction for
 	/// every new snake.
 	pub const SNAKE_DIRECTION: Option<Direction> = Some(Direction::Right);
+
+	/// Default shape of the grid cells.
+	pub const TOPOLOGY: Topology = Topology::Square;
+
+	/// Check that game with these settings and `grid_size` can be played.
+	pub fn validate(&self, grid_size: (usize, usize)) -> Result<()> {
+		let invalid = |reason: String| Err(GameError::InvalidSettings(reason));
+		let cells = match grid_size.0.checked_mul(grid_size.1) {
+			Some(cells) if cells <= Grid::MAX_CELLS => cells,
+			_ => {
+				return invalid(format!(
+					"grid size {}x{} is larger than {} cells",
+					grid_size.0,
+					grid_size.1,
+					Grid::MAX_CELLS
+				))
+			}
+		};
+
+		if cells == 0 {
+			return invalid(format!(
+				"grid size {}x{} has no cells",
+				grid_size.0, grid_size.1
+			));
+		}
+		if self.apples_amount > cells {
+			return invalid(format!(
+				"{} apples don't fit into the grid of {} cells",
+				self.apples_amount, cells
+			));
+		}
+		if self.snake_increment_size > cells {
+			return invalid(format!(
+				"snake increment size {} is larger than the grid of {} cells",
+				self.snake_increment_size, cells
+			));
+		}
+		if self.snake_step == 0 {
+			return invalid("snake step is zero".into());
+		}
+		match &self.snake_length {
+			SnakeLength::Fixed(0) => {
+				return invalid("snake length is zero".into());
+			}
+			SnakeLength::Random(range) if range.start == 0 => {
+				return invalid(format!(
+					"snake length range {:?} starts at zero",
+					range
+				));
+			}
+			SnakeLength::Random(range) if range.is_empty() => {
+				return invalid(format!(
+					"snake length range {:?} is empty",
+					range
+				));
+			}
+			_ => (),
+		}
+		if let Some(direction) = self.snake_direction {
+			if !self.topology.supports(direction) {
+				return invalid(format!(
+					"snake can't go {} on the {} grid",
+					direction, self.topology
+				));
+			}
+		}
+
+		Ok(())
+	}
 }
 
 impl Default for Settings {
@@ -332,6 +700,7 @@ impl Default for Settings {
 			snake_increment_size: Self::SNAKE_INCREMENT_SIZE,
 			snake_length: Self::SNAKE_LENGTH,
 			snake_direction: Self::SNAKE_DIRECTION,
+			topology: Self::TOPOLOGY,
 		}
 	}
 }
@@ -361,4 +730,225 @@ pub mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn death_causes() -> crate::Result<()> {
+		let mut gd = GameData::new(Some((20, 20)), Default::default());
+
+		gd.spawn_snake('1', Some((-1, -1).into()), None, Some(1))?;
+
+		gd.spawn_snake('2', Some((15, 5).into()), None, Some(5))?;
+		snake::bump_parts(gd.snake_mut('2')?)?;
+
+		gd.spawn_snake('3', Some((4, 6).into()), None, Some(1))?;
+		gd.spawn_snake('4', Some((3, 6).into()), None, Some(2))?;
+
+		let mut killed: Vec<(String, DeathCause)> = gd
+			.kill_dead_snakes()
+			.into_iter()
+			.map(|(snake, cause)| (snake.name(), cause))
+			.collect();
+		killed.sort_by_key(|(name, _)| name.clone());
+
+		assert_eq!(
+			killed,
+			[
+				("1".to_string(), DeathCause::OutOfBounds),
+				("2".to_string(), DeathCause::BitItself),
+				("3".to_string(), DeathCause::Bumped("4".to_string())),
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn seeded_games_are_equal() -> crate::Result<()> {
+		let play = || -> crate::Result<Vec<(String, usize)>> {
+			let mut gd = GameData::new(Some((10, 10)), Default::default());
+			gd.set_seed(42);
+			for i in 0..3 {
+				gd.add_bot(
+					Bot::new(i.to_string(), bot::Difficulty::Random),
+					None,
+				)?;
+			}
+			for _ in 0..50 {
+				gd.advance()?;
+			}
+			let mut scoreboard = gd.scoreboard();
+			scoreboard.push(("tick".into(), gd.tick() as usize));
+			scoreboard.extend(
+				gd.grid().points().map(|p| (format!("{}", p.coords()), 0)),
+			);
+			Ok(scoreboard)
+		};
+
+		assert_eq!(play()?, play()?);
+
+		Ok(())
+	}
+
+	#[test]
+	fn bots() -> crate::Result<()> {
+		let settings = Settings {
+			snakes_amount: 2,
+			..Default::default()
+		};
+		let mut gd = GameData::new(Some((20, 20)), settings);
+
+		gd.add_bot(Bot::new("bot", Default::default()), None)?;
+		gd.spawn_snake("bot", None, None, None)
+			.expect_err("bot's name should be taken");
+
+		gd.spawn_snake("player 1", None, None, None)?;
+		assert!(gd.is_full());
+
+		assert_eq!(gd.evict_bot(), Some("bot".to_string()));
+		gd.spawn_snake("player 2", None, None, None)?;
+
+		gd.kill_snake("player 1")?;
+		gd.respawn_bots()?;
+		assert!(gd.find_snake("bot"), "bot should be respawned");
+
+		Ok(())
+	}
+
+	#[test]
+	fn set_bots() -> crate::Result<()> {
+		let mut gd = GameData::new(Some((20, 20)), Default::default());
+		gd.add_bot(Bot::new("bot 1", Default::default()), None)?;
+		gd.add_bot(Bot::new("bot 2", Default::default()), None)?;
+		gd.spawn_snake("player", None, None, None)?;
+
+		gd.set_bots(vec![Bot::new("player", Default::default())])
+			.expect_err("client's name should be taken");
+		assert_eq!(gd.bots().len(), 2);
+
+		let bots = ["bot 2", "bot 3"]
+			.iter()
+			.map(|&name| Bot::new(name, Default::default()))
+			.collect();
+		gd.set_bots(bots)?;
+		assert!(!gd.find_snake("bot 1"), "removed bot should be killed");
+		assert!(gd.find_snake("bot 2"));
+		gd.respawn_bots()?;
+		assert!(gd.find_snake("bot 3"), "new bot should be spawned");
+		assert!(gd.find_snake("player"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn grid_update() -> crate::Result<()> {
+		let mut gd = GameData::new(Some((20, 20)), Default::default());
+		gd.spawn_snake('1', Some((5, 5).into()), None, Some(3))?;
+
+		let mut grid = gd.grid();
+		for _ in 0..3 {
+			gd.update_grid()?;
+		}
+
+		let update = gd.grid_update(grid.tick);
+		assert!(matches!(update, GridUpdate::Delta(_)));
+		grid.apply(update)?;
+		assert_eq!(grid.tick, gd.tick());
+		assert_eq!(grid.data(), gd.grid().data());
+
+		// Deltas since every kept tick bring the grid to the current one.
+		let mut grids = vec![gd.grid()];
+		gd.spawn_snake('2', Some((10, 10).into()), None, Some(4))?;
+		for _ in 0..5 {
+			gd.update_grid()?;
+			grids.push(gd.grid());
+		}
+		for mut grid in grids {
+			grid.apply(gd.grid_update(grid.tick))?;
+			assert_eq!(grid.data(), gd.grid().data());
+		}
+
+		for _ in 0..GameData::GRID_HISTORY + 1 {
+			gd.update_grid()?;
+		}
+
+		let update = gd.grid_update(grid.tick);
+		assert!(matches!(update, GridUpdate::Full(_)));
+
+		Ok(())
+	}
+
+	#[test]
+	fn validate_settings() {
+		let size = GameData::GRID_SIZE;
+		assert!(Settings::default().validate(size).is_ok());
+		assert!(Settings::default().validate((0, 10)).is_err());
+		assert!(Settings::default().validate((usize::MAX, 2)).is_err());
+		assert!(Settings::default().validate((1 << 11, 1 << 11)).is_err());
+		let unlimited = Settings {
+			snakes_amount: 0,
+			..Default::default()
+		};
+		assert!(unlimited.validate(size).is_ok());
+
+		let invalid = [
+			Settings {
+				snake_increment_size: size.0 * size.1 + 1,
+				..Default::default()
+			},
+			Settings {
+				snake_length: SnakeLength::Random(0..5),
+				..Default::default()
+			},
+			Settings {
+				snake_length: SnakeLength::Fixed(0),
+				..Default::default()
+			},
+			Settings {
+				snake_direction: Some(Direction::Up),
+				topology: Topology::Hex,
+				..Default::default()
+			},
+		];
+		for settings in invalid {
+			assert!(matches!(
+				settings.validate(size),
+				Err(GameError::InvalidSettings(_))
+			));
+		}
+	}
+
+	#[test]
+	fn set_settings() -> crate::Result<()> {
+		let mut gd = GameData::new(Some((20, 20)), Settings::default());
+		gd.add_bot(Bot::new("bot 1", Default::default()), None)?;
+		gd.add_bot(Bot::new("bot 2", Default::default()), None)?;
+		gd.check_apples()?;
+		assert_eq!(gd.apples.len(), Settings::APPLES_AMOUNT);
+
+		let settings = Settings {
+			apples_amount: 5,
+			..gd.settings()
+		};
+		gd.set_settings(settings.clone())?;
+		assert_eq!(gd.apples.len(), 5);
+
+		gd.set_settings(Settings {
+			apples_amount: 2,
+			snakes_amount: 1,
+			..settings
+		})?;
+		assert_eq!(gd.apples.len(), 2);
+		assert_eq!(gd.snakes(), 1);
+		assert!(gd.is_full());
+
+		let hex = Settings {
+			topology: Topology::Hex,
+			snake_direction: None,
+			..gd.settings()
+		};
+		assert!(gd.set_settings(hex).is_err());
+		assert_eq!(gd.settings().apples_amount, 2);
+
+		Ok(())
+	}
 }
@@ -0,0 +1,296 @@
+//! Pathfinding and spatial analysis on the [`Grid`].
+//!
+//! Snake can't go through parts of snakes (including its own ones) and can't
+//! leave the grid. Everything else, including apples, is passable.
+
+use crate::{
+	aux::{Coordinates, Direction},
+	grid::{GameObject, Grid},
+	snake::Snake,
+};
+use std::{
+	cmp::Reverse,
+	collections::{BinaryHeap, HashMap, VecDeque},
+};
+
+/// Return true if snake can go to `coords` without dying.
+pub fn is_passable(grid: &Grid, coords: Coordinates) -> bool {
+	grid.contains(coords)
+		&& !matches!(
+			grid.get(coords).map(|point| point.object_kind),
+			Some(GameObject::SnakePart)
+		)
+}
+
+/// Return manhattan distance between two points. See
+/// [`Topology::distance`](crate::aux::Topology::distance) to get amount of steps
+/// between cells on any grid.
+pub fn manhattan(a: Coordinates, b: Coordinates) -> usize {
+	let d = a - b;
+	(d.x.unsigned_abs() + d.y.unsigned_abs()) as usize
+}
+
+/// Find the shortest path from `from` to `to` using A* algorithm. Returned
+/// path doesn't contain `from` point, but contains `to` one, so it's empty if
+/// they're equal. `from` point itself doesn't have to be passable, so it may
+/// be a head of a snake.
+pub fn shortest_path(
+	grid: &Grid,
+	from: Coordinates,
+	to: Coordinates,
+) -> Option<Vec<Coordinates>> {
+	if from == to {
+		return Some(vec![]);
+	}
+	if !is_passable(grid, to) {
+		return None;
+	}
+
+	let mut open = BinaryHeap::new();
+	let mut came_from: HashMap<Coordinates, Coordinates> = HashMap::new();
+	let mut cost: HashMap<Coordinates, usize> = HashMap::new();
+
+	cost.insert(from, 0);
+	let distance = |a, b| grid.topology.distance(a, b);
+	open.push(Reverse((distance(from, to), 0, from.x, from.y)));
+
+	while let Some(Reverse((_, g, x, y))) = open.pop() {
+		let current = Coordinates::new(x, y);
+		if current == to {
+			let mut path = vec![current];
+			let mut current = current;
+			while let Some(&previous) = came_from.get(&current) {
+				if previous == from {
+					break;
+				}
+				path.push(previous);
+				current = previous;
+			}
+			path.reverse();
+			return Some(path);
+		}
+		if cost.get(&current).is_some_and(|&c| c < g) {
+			continue;
+		}
+		for next in grid.neighbors(current) {
+			if !is_passable(grid, next) {
+				continue;
+			}
+			let next_cost = g + 1;
+			if next_cost < cost.get(&next).copied().unwrap_or(usize::MAX) {
+				cost.insert(next, next_cost);
+				came_from.insert(next, current);
+				open.push(Reverse((
+					next_cost + distance(next, to),
+					next_cost,
+					next.x,
+					next.y,
+				)));
+			}
+		}
+	}
+
+	None
+}
+
+/// Return true if snake can get from `from` to `to`.
+pub fn reachable(grid: &Grid, from: Coordinates, to: Coordinates) -> bool {
+	shortest_path(grid, from, to).is_some()
+}
+
+/// Count passable cells reachable from `from` including it.
+pub fn flood_fill(grid: &Grid, from: Coordinates) -> usize {
+	let mut visited = Visited::new(grid);
+	let mut stack = vec![from];
+	let mut area = 0;
+
+	while let Some(coords) = stack.pop() {
+		if !is_passable(grid, coords) || !visited.insert(coords) {
+			continue;
+		}
+		area += 1;
+		stack.extend(grid.neighbors(coords));
+	}
+
+	area
+}
+
+/// Find the nearest apple snake can get to from `from`. Return its
+/// coordinates and amount of steps to it.
+pub fn nearest_apple(
+	grid: &Grid,
+	from: Coordinates,
+) -> Option<(Coordinates, usize)> {
+	let mut visited = Visited::new(grid);
+	let mut queue = VecDeque::from([(from, 0)]);
+	visited.insert(from);
+
+	while let Some((coords, distance)) = queue.pop_front() {
+		if let Some(GameObject::Apple) =
+			grid.get(coords).map(|point| point.object_kind)
+		{
+			return Some((coords, distance));
+		}
+		for next in grid.neighbors(coords) {
+			if is_passable(grid, next) && visited.insert(next) {
+				queue.push_back((next, distance + 1));
+			}
+		}
+	}
+
+	None
+}
+
+/// Return directions the `snake` moving on `step` cells per tick can go on
+/// the next tick without dying and coordinates its leading part will get.
+///
+/// Snake can't turn 180 degrees if it's longer than one part, so opposite
+/// direction is never returned for such snakes.
+pub fn safe_moves(
+	grid: &Grid,
+	snake: &Snake,
+	step: i32,
+) -> Vec<(Direction, Coordinates)> {
+	let head = match snake.head() {
+		Some(head) => head,
+		None => return vec![],
+	};
+	let topology = grid.topology;
+
+	topology
+		.directions()
+		.iter()
+		.copied()
+		.filter(|&direction| snake.len() == 1 || direction != -snake.heading())
+		.map(|direction| (direction, topology.moved(head, direction, step)))
+		.filter(|&(_, coords)| is_passable(grid, coords))
+		.collect()
+}
+
+/// Set of visited cells of the grid.
+struct Visited<'a> {
+	grid: &'a Grid,
+	cells: Vec<bool>,
+}
+
+impl<'a> Visited<'a> {
+	/// Return empty set of visited cells of the `grid`.
+	fn new(grid: &'a Grid) -> Self {
+		Self {
+			grid,
+			cells: vec![false; grid.size.0 * grid.size.1],
+		}
+	}
+
+	/// Mark cell at `coords` as visited. Return false if it's already visited
+	/// or if it's out of grid bounds.
+	fn insert(&mut self, coords: Coordinates) -> bool {
+		match self.grid.index(coords) {
+			Some(index) if !self.cells[index] => {
+				self.cells[index] = true;
+				true
+			}
+			_ => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{aux::Color, grid::GridPoint, GameData, Result, Settings};
+
+	#[test]
+	fn shortest_path_around_wall() {
+		// Vertical wall at x = 3 with a gap at y = 5.
+		let grid = grid_with_wall((3, 1), 4);
+		let path = shortest_path(&grid, (1, 1).into(), (5, 1).into())
+			.expect("path should exist");
+
+		assert_eq!(path.len(), 12);
+		assert_eq!(path.last(), Some(&(5, 1).into()));
+		assert!(path.contains(&(3, 5).into()));
+		for coords in &path {
+			assert!(is_passable(&grid, *coords));
+		}
+
+		// The wall's part may be a head of a snake which is already there.
+		let path = shortest_path(&grid, (3, 1).into(), (3, 1).into());
+		assert_eq!(path, Some(vec![]));
+	}
+
+	#[test]
+	fn unreachable() {
+		// Vertical wall at x = 3 across the whole grid.
+		let grid = grid_with_wall((3, 1), 5);
+
+		assert!(!reachable(&grid, (1, 1).into(), (5, 1).into()));
+		assert!(reachable(&grid, (1, 1).into(), (2, 5).into()));
+		assert_eq!(shortest_path(&grid, (1, 1).into(), (3, 3).into()), None);
+	}
+
+	#[test]
+	fn flood_fill_area() {
+		let grid = grid_with_wall((3, 1), 5);
+
+		assert_eq!(flood_fill(&grid, (1, 1).into()), 10);
+		assert_eq!(flood_fill(&grid, (4, 1).into()), 10);
+		assert_eq!(flood_fill(&grid, (3, 1).into()), 0);
+	}
+
+	#[test]
+	fn nearest_apple_distance() {
+		let mut grid = grid_with_wall((3, 1), 4);
+		grid.insert(GridPoint::new(
+			GameObject::Apple,
+			(4, 1).into(),
+			Color::RED,
+		));
+		grid.insert(GridPoint::new(
+			GameObject::Apple,
+			(1, 4).into(),
+			Color::RED,
+		));
+
+		assert_eq!(
+			nearest_apple(&grid, (1, 1).into()),
+			Some(((1, 4).into(), 3))
+		);
+		assert_eq!(
+			nearest_apple(&grid, (5, 1).into()),
+			Some(((4, 1).into(), 1))
+		);
+	}
+
+	#[test]
+	fn safe_moves_near_bounds() -> Result<()> {
+		let mut gd = GameData::new(Some((5, 5)), Settings::default());
+		gd.spawn_snake("snake", Some((4, 1).into()), None, Some(2))?;
+		gd.grid = gd.build_grid();
+
+		let step = gd.settings.snake_step;
+		let moves: Vec<Direction> =
+			safe_moves(&gd.grid, gd.snake("snake")?, step)
+				.into_iter()
+				.map(|(direction, _)| direction)
+				.collect();
+
+		assert_eq!(moves, [Direction::Up]);
+
+		Ok(())
+	}
+
+	/// Return 5x5 grid with a vertical wall of snake parts going up from
+	/// `from` point.
+	fn grid_with_wall(from: (i32, i32), length: i32) -> Grid {
+		let mut grid = Grid::new((5, 5));
+		for i in 0..length {
+			grid.insert(GridPoint::new(
+				GameObject::SnakePart,
+				(from.0, from.1 + i).into(),
+				Color::GREEN,
+			));
+		}
+		grid
+	}
+}
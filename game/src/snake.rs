@@ -14,38 +14,34 @@ pub struct Snake {
 
 	/// Direction of snake's leading part.
 	pub(crate) direction: Direction,
+
+	/// Direction snake has moved in last time, it's the direction if none.
+	#[serde(default)]
+	pub(crate) heading: Option<Direction>,
+
+	/// Topology of the grid snake goes on.
+	#[serde(default)]
+	pub(crate) topology: Topology,
 }
 
 impl Snake {
 	/// Return [`Snake`] with specified name, initial leading part location,
-	/// direction and length (amount of parts).
+	/// direction and length (amount of parts) going on the grid with specified
+	/// `topology`.
 	pub(crate) fn new<T: Into<String>>(
 		name: T,
 		coordinates: Coordinates,
 		direction: Direction,
 		length: usize,
+		topology: Topology,
 	) -> Self {
 		Self {
 			name: name.into(),
 			parts: {
 				let mut v = vec![];
 				for i in 0..length {
-					let offset = i as i32;
-					let part_coords = match direction {
-						Direction::Right => {
-							(coordinates.x + offset, coordinates.y)
-						}
-						Direction::Left => {
-							(coordinates.x - offset, coordinates.y)
-						}
-						Direction::Up => {
-							(coordinates.x, coordinates.y + offset)
-						}
-						Direction::Down => {
-							(coordinates.x, coordinates.y - offset)
-						}
-					}
-					.into();
+					let part_coords =
+						topology.moved(coordinates, direction, i as i32);
 
 					let part_color = if i == length - 1 {
 						Color::new(0, 200, 0, 255)
@@ -58,45 +54,48 @@ impl Snake {
 				v
 			},
 			direction,
+			heading: Some(direction),
+			topology,
 		}
 	}
 
 	/// Move snake's leading part relatively to current direction on `step`
 	/// points.
 	fn lp_move(&mut self, step: i32) -> Result<()> {
-		let direction = self.direction;
+		let (direction, topology) = (self.direction, self.topology);
 		let lp = match self.lp_mut() {
 			Some(lp) => lp,
-			None => {
-				return Err(Box::new(GameError::EmptySnake(self.name.clone())))
-			}
+			None => return Err(GameError::EmptySnake(self.name.clone())),
 		};
-		match direction {
-			Direction::Up => lp.mv((0, step)),
-			Direction::Down => lp.mv((0, -step)),
-			Direction::Left => lp.mv((-step, 0)),
-			Direction::Right => lp.mv((step, 0)),
-		}
+		let coords = topology.moved(lp.coords(), direction, step);
+		lp.mv(coords - lp.coords());
 		Ok(())
 	}
 
-	/// Change snake's leading part direction.
+	/// Change snake's leading part direction. Snake can't turn back to the
+	/// direction opposite to its [`heading`](Self::heading).
 	pub fn change_direction(&mut self, direction: Direction) -> Result<()> {
 		match self.is_empty() {
 			false => {
-				if self.len() > 1 && self.direction == -direction {
-					Err(Box::new(GameError::ChangeDirectionToOpposite(
-						self.name(),
-					)))
+				if !self.topology.supports(direction) {
+					Err(GameError::UnsupportedDirection(self.name(), direction))
+				} else if self.len() > 1 && self.heading() == -direction {
+					Err(GameError::ChangeDirectionToOpposite(self.name()))
 				} else {
 					self.direction = direction;
 					Ok(())
 				}
 			}
-			true => Err(Box::new(GameError::EmptySnake(self.name()))),
+			true => Err(GameError::EmptySnake(self.name())),
 		}
 	}
 
+	/// Turn snake's leading part relatively to its [`heading`](Self::heading),
+	/// so several turns before the next move don't add up.
+	pub fn turn(&mut self, turn: Turn) -> Result<()> {
+		self.change_direction(self.topology.turn(self.heading(), turn))
+	}
+
 	/// Relatively move all parts of the snake on `step` steps depending on its
 	/// leading part direction.
 	pub(crate) fn move_parts(&mut self, step: i32) -> Result<()> {
@@ -110,6 +109,7 @@ impl Snake {
 			parts[i].set_coords(coords.unwrap());
 		}
 		self.lp_move(step)?;
+		self.heading = Some(self.direction);
 
 		Ok(())
 	}
@@ -120,7 +120,7 @@ impl Snake {
 	pub(crate) fn parts_bumped(&self) -> Result<bool> {
 		let lp = self.lp();
 		if lp.is_none() {
-			return Err(Box::new(GameError::EmptySnake(self.name())));
+			return Err(GameError::EmptySnake(self.name()));
 		}
 		let lp = lp.unwrap();
 		for part in self.pwl() {
@@ -169,7 +169,7 @@ impl Snake {
 	pub(crate) fn insert_part(&mut self, color: Option<Color>) -> Result<()> {
 		let tail_part = match self.parts.first() {
 			Some(part) => part.clone(),
-			None => return Err(Box::new(GameError::EmptySnake(self.name()))),
+			None => return Err(GameError::EmptySnake(self.name())),
 		};
 		let color = match color {
 			Some(color) => color,
@@ -192,6 +192,21 @@ impl Snake {
 		self.len() == 0
 	}
 
+	/// Return coordinates of the snake leading part.
+	pub fn head(&self) -> Option<Coordinates> {
+		self.lp().map(SnakePart::coords)
+	}
+
+	/// Return direction of the snake leading part.
+	pub fn direction(&self) -> Direction {
+		self.direction
+	}
+
+	/// Return direction the snake has moved in last time.
+	pub fn heading(&self) -> Direction {
+		self.heading.unwrap_or(self.direction)
+	}
+
 	/// Return immutable reference of the snake leading part.
 	pub(crate) fn lp(&self) -> Option<&SnakePart> {
 		self.parts.last()
@@ -210,7 +225,7 @@ impl Snake {
 	}
 
 	/// Return cloned snake name.
-	pub(crate) fn name(&self) -> String {
+	pub fn name(&self) -> String {
 		self.name.clone()
 	}
 }
@@ -227,6 +242,30 @@ pub(crate) fn bump_parts(snake: &mut Snake) -> Result<()> {
 	Ok(())
 }
 
+/// Reason why snake was killed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeathCause {
+	/// Snake's leading part left the grid.
+	OutOfBounds,
+
+	/// Snake's leading part bumped into its own part.
+	BitItself,
+
+	/// Snake's leading part bumped into a part of a snake with name specified
+	/// in variant's argument.
+	Bumped(String),
+}
+
+impl fmt::Display for DeathCause {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::OutOfBounds => write!(f, "left the grid"),
+			Self::BitItself => write!(f, "bit itself"),
+			Self::Bumped(name) => write!(f, "bumped into {}", name),
+		}
+	}
+}
+
 /// Snake initial length abstraction.
 #[derive(Debug, Clone)]
 pub enum SnakeLength {
@@ -239,8 +278,13 @@ pub enum SnakeLength {
 
 impl SnakeLength {
 	pub fn get(self) -> usize {
+		self.get_with(&mut rand::thread_rng())
+	}
+
+	/// Return length using `rng` random number generator if it's random.
+	pub fn get_with(self, rng: &mut impl Rng) -> usize {
 		match self {
-			Self::Random(range) => rand::thread_rng().gen_range(range),
+			Self::Random(range) => rng.gen_range(range),
 			Self::Fixed(number) => number,
 		}
 	}
@@ -268,9 +312,9 @@ impl From<SnakeLength> for usize {
 }
 
 impl FromStr for SnakeLength {
-	type Err = Box<dyn std::error::Error>;
+	type Err = ParseSnakeLengthError;
 
-	fn from_str(s: &str) -> Result<Self> {
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
 		if let Ok(n) = s.parse::<usize>() {
 			Ok(Self::Fixed(n))
 		} else {
@@ -282,17 +326,21 @@ impl FromStr for SnakeLength {
 				if i == 0 && token.parse::<usize>().is_ok() {
 					start = token.parse::<usize>().unwrap();
 				} else if i == 1 {
-					if token.starts_with('=') {
-						inclusive = true;
-						end = token.get(1..).unwrap().parse::<usize>()?;
-					} else {
-						end = token.parse::<usize>()?;
-					}
+					let token = match token.strip_prefix('=') {
+						Some(token) => {
+							inclusive = true;
+							token
+						}
+						None => token,
+					};
+					end = token
+						.parse::<usize>()
+						.map_err(|_| ParseSnakeLengthError)?;
 				}
 			}
 
 			if end == 0 || end < start {
-				return Err(Box::new(ParseSnakeLengthError));
+				return Err(ParseSnakeLengthError);
 			}
 
 			match inclusive {
@@ -360,7 +408,13 @@ mod tests {
 
 		#[test]
 		fn new() {
-			let snake = Snake::new("snake", (0, 0).into(), Direction::Right, 5);
+			let snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Right,
+				5,
+				Topology::Square,
+			);
 
 			assert_eq!(snake.name(), "snake".to_string());
 			assert_eq!(snake.direction, Direction::Right);
@@ -369,7 +423,13 @@ mod tests {
 
 			assert_eq!(part_coords, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]);
 
-			let snake = Snake::new("snake", (0, 0).into(), Direction::Left, 5);
+			let snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Left,
+				5,
+				Topology::Square,
+			);
 			let part_coords = parts_into_tuple_coords(&snake.parts);
 
 			assert_eq!(
@@ -377,12 +437,24 @@ mod tests {
 				[(0, 0), (-1, 0), (-2, 0), (-3, 0), (-4, 0)]
 			);
 
-			let snake = Snake::new("snake", (0, 0).into(), Direction::Up, 5);
+			let snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Up,
+				5,
+				Topology::Square,
+			);
 			let part_coords = parts_into_tuple_coords(&snake.parts);
 
 			assert_eq!(part_coords, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]);
 
-			let snake = Snake::new("snake", (0, 0).into(), Direction::Down, 5);
+			let snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Down,
+				5,
+				Topology::Square,
+			);
 			let part_coords = parts_into_tuple_coords(&snake.parts);
 
 			assert_eq!(
@@ -393,8 +465,13 @@ mod tests {
 
 		#[test]
 		fn lp_move_expect_zero_length_error() {
-			let mut snake =
-				Snake::new("snake", (0, 0).into(), Direction::default(), 0);
+			let mut snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::default(),
+				0,
+				Topology::Square,
+			);
 			snake
 				.lp_move(1)
 				.expect_err("snake zero length error expected");
@@ -402,14 +479,24 @@ mod tests {
 
 		#[test]
 		fn lp_move() -> Result<()> {
-			let mut snake =
-				Snake::new("snake", (0, 0).into(), Direction::Right, 5);
+			let mut snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Right,
+				5,
+				Topology::Square,
+			);
 			snake.lp_move(1)?;
 			let part_coords = parts_into_tuple_coords(&snake.parts);
 			assert_eq!(part_coords, [(0, 0), (1, 0), (2, 0), (3, 0), (5, 0)]);
 
-			let mut snake =
-				Snake::new("snake", (0, 0).into(), Direction::Left, 5);
+			let mut snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Left,
+				5,
+				Topology::Square,
+			);
 			snake.lp_move(-5)?;
 			let part_coords = parts_into_tuple_coords(&snake.parts);
 			assert_eq!(
@@ -417,8 +504,13 @@ mod tests {
 				[(0, 0), (-1, 0), (-2, 0), (-3, 0), (1, 0)]
 			);
 
-			let mut snake =
-				Snake::new("snake", (0, 0).into(), Direction::Up, 5);
+			let mut snake = Snake::new(
+				"snake",
+				(0, 0).into(),
+				Direction::Up,
+				5,
+				Topology::Square,
+			);
 			snake.lp_move(5)?;
 			let part_coords = parts_into_tuple_coords(&snake.parts);
 			assert_eq!(part_coords, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 9)]);
@@ -438,6 +530,28 @@ mod tests {
 				.change_direction(Direction::Down)
 				.expect_err("snake must have wanted to turn 180 degrees");
 
+			let mut snake = new_snake(Direction::Right, 5);
+			snake.change_direction(Direction::Up)?;
+			snake
+				.change_direction(Direction::Left)
+				.expect_err("snake must have wanted to turn 180 degrees");
+
+			Ok(())
+		}
+
+		#[test]
+		fn turn() -> Result<()> {
+			let mut snake = new_snake(Direction::Right, 5);
+			snake.turn(Turn::Left)?;
+			snake.turn(Turn::Left)?;
+
+			assert_eq!(snake.direction, Direction::Up);
+
+			snake.move_parts(1)?;
+			snake.turn(Turn::Left)?;
+
+			assert_eq!(snake.direction, Direction::Left);
+
 			Ok(())
 		}
 
@@ -564,7 +678,7 @@ mod tests {
 		/// Return a snake with (0, 0) leading part coordinates, `direction` and
 		/// `n` parts.
 		fn new_snake(direction: Direction, n: usize) -> Snake {
-			Snake::new("snake", (0, 0).into(), direction, n)
+			Snake::new("snake", (0, 0).into(), direction, n, Topology::Square)
 		}
 
 		/// Convert all SnakePart's vector into tuple's vector.
@@ -592,7 +706,7 @@ mod tests {
 		}
 
 		#[test]
-		fn fromstr() -> Result<()> {
+		fn fromstr() -> std::result::Result<(), ParseSnakeLengthError> {
 			assert_eq!(
 				SnakeLength::Fixed(10).get(),
 				"10".parse::<SnakeLength>()?.get()
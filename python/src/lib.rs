@@ -0,0 +1,877 @@
+//! # Snake Game Python Bindings
+//! This crate provides `snake_game` Python module which wraps game abstractions
+//! and the [`Client`] so that clients and bots can be written on Python without
+//! implementing the server protocol by hand.
+//!
+//! Build and install the module into the current virtual environment with
+//! [maturin](https://github.com/PyO3/maturin):
+//! ```bash
+//! cd python
+//! maturin develop --release
+//! ```
+//!
+//! Then use it like this:
+//! ```python
+//! import snake_game
+//!
+//! client = snake_game.Client("python snake")
+//! client.connect("127.0.0.1:9999")
+//! grid = client.request_grid()
+//! client.change_direction(snake_game.Direction.Up)
+//! client.request_grid_update(grid)
+//! client.disconnect()
+//! ```
+
+// False positive in code generated by pyo3 macros.
+#![allow(clippy::useless_conversion)]
+
+use game::{
+	grid::{GameObject, GridPoint},
+	prelude::*,
+	snake::SnakeLength,
+};
+use pyo3::{
+	exceptions::{PyRuntimeError, PyValueError},
+	prelude::*,
+};
+use server::{encoding::Encoding, ErrorChain, ServerError};
+use std::{error::Error, net::TcpStream};
+
+/// Convert game or server error into Python exception.
+fn to_py_err(e: impl Error) -> PyErr {
+	PyRuntimeError::new_err(ErrorChain(&e).to_string())
+}
+
+/// Python module definition.
+#[pymodule]
+fn snake_game(m: &Bound<'_, PyModule>) -> PyResult<()> {
+	m.add_class::<PyDirection>()?;
+	m.add_class::<PyTurn>()?;
+	m.add_class::<PyTopology>()?;
+	m.add_class::<PySettings>()?;
+	m.add_class::<PyPoint>()?;
+	m.add_class::<PyGrid>()?;
+	m.add_class::<PyGameData>()?;
+	m.add_class::<PyStatus>()?;
+	m.add_class::<PyMessage>()?;
+	m.add_class::<PyUpdate>()?;
+	m.add_class::<PyClient>()?;
+	Ok(())
+}
+
+/// Snake direction. Diagonal directions are used only on the hexagonal grid.
+#[pyclass(name = "Direction", eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PyDirection {
+	Up,
+	Down,
+	Left,
+	Right,
+	UpLeft,
+	UpRight,
+	DownLeft,
+	DownRight,
+}
+
+impl From<PyDirection> for Direction {
+	fn from(direction: PyDirection) -> Self {
+		match direction {
+			PyDirection::Up => Self::Up,
+			PyDirection::Down => Self::Down,
+			PyDirection::Left => Self::Left,
+			PyDirection::Right => Self::Right,
+			PyDirection::UpLeft => Self::UpLeft,
+			PyDirection::UpRight => Self::UpRight,
+			PyDirection::DownLeft => Self::DownLeft,
+			PyDirection::DownRight => Self::DownRight,
+		}
+	}
+}
+
+impl From<Direction> for PyDirection {
+	fn from(direction: Direction) -> Self {
+		match direction {
+			Direction::Up => Self::Up,
+			Direction::Down => Self::Down,
+			Direction::Left => Self::Left,
+			Direction::Right => Self::Right,
+			Direction::UpLeft => Self::UpLeft,
+			Direction::UpRight => Self::UpRight,
+			Direction::DownLeft => Self::DownLeft,
+			Direction::DownRight => Self::DownRight,
+		}
+	}
+}
+
+#[pymethods]
+impl PyDirection {
+	/// Return opposite direction.
+	fn opposite(&self) -> Self {
+		Direction::from(*self).opposite().into()
+	}
+}
+
+/// Turn relative to the current direction.
+#[pyclass(name = "Turn", eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PyTurn {
+	Left,
+	Right,
+}
+
+impl From<PyTurn> for Turn {
+	fn from(turn: PyTurn) -> Self {
+		match turn {
+			PyTurn::Left => Self::Left,
+			PyTurn::Right => Self::Right,
+		}
+	}
+}
+
+/// Shape of the grid cells.
+#[pyclass(name = "Topology", eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PyTopology {
+	Square,
+	Hex,
+}
+
+impl From<PyTopology> for Topology {
+	fn from(topology: PyTopology) -> Self {
+		match topology {
+			PyTopology::Square => Self::Square,
+			PyTopology::Hex => Self::Hex,
+		}
+	}
+}
+
+impl From<Topology> for PyTopology {
+	fn from(topology: Topology) -> Self {
+		match topology {
+			Topology::Square => Self::Square,
+			Topology::Hex => Self::Hex,
+		}
+	}
+}
+
+/// Initial snake length, either a number or a `(start, end)` range to pick a
+/// random length from. Empty ranges raise `ValueError`.
+#[derive(Debug, Clone, FromPyObject)]
+enum PySnakeLength {
+	Fixed(usize),
+	Random((usize, usize)),
+}
+
+/// Game settings, see [`Settings`].
+#[pyclass(name = "Settings")]
+#[derive(Debug, Clone, Default)]
+struct PySettings(Settings);
+
+#[pymethods]
+impl PySettings {
+	#[new]
+	#[pyo3(signature = (
+		snakes_amount = Settings::SNAKES_AMOUNT,
+		apples_amount = Settings::APPLES_AMOUNT,
+		snake_step = Settings::SNAKE_STEP,
+		snake_increment_size = Settings::SNAKE_INCREMENT_SIZE,
+		snake_length = None,
+		snake_direction = None,
+		topology = PyTopology::Square,
+	))]
+	fn new(
+		snakes_amount: usize,
+		apples_amount: usize,
+		snake_step: i32,
+		snake_increment_size: usize,
+		snake_length: Option<PySnakeLength>,
+		snake_direction: Option<PyDirection>,
+		topology: PyTopology,
+	) -> PyResult<Self> {
+		let mut settings = Self(Settings {
+			snakes_amount,
+			apples_amount,
+			snake_step,
+			snake_increment_size,
+			snake_direction: snake_direction.map(Into::into),
+			topology: topology.into(),
+			..Default::default()
+		});
+		if let Some(length) = snake_length {
+			settings.set_snake_length(length)?;
+		}
+		Ok(settings)
+	}
+
+	#[getter]
+	fn snakes_amount(&self) -> usize {
+		self.0.snakes_amount
+	}
+
+	#[setter]
+	fn set_snakes_amount(&mut self, value: usize) {
+		self.0.snakes_amount = value;
+	}
+
+	#[getter]
+	fn apples_amount(&self) -> usize {
+		self.0.apples_amount
+	}
+
+	#[setter]
+	fn set_apples_amount(&mut self, value: usize) {
+		self.0.apples_amount = value;
+	}
+
+	#[getter]
+	fn snake_step(&self) -> i32 {
+		self.0.snake_step
+	}
+
+	#[setter]
+	fn set_snake_step(&mut self, value: i32) {
+		self.0.snake_step = value;
+	}
+
+	#[getter]
+	fn snake_increment_size(&self) -> usize {
+		self.0.snake_increment_size
+	}
+
+	#[setter]
+	fn set_snake_increment_size(&mut self, value: usize) {
+		self.0.snake_increment_size = value;
+	}
+
+	#[getter]
+	fn snake_length(&self, py: Python) -> PyObject {
+		match &self.0.snake_length {
+			SnakeLength::Fixed(n) => n.into_py(py),
+			SnakeLength::Random(range) => (range.start, range.end).into_py(py),
+		}
+	}
+
+	#[setter]
+	fn set_snake_length(&mut self, value: PySnakeLength) -> PyResult<()> {
+		self.0.snake_length = match value {
+			PySnakeLength::Fixed(n) => SnakeLength::Fixed(n),
+			PySnakeLength::Random((start, end)) if start >= end => {
+				return Err(PyValueError::new_err(format!(
+					"snake length range ({}, {}) is empty",
+					start, end
+				)));
+			}
+			PySnakeLength::Random((start, end)) => (start..end).into(),
+		};
+		Ok(())
+	}
+
+	#[getter]
+	fn snake_direction(&self) -> Option<PyDirection> {
+		self.0.snake_direction.map(Into::into)
+	}
+
+	#[setter]
+	fn set_snake_direction(&mut self, value: Option<PyDirection>) {
+		self.0.snake_direction = value.map(Into::into);
+	}
+
+	#[getter]
+	fn topology(&self) -> PyTopology {
+		self.0.topology.into()
+	}
+
+	#[setter]
+	fn set_topology(&mut self, value: PyTopology) {
+		self.0.topology = value.into();
+	}
+
+	fn __repr__(&self) -> String {
+		format!("{:?}", self.0)
+	}
+}
+
+/// One point of the grid, see [`GridPoint`].
+#[pyclass(name = "Point", get_all)]
+#[derive(Debug, Clone)]
+struct PyPoint {
+	/// Kind of the object, either `snake_part` or `apple`.
+	kind: String,
+	x: i32,
+	y: i32,
+	/// `(r, g, b, a)` color of the point.
+	color: (u8, u8, u8, u8),
+}
+
+impl From<&GridPoint> for PyPoint {
+	fn from(point: &GridPoint) -> Self {
+		let kind = match point.object_kind {
+			GameObject::SnakePart => "snake_part",
+			GameObject::Apple => "apple",
+		};
+		let Color { r, g, b, a } = point.color;
+		Self {
+			kind: kind.to_string(),
+			x: point.coordinates.x,
+			y: point.coordinates.y,
+			color: (r, g, b, a),
+		}
+	}
+}
+
+#[pymethods]
+impl PyPoint {
+	fn __repr__(&self) -> String {
+		format!("Point({}, {}, {})", self.kind, self.x, self.y)
+	}
+}
+
+/// Server status, see [`server::Status`].
+#[pyclass(name = "Status", get_all)]
+#[derive(Debug, Clone)]
+struct PyStatus {
+	tick: u64,
+	paused: bool,
+	players: usize,
+	spectators: usize,
+	pause_votes: usize,
+	resume_votes: usize,
+}
+
+impl From<server::Status> for PyStatus {
+	fn from(status: server::Status) -> Self {
+		Self {
+			tick: status.tick,
+			paused: status.paused,
+			players: status.players,
+			spectators: status.spectators,
+			pause_votes: status.pause_votes,
+			resume_votes: status.resume_votes,
+		}
+	}
+}
+
+#[pymethods]
+impl PyStatus {
+	fn __repr__(&self) -> String {
+		format!("{:?}", self)
+	}
+}
+
+/// Chat message, see [`server::chat::Message`].
+#[pyclass(name = "Message", get_all)]
+#[derive(Debug, Clone)]
+struct PyMessage {
+	id: u64,
+	/// Name of the player sent the message, `None` for system messages.
+	author: Option<String>,
+	text: String,
+}
+
+impl From<server::chat::Message> for PyMessage {
+	fn from(message: server::chat::Message) -> Self {
+		Self {
+			id: message.id,
+			author: message.author,
+			text: message.text,
+		}
+	}
+}
+
+#[pymethods]
+impl PyMessage {
+	fn __repr__(&self) -> String {
+		format!("{:?}", self)
+	}
+}
+
+/// Update pushed by the server after every tick, see [`server::Update`].
+/// Its grid is applied by [`PyClient::read_update`].
+#[pyclass(name = "Update", get_all)]
+#[derive(Debug, Clone)]
+struct PyUpdate {
+	tick: u64,
+	status: PyStatus,
+	/// List of `(name, length)` tuples of snakes.
+	scoreboard: Vec<(String, usize)>,
+	/// Chat messages posted since the previous update.
+	messages: Vec<PyMessage>,
+}
+
+impl From<server::Update> for PyUpdate {
+	fn from(update: server::Update) -> Self {
+		Self {
+			tick: update.tick,
+			status: update.status.into(),
+			scoreboard: update.scoreboard,
+			messages: update
+				.messages
+				.into_iter()
+				.map(PyMessage::from)
+				.collect(),
+		}
+	}
+}
+
+#[pymethods]
+impl PyUpdate {
+	fn __repr__(&self) -> String {
+		format!("{:?}", self)
+	}
+}
+
+/// Game grid, see [`Grid`].
+#[pyclass(name = "Grid")]
+#[derive(Debug, Clone)]
+struct PyGrid(Grid);
+
+#[pymethods]
+impl PyGrid {
+	#[new]
+	fn new(width: usize, height: usize) -> Self {
+		Self(Grid::new((width, height)))
+	}
+
+	/// Parse grid from json string sent by the server.
+	#[staticmethod]
+	fn from_json(string: &str) -> PyResult<Self> {
+		Grid::from_string(string).map(Self).map_err(to_py_err)
+	}
+
+	/// Return `(width, height)` of the grid.
+	#[getter]
+	fn size(&self) -> (usize, usize) {
+		self.0.size
+	}
+
+	/// Return tick the grid was built on.
+	#[getter]
+	fn tick(&self) -> u64 {
+		self.0.tick
+	}
+
+	/// Return shape of the grid cells.
+	#[getter]
+	fn topology(&self) -> PyTopology {
+		self.0.topology.into()
+	}
+
+	/// Return point at `(x, y)` or none if the cell is empty.
+	fn get(&self, x: i32, y: i32) -> Option<PyPoint> {
+		self.0.get(Coordinates::new(x, y)).map(Into::into)
+	}
+
+	/// Return true if `(x, y)` is within grid bounds.
+	fn contains(&self, x: i32, y: i32) -> bool {
+		self.0.contains(Coordinates::new(x, y))
+	}
+
+	/// Return all points of the grid.
+	fn points(&self) -> Vec<PyPoint> {
+		self.0.points().map(Into::into).collect()
+	}
+
+	fn __len__(&self) -> usize {
+		self.0.len()
+	}
+
+	fn __repr__(&self) -> String {
+		format!(
+			"Grid({}x{}, tick {}, {} points)",
+			self.0.size.0,
+			self.0.size.1,
+			self.0.tick,
+			self.0.len()
+		)
+	}
+}
+
+/// Game state, see [`GameData`]. Useful to run games locally without a server.
+#[pyclass(name = "GameData")]
+#[derive(Debug)]
+struct PyGameData(GameData);
+
+#[pymethods]
+impl PyGameData {
+	/// Raise an exception if the game can't be played with `settings`.
+	#[new]
+	#[pyo3(signature = (grid_size = None, settings = None))]
+	fn new(
+		grid_size: Option<(usize, usize)>,
+		settings: Option<PySettings>,
+	) -> PyResult<Self> {
+		let settings = settings.unwrap_or_default().0;
+		settings
+			.validate(grid_size.unwrap_or(GameData::GRID_SIZE))
+			.map_err(to_py_err)?;
+		Ok(Self(GameData::new(grid_size, settings)))
+	}
+
+	/// Seed random number generator to make the game reproducible.
+	fn set_seed(&mut self, seed: u64) {
+		self.0.set_seed(seed);
+	}
+
+	/// Add a new snake to the game. Unspecified arguments are random or taken
+	/// from the game settings.
+	#[pyo3(signature = (name, x = None, y = None, direction = None, length = None))]
+	fn spawn_snake(
+		&mut self,
+		name: &str,
+		x: Option<i32>,
+		y: Option<i32>,
+		direction: Option<PyDirection>,
+		length: Option<usize>,
+	) -> PyResult<()> {
+		let coords = x.zip(y).map(|(x, y)| Coordinates::new(x, y));
+		let direction = direction.map(|d| Some(d.into()));
+		self.0
+			.spawn_snake(name, coords, direction, length)
+			.map_err(to_py_err)
+	}
+
+	/// Change direction of the snake with `name`.
+	fn change_direction(
+		&mut self,
+		name: &str,
+		direction: PyDirection,
+	) -> PyResult<()> {
+		self.0
+			.snake_mut(name)
+			.and_then(|snake| snake.change_direction(direction.into()))
+			.map_err(to_py_err)
+	}
+
+	/// Advance the game on one tick. Return `(name, cause)` of snakes died
+	/// during the tick.
+	fn advance(&mut self) -> PyResult<Vec<(String, String)>> {
+		Ok(self
+			.0
+			.advance()
+			.map_err(to_py_err)?
+			.into_iter()
+			.map(|(snake, cause)| (snake.name(), cause.to_string()))
+			.collect())
+	}
+
+	/// Return current game grid.
+	fn grid(&self) -> PyGrid {
+		PyGrid(self.0.grid())
+	}
+
+	/// Return `(name, length)` of every snake in the game.
+	fn scoreboard(&self) -> Vec<(String, usize)> {
+		self.0.scoreboard()
+	}
+
+	/// Return amount of snakes in the game.
+	fn snakes(&self) -> usize {
+		self.0.snakes()
+	}
+
+	/// Return current game tick.
+	#[getter]
+	fn tick(&self) -> u64 {
+		self.0.tick()
+	}
+
+	/// Return game settings.
+	#[getter]
+	fn settings(&self) -> PySettings {
+		PySettings(self.0.settings())
+	}
+}
+
+/// Server client, see [`server::Client`].
+#[pyclass(name = "Client")]
+#[derive(Debug)]
+struct PyClient {
+	id: Option<String>,
+	stream: Option<TcpStream>,
+	encoding: Encoding,
+}
+
+impl server::Client for PyClient {
+	fn set_stream(&mut self, stream: Option<TcpStream>) {
+		self.stream = stream;
+	}
+
+	fn stream(&mut self) -> Option<&mut TcpStream> {
+		self.stream.as_mut()
+	}
+
+	fn stream_clone(&self) -> server::Result<TcpStream> {
+		let stream = self.stream.as_ref().ok_or(ServerError::NoStream)?;
+		Ok(stream.try_clone()?)
+	}
+
+	fn set_id(&mut self, id: Option<String>) {
+		self.id = id
+	}
+
+	fn id(&self) -> Option<String> {
+		self.id.clone()
+	}
+
+	fn set_encoding(&mut self, encoding: Encoding) {
+		self.encoding = encoding
+	}
+
+	fn encoding(&self) -> Encoding {
+		self.encoding
+	}
+}
+
+#[pymethods]
+impl PyClient {
+	/// Return a new client which will connect with `name`.
+	#[new]
+	fn new(name: String) -> Self {
+		Self {
+			id: Some(name),
+			stream: None,
+			encoding: Encoding::Json,
+		}
+	}
+
+	/// Return name accepted by the server.
+	#[getter]
+	fn name(&self) -> Option<String> {
+		self.id.clone()
+	}
+
+	/// Return true if client is connected to the server.
+	#[getter]
+	fn connected(&self) -> bool {
+		self.stream.is_some()
+	}
+
+	/// Connect to the server with `address` like `127.0.0.1:9999`.
+	fn connect(&mut self, address: &str) -> PyResult<()> {
+		server::Client::connect(self, address).map_err(to_py_err)
+	}
+
+	/// Connect to the server with `address` as a spectator which doesn't
+	/// spawn a snake. `follow` is a name of the player to watch.
+	#[pyo3(signature = (address, follow = None))]
+	fn spectate(
+		&mut self,
+		address: &str,
+		follow: Option<String>,
+	) -> PyResult<()> {
+		server::Client::spectate(self, address, follow).map_err(to_py_err)
+	}
+
+	/// Request the whole game grid.
+	fn request_grid(&mut self) -> PyResult<PyGrid> {
+		self.check_connection()?;
+		server::Client::request_grid(self)
+			.map(PyGrid)
+			.map_err(to_py_err)
+	}
+
+	/// Request changes made since the tick `grid` was built on and apply them
+	/// to the `grid`.
+	fn request_grid_update(
+		&mut self,
+		mut grid: PyRefMut<PyGrid>,
+	) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::request_grid_update(self, &mut grid.0)
+			.map_err(to_py_err)
+	}
+
+	/// Change snake's direction.
+	fn change_direction(&mut self, direction: PyDirection) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::change_direction(self, direction.into())
+			.map_err(to_py_err)
+	}
+
+	/// Turn snake relatively to its current direction.
+	fn turn(&mut self, turn: PyTurn) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::turn(self, turn.into()).map_err(to_py_err)
+	}
+
+	/// Request list of `(name, length)` tuples of snakes.
+	fn request_scoreboard(&mut self) -> PyResult<Vec<(String, usize)>> {
+		self.check_connection()?;
+		server::Client::request_scoreboard(self).map_err(to_py_err)
+	}
+
+	/// Send chat message to everyone in the game.
+	fn say(&mut self, text: &str) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::say(self, text).map_err(to_py_err)
+	}
+
+	/// Request chat messages posted after the message with the `since`
+	/// identifier.
+	#[pyo3(signature = (since = 0))]
+	fn request_messages(&mut self, since: u64) -> PyResult<Vec<PyMessage>> {
+		self.check_connection()?;
+		server::Client::request_messages(self, since)
+			.map(|messages| messages.into_iter().map(PyMessage::from).collect())
+			.map_err(to_py_err)
+	}
+
+	/// Request status of the server.
+	fn request_status(&mut self) -> PyResult<PyStatus> {
+		self.check_connection()?;
+		server::Client::request_status(self)
+			.map(PyStatus::from)
+			.map_err(to_py_err)
+	}
+
+	/// Subscribe to updates pushed after every tick. `since` is a tick of the
+	/// grid client already has, if it's `None`, the first update contains the
+	/// whole grid. Requests expecting answers shouldn't be sent after that.
+	#[pyo3(signature = (since = None))]
+	fn subscribe(&mut self, since: Option<u64>) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::subscribe(self, since).map_err(to_py_err)
+	}
+
+	/// Stop pushing updates. Already pushed ones should still be read.
+	fn unsubscribe(&mut self) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::unsubscribe(self).map_err(to_py_err)
+	}
+
+	/// Read the next pushed update and apply its changes to the `grid`.
+	fn read_update(
+		&mut self,
+		mut grid: PyRefMut<PyGrid>,
+	) -> PyResult<PyUpdate> {
+		self.check_connection()?;
+		let update = server::Client::read_update(self).map_err(to_py_err)?;
+		grid.0.apply(update.grid.clone()).map_err(to_py_err)?;
+		Ok(update.into())
+	}
+
+	/// Vote for pausing the game if `pause` is true or for resuming it
+	/// otherwise.
+	fn vote_pause(&mut self, pause: bool) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::vote_pause(self, pause).map_err(to_py_err)
+	}
+
+	/// Disconnect from the server.
+	fn disconnect(&mut self) -> PyResult<()> {
+		self.check_connection()?;
+		server::Client::disconnect(self).map_err(to_py_err)?;
+		self.stream = None;
+		Ok(())
+	}
+}
+
+impl PyClient {
+	/// Return error if client isn't connected to the server.
+	fn check_connection(&self) -> PyResult<()> {
+		match self.stream {
+			Some(_) => Ok(()),
+			None => Err(PyRuntimeError::new_err("client is not connected")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn with_length(length: PySnakeLength) -> PyResult<PySettings> {
+		PySettings::new(
+			Settings::SNAKES_AMOUNT,
+			Settings::APPLES_AMOUNT,
+			Settings::SNAKE_STEP,
+			Settings::SNAKE_INCREMENT_SIZE,
+			Some(length),
+			None,
+			PyTopology::Square,
+		)
+	}
+
+	#[test]
+	fn directions() {
+		let directions = [
+			PyDirection::Up,
+			PyDirection::Down,
+			PyDirection::Left,
+			PyDirection::Right,
+			PyDirection::UpLeft,
+			PyDirection::UpRight,
+			PyDirection::DownLeft,
+			PyDirection::DownRight,
+		];
+		for direction in directions {
+			assert_eq!(
+				PyDirection::from(Direction::from(direction)),
+				direction
+			);
+			assert_eq!(direction.opposite().opposite(), direction);
+		}
+		assert_eq!(PyDirection::UpLeft.opposite(), PyDirection::DownRight);
+	}
+
+	#[test]
+	fn snake_length() -> PyResult<()> {
+		let settings = with_length(PySnakeLength::Random((2, 5)))?;
+		assert!(matches!(
+			settings.0.snake_length,
+			SnakeLength::Random(ref range) if *range == (2..5)
+		));
+
+		assert!(with_length(PySnakeLength::Random((5, 5))).is_err());
+		assert!(with_length(PySnakeLength::Random((5, 2))).is_err());
+
+		let mut settings = with_length(PySnakeLength::Fixed(3))?;
+		assert!(settings
+			.set_snake_length(PySnakeLength::Random((4, 1)))
+			.is_err());
+		assert!(matches!(settings.0.snake_length, SnakeLength::Fixed(3)));
+		Ok(())
+	}
+
+	#[test]
+	fn game() -> PyResult<()> {
+		assert!(PyGameData::new(Some((0, 10)), None).is_err());
+
+		let mut game = PyGameData::new(Some((10, 10)), None)?;
+		game.spawn_snake(
+			"snake",
+			Some(5),
+			Some(5),
+			Some(PyDirection::Up),
+			None,
+		)?;
+		assert!(game.spawn_snake("snake", None, None, None, None).is_err());
+		assert!(game.change_direction("snake", PyDirection::Right).is_ok());
+
+		game.advance()?;
+		assert_eq!(game.tick(), 1);
+		assert_eq!(game.snakes(), 1);
+		let grid = game.grid();
+		assert_eq!(grid.size(), (10, 10));
+		assert!(grid.get(6, 5).is_some(), "snake should turn right");
+		Ok(())
+	}
+
+	#[test]
+	fn grid() {
+		let grid = PyGrid::new(5, 4);
+		assert!(grid.contains(5, 4));
+		assert!(!grid.contains(6, 1));
+		assert!(grid.get(1, 1).is_none());
+		assert_eq!(grid.__len__(), 0);
+		assert!(PyGrid::from_json("not a grid").is_err());
+	}
+
+	#[test]
+	fn disconnected_client() {
+		let mut client = PyClient::new("python snake".into());
+		assert_eq!(client.name().as_deref(), Some("python snake"));
+		assert!(!client.connected());
+		assert!(client.vote_pause(true).is_err());
+	}
+}
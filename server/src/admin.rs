@@ -0,0 +1,388 @@
+//! Remote admin protocol.
+//!
+//! Admin listener accepts TCP connections on its own port and executes
+//! [console](crate::console) commands sent by them. Every request is a json
+//! object on a separate line:
+//! ```json
+//! {"token": "secret", "command": "kick Mark"}
+//! ```
+//! Server answers with a line containing the command output:
+//! ```json
+//! {"ok": true, "message": "Kicked Mark"}
+//! ```
+//! Some commands also return `data` field with the output for programs. If
+//! the command fails, server answers with the error:
+//! ```json
+//! {"ok": false, "error": "client Mark isn't connected"}
+//! ```
+//! Connection is closed after a request which isn't authorized with the
+//! token, including invalid json and lines longer than [`MAX_LINE_SIZE`].
+//! At most [`MAX_CONNECTIONS`] admins may be connected at once, connections
+//! idle for [`READ_TIMEOUT`] are closed. Configuration can't be saved
+//! remotely because it would let admins write any file on the server.
+
+use crate::{console::Console, lock, ErrorChain, Result, ServerError};
+use logger::*;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+	io::{BufRead, BufReader, Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+	thread,
+	time::Duration,
+};
+
+/// Maximum size of a request line in bytes.
+pub const MAX_LINE_SIZE: usize = 4096;
+
+/// Maximum amount of admins connected at once.
+pub const MAX_CONNECTIONS: usize = 4;
+
+/// Time after which connection of an idle admin is closed.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Request of an admin.
+#[derive(Debug, Deserialize)]
+struct Request {
+	/// Token authorizing the admin.
+	token: String,
+
+	/// Console command to execute.
+	command: String,
+}
+
+/// Listener executing commands of remote admins.
+pub struct AdminListener {
+	listener: TcpListener,
+	token: Arc<String>,
+	console: Arc<Mutex<Console>>,
+
+	/// Amount of connected admins.
+	connections: Arc<AtomicUsize>,
+}
+
+impl AdminListener {
+	/// Bind listener to the `address`. Requests have to contain the `token` to
+	/// be executed by the `console`.
+	pub fn bind(
+		address: &str,
+		token: String,
+		console: Arc<Mutex<Console>>,
+	) -> Result<Self> {
+		Ok(Self {
+			listener: TcpListener::bind(address)?,
+			token: Arc::new(token),
+			console,
+			connections: Arc::new(AtomicUsize::new(0)),
+		})
+	}
+
+	/// Return address the listener is bound to.
+	pub fn local_addr(&self) -> Result<SocketAddr> {
+		Ok(self.listener.local_addr()?)
+	}
+
+	/// Accept connections and handle every one in its own thread.
+	pub fn run(self) -> Result<()> {
+		for stream in self.listener.incoming() {
+			let stream = match stream {
+				Ok(stream) => stream,
+				Err(e) => {
+					warn!("Failed to accept admin connection: {}", e);
+					continue;
+				}
+			};
+			let address = stream.peer_addr().ok();
+			let connections = Arc::clone(&self.connections);
+			if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+				connections.fetch_sub(1, Ordering::SeqCst);
+				warn!("Refused admin connection {:?}: too many", address);
+				let mut stream = stream;
+				let _ = respond(&mut stream, Err(ServerError::TooManyAdmins));
+				continue;
+			}
+
+			let token = Arc::clone(&self.token);
+			let console = Arc::clone(&self.console);
+			thread::spawn(move || {
+				if let Err(e) = handle(stream, &token, &console) {
+					warn!(
+						"Admin connection {:?} is closed: {}",
+						address,
+						ErrorChain(&e)
+					);
+				}
+				connections.fetch_sub(1, Ordering::SeqCst);
+			});
+		}
+		Ok(())
+	}
+}
+
+/// Execute requests read from the `stream` until it's closed or a request
+/// isn't authorized.
+fn handle(
+	stream: TcpStream,
+	token: &str,
+	console: &Mutex<Console>,
+) -> Result<()> {
+	stream.set_read_timeout(Some(READ_TIMEOUT))?;
+	let mut writer = stream.try_clone()?;
+	let mut reader = BufReader::new(stream);
+	loop {
+		let mut line = String::new();
+		// One more byte is read to find out whether the line is too long.
+		let limit = MAX_LINE_SIZE as u64 + 1;
+		if (&mut reader).take(limit).read_line(&mut line)? == 0 {
+			return Ok(());
+		}
+		if line.len() > MAX_LINE_SIZE {
+			respond(&mut writer, Err(ServerError::LineTooLong))?;
+			return Err(ServerError::LineTooLong);
+		}
+		if line.trim().is_empty() {
+			continue;
+		}
+		let request = serde_json::from_str::<Request>(&line)
+			.map_err(ServerError::from)
+			.and_then(|request| match token_eq(&request.token, token) {
+				true => Ok(request),
+				false => Err(ServerError::Unauthorized),
+			});
+		let request = match request {
+			Ok(request) => request,
+			Err(e) => {
+				// Request without a valid token isn't authorized either.
+				respond(&mut writer, Err(e))?;
+				return Err(ServerError::Unauthorized);
+			}
+		};
+
+		info!("Admin executes \"{}\"", request.command);
+		let output = lock(console).execute_remote(&request.command);
+		respond(&mut writer, output.map(|output| json!(output)))?;
+	}
+}
+
+/// Write response line with the `result` of a request to the `stream`.
+fn respond(stream: &mut TcpStream, result: Result<Value>) -> Result<()> {
+	let response = match result {
+		Ok(mut output) => {
+			output["ok"] = true.into();
+			output
+		}
+		Err(e) => json!({ "ok": false, "error": ErrorChain(&e).to_string() }),
+	};
+	let mut line = serde_json::to_string(&response)?;
+	line.push('\n');
+	stream.write_all(line.as_bytes())?;
+	Ok(())
+}
+
+/// Compare tokens in time independent of the position of the first
+/// mismatching byte.
+fn token_eq(a: &str, b: &str) -> bool {
+	a.len() == b.len()
+		&& a.bytes()
+			.zip(b.bytes())
+			.fold(0, |acc, (a, b)| acc | (a ^ b))
+			== 0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{config::Config, console::Command, Server};
+	use game::prelude::*;
+	use std::path::Path;
+
+	/// Connection of a test admin.
+	struct Admin {
+		reader: BufReader<TcpStream>,
+		writer: TcpStream,
+	}
+
+	impl Admin {
+		fn connect(address: SocketAddr) -> Result<Self> {
+			let writer = TcpStream::connect(address)?;
+			writer.set_read_timeout(Some(Duration::from_secs(5)))?;
+			let reader = BufReader::new(writer.try_clone()?);
+			Ok(Self { reader, writer })
+		}
+
+		/// Send the `line` and return the response to it.
+		fn send(&mut self, line: &str) -> Result<Value> {
+			self.writer.write_all(format!("{}\n", line).as_bytes())?;
+			self.read()
+		}
+
+		/// Send the `command` with the right token.
+		fn execute(&mut self, command: &str) -> Result<Value> {
+			let request = json!({ "token": "secret", "command": command });
+			self.send(&request.to_string())
+		}
+
+		fn read(&mut self) -> Result<Value> {
+			let mut line = String::new();
+			self.reader.read_line(&mut line)?;
+			Ok(serde_json::from_str(&line)?)
+		}
+
+		/// Return true if the server has closed the connection.
+		fn is_closed(&mut self) -> bool {
+			let mut line = String::new();
+			matches!(self.reader.read_line(&mut line), Ok(0) | Err(_))
+		}
+	}
+
+	/// Start admin listener with "secret" token for a new server.
+	fn listen() -> Result<(Server, SocketAddr)> {
+		let gamedata = GameData::new(Some((20, 20)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let config = Arc::new(Mutex::new(Config::default()));
+		let console = Console::new(server.handle(), config);
+		let console = Arc::new(Mutex::new(console));
+		let admin =
+			AdminListener::bind("127.0.0.1:0", "secret".into(), console)?;
+		let address = admin.local_addr()?;
+		thread::spawn(move || admin.run());
+		Ok((server, address))
+	}
+
+	#[test]
+	fn unauthorized() -> Result<()> {
+		let (_server, address) = listen()?;
+		let requests = [
+			r#"{"token": "wrong", "command": "list"}"#,
+			r#"{"token": "", "command": "list"}"#,
+			r#"{"command": "list"}"#,
+			r#"list"#,
+		];
+		for request in requests {
+			let mut admin = Admin::connect(address)?;
+			let response = admin.send(request)?;
+			assert_eq!(response["ok"], false, "{} should fail", request);
+			assert!(response["error"].is_string());
+			assert!(admin.is_closed(), "{} should close connection", request);
+		}
+
+		let mut admin = Admin::connect(address)?;
+		assert_eq!(
+			admin.send(r#"{"token": "wrong", "command": "list"}"#)?,
+			json!({ "ok": false, "error": "wrong admin token" })
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn long_line() -> Result<()> {
+		let (_server, address) = listen()?;
+		let mut admin = Admin::connect(address)?;
+		admin.writer.write_all(&[b'a'; MAX_LINE_SIZE + 10])?;
+		let response = admin.read()?;
+		assert_eq!(response["ok"], false);
+		assert!(admin.is_closed());
+		Ok(())
+	}
+
+	#[test]
+	fn too_many_connections() -> Result<()> {
+		let (_server, address) = listen()?;
+		let mut admins = (0..MAX_CONNECTIONS)
+			.map(|_| Admin::connect(address))
+			.collect::<Result<Vec<_>>>()?;
+		for admin in &mut admins {
+			assert_eq!(admin.execute("pause")?["ok"], true);
+		}
+
+		let mut admin = Admin::connect(address)?;
+		assert_eq!(
+			admin.read()?,
+			json!({ "ok": false, "error": "too many admin connections" })
+		);
+		assert!(admin.is_closed());
+		Ok(())
+	}
+
+	#[test]
+	fn commands() -> Result<()> {
+		let (_server, address) = listen()?;
+		let mut admin = Admin::connect(address)?;
+
+		let list = admin.execute("list")?;
+		assert_eq!(list["ok"], true);
+		assert!(list["message"].is_string());
+		for key in ["tick", "paused", "clients", "snakes", "spectators"] {
+			assert!(list["data"].get(key).is_some(), "list has no {}", key);
+		}
+
+		let ok = |message: &str| json!({ "ok": true, "message": message });
+		assert_eq!(admin.execute("pause")?, ok("Game is paused"));
+		assert_eq!(admin.execute("resume")?, ok("Game is resumed"));
+		assert_eq!(admin.execute("say hi")?, ok("Said \"hi\""));
+		assert_eq!(
+			admin.execute("spawn-apple 3 4")?,
+			ok("Spawned apple at (3, 4)")
+		);
+		assert_eq!(
+			admin.execute("step")?,
+			json!({
+				"ok": true,
+				"message": "Game is on tick 1",
+				"data": { "tick": 1 },
+			})
+		);
+		assert_eq!(
+			admin.execute("ban Nobody")?,
+			json!({
+				"ok": true,
+				"message": "Banned Nobody, disconnected 0 clients",
+				"data": { "disconnected": 0 },
+			})
+		);
+		assert_eq!(
+			admin.execute("set apples 3")?,
+			json!({
+				"ok": true,
+				"message": "Set apples to 3",
+				"data": { "restart_required": false },
+			})
+		);
+
+		let snapshot = admin.execute("snapshot")?;
+		assert_eq!(snapshot["data"]["size"], json!([20, 20]));
+		assert!(snapshot["data"]["data"].is_array());
+
+		let help = admin.execute("help")?;
+		assert_eq!(help["message"], Command::USAGE);
+
+		assert_eq!(
+			admin.execute("kick Nobody")?,
+			json!({ "ok": false, "error": "client Nobody isn't connected" })
+		);
+		assert_eq!(admin.execute("fly")?["ok"], false);
+		Ok(())
+	}
+
+	#[test]
+	fn remote_save() -> Result<()> {
+		let (_server, address) = listen()?;
+		let mut admin = Admin::connect(address)?;
+		let file = std::env::temp_dir().join("snake-admin-save.toml");
+		for path in [file.to_str().unwrap(), "/etc/passwd", "../config.toml"] {
+			assert_eq!(
+				admin.execute(&format!("save {}", path))?,
+				json!({
+					"ok": false,
+					"error": "save can be used only in the local console",
+				})
+			);
+		}
+		assert!(!Path::new(&file).exists());
+		Ok(())
+	}
+}
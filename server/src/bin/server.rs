@@ -1,24 +1,211 @@
-const DEFAULT_PORT: &str = "8787";
+use std::{
+	fs,
+	io::{self, BufRead},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, PoisonError},
+	thread,
+	time::{Duration, SystemTime},
+};
 
-use std::time::Duration;
-
-use game::prelude::*;
+use game::{bot::Difficulty, prelude::*};
 use logger::*;
+use server::{
+	admin::AdminListener, config::Config, console::Console, udp::UdpServer,
+	websocket::WebSocketListener, ErrorChain, Server, ServerHandle,
+};
+
+/// How often configuration file is checked for changes.
+const CONFIG_POLL_DELAY: Duration = Duration::from_secs(1);
 
 fn main() {
 	init_logger();
-	let (port, grid_size, game_delay, settings) = init_settings(init_cli());
+	let matches = init_cli();
+	let args: Vec<(&str, String)> = Config::KEYS
+		.iter()
+		.filter_map(|&key| Some((key, matches.value_of(key)?.to_string())))
+		.collect();
+	let file = matches.value_of("config").map(PathBuf::from);
+
+	let config = match load_config(file.as_deref(), &args) {
+		Ok(config) => config,
+		Err(e) => {
+			error!("Failed to load configuration: {}", ErrorChain(&e));
+			std::process::exit(1);
+		}
+	};
+	let address = config.listen_address();
+
+	let mut gamedata =
+		GameData::new(Some(config.grid_size), config.settings.clone());
+	let bots = gamedata
+		.set_bots(config.new_bots())
+		.and_then(|_| gamedata.respawn_bots());
+	if let Err(e) = bots {
+		warn!("Failed to add bots: {}", ErrorChain(&e));
+	}
+
+	let server = match Server::bind(&address, gamedata, Some(config.delay)) {
+		Ok(server) => server,
+		Err(e) => {
+			error!(
+				"Failed to bind the server to {}: {}",
+				address,
+				ErrorChain(&e)
+			);
+			std::process::exit(1);
+		}
+	};
+
+	server.handle().set_spectators_limit(config.spectators);
+	server
+		.handle()
+		.set_max_request_size(config.max_request_size);
+
+	let shared_config = Arc::new(Mutex::new(config.clone()));
+	let console = Console::new(server.handle(), Arc::clone(&shared_config));
+	let console = Arc::new(Mutex::new(console));
+	if let Some(port) = config.admin_port {
+		let address = format!("{}:{}", config.address, port);
+		let token = config.admin_token.clone();
+		match AdminListener::bind(&address, token, Arc::clone(&console)) {
+			Ok(admin) => {
+				info!("Listening for admin commands on {} address", address);
+				thread::spawn(move || admin.run());
+			}
+			Err(e) => {
+				error!(
+					"Failed to bind admin listener to {}: {}",
+					address,
+					ErrorChain(&e)
+				);
+				std::process::exit(1);
+			}
+		}
+	}
+	if let Some(port) = config.websocket_port {
+		let address = format!("{}:{}", config.address, port);
+		match WebSocketListener::bind(&address, server.handle()) {
+			Ok(listener) => {
+				info!("Listening for WebSocket clients on {} address", address);
+				thread::spawn(move || listener.run());
+			}
+			Err(e) => {
+				error!(
+					"Failed to bind WebSocket listener to {}: {}",
+					address,
+					ErrorChain(&e)
+				);
+				std::process::exit(1);
+			}
+		}
+	}
+	if let Some(port) = config.udp_port {
+		let address = format!("{}:{}", config.address, port);
+		match UdpServer::bind(&address, server.handle()) {
+			Ok(udp) => {
+				info!("Listening for UDP packets on {} address", address);
+				thread::spawn(move || udp.run());
+			}
+			Err(e) => {
+				error!(
+					"Failed to bind UDP socket to {}: {}",
+					address,
+					ErrorChain(&e)
+				);
+				std::process::exit(1);
+			}
+		}
+	}
+	thread::spawn(move || run_console(console));
 
-	let address = format!("0.0.0.0:{}", port);
+	if let Some(file) = file {
+		let handle = server.handle();
+		thread::spawn(move || {
+			watch_config(file, args, shared_config, handle)
+		});
+	}
 
 	info!("Running server on {} address", address);
 
-	if let Err(e) = server::run(
-		address,
-		GameData::new(Some(grid_size), settings),
-		Some(game_delay),
-	) {
-		error!("Error while running the server: {}", e);
+	if let Err(e) = server.run() {
+		error!("Error while running the server: {}", ErrorChain(&e));
+	}
+}
+
+/// Execute admin commands read from stdin until it's closed.
+fn run_console(console: Arc<Mutex<Console>>) {
+	for line in io::stdin().lock().lines() {
+		let line = match line {
+			Ok(line) => line,
+			Err(_) => break,
+		};
+		let output = console
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.execute(&line);
+		match output {
+			Ok(output) if output.message.is_empty() => (),
+			Ok(output) => println!("{}", output),
+			Err(e) => println!("Error: {}", ErrorChain(&e)),
+		}
+	}
+}
+
+/// Load configuration from the `file` and command line `args`.
+fn load_config(
+	file: Option<&Path>,
+	args: &[(&str, String)],
+) -> Result<Config, server::config::ConfigError> {
+	let args = args.iter().map(|(key, value)| (*key, value.as_str()));
+	Config::load(file, args)
+}
+
+/// Reload configuration every time the `file` is modified and apply it to the
+/// running server. `config` is the last applied configuration, it's shared
+/// with the console.
+fn watch_config(
+	file: PathBuf,
+	args: Vec<(&str, String)>,
+	config: Arc<Mutex<Config>>,
+	handle: ServerHandle,
+) {
+	let modified = |file: &Path| -> Option<SystemTime> {
+		fs::metadata(file).and_then(|meta| meta.modified()).ok()
+	};
+	let mut last_modified = modified(&file);
+
+	loop {
+		thread::sleep(CONFIG_POLL_DELAY);
+		let current = modified(&file);
+		if current == last_modified {
+			continue;
+		}
+		last_modified = current;
+
+		let reloaded = match load_config(Some(&file), &args) {
+			Ok(reloaded) => reloaded,
+			Err(e) => {
+				error!("Failed to reload configuration: {}", ErrorChain(&e));
+				continue;
+			}
+		};
+		let mut config = config.lock().unwrap_or_else(PoisonError::into_inner);
+		let restart_keys = config.restart_keys(&reloaded);
+		if !restart_keys.is_empty() {
+			warn!(
+				"Changes of {} can't be applied until the server is restarted",
+				restart_keys.join(", ")
+			);
+		}
+		match handle.reload(&reloaded) {
+			Ok(_) => {
+				info!("Reloaded configuration from {}", file.display());
+				*config = reloaded;
+			}
+			Err(e) => {
+				error!("Failed to apply configuration: {}", ErrorChain(&e))
+			}
+		}
 	}
 }
 
@@ -27,12 +214,25 @@ fn init_cli() -> clap::ArgMatches<'static> {
 
 	App::new("Snake Game by Mark")
         .about("Allows running own multiplayer server")
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("Configuration file in TOML or JSON format. Command line arguments and SNAKE_* environment variables override its values"),
+        )
+        .arg(
+            Arg::with_name("address")
+                .long("address")
+                .value_name("ADDRESS")
+                .help(&format!("Address to listen on. Default is {}", Config::ADDRESS)),
+        )
         .arg(
             Arg::with_name("port")
                 .value_name("NUMBER")
                 .short("p")
                 .long("port")
-                .help(&format!("Server port. Default is {}", DEFAULT_PORT)),
+                .help(&format!("Server port. Default is {}", Config::PORT)),
         )
         .arg(
             Arg::with_name("grid_size")
@@ -65,7 +265,7 @@ fn init_cli() -> clap::ArgMatches<'static> {
 						Settings::APPLES_AMOUNT)),
         )
         .arg(
-            Arg::with_name("game_delay")
+            Arg::with_name("delay")
                 .short("-d")
                 .long("delay")
                 .value_name("DURATION")
@@ -75,7 +275,7 @@ fn init_cli() -> clap::ArgMatches<'static> {
                 )),
         )
         .arg(
-            Arg::with_name("snake_inc_size")
+            Arg::with_name("inc_size")
                 .short("-i")
                 .long("inc-size")
                 .value_name("NUMBER")
@@ -114,90 +314,79 @@ into the range. Default is {}",
                 .long("snake-direction")
                 .value_name("DIRECTION")
                 .help(&format!(
-					"Specifies initial snake direction. Can be: left, right, up, down, random. Default is {:?}",
+					"Specifies initial snake direction. Can be: left, right, up, down, up_left, up_right, down_left, down_right, random. Default is {:?}",
 					match Settings::SNAKE_DIRECTION {
 						Some(val) => format!("{}", val),
 						None => "random".into(),
 					},
 				)),
         )
+        .arg(
+            Arg::with_name("topology")
+                .long("topology")
+                .value_name("TOPOLOGY")
+                .help(&format!(
+                    "Specifies shape of the grid cells. Can be: square, hex. Default is {}",
+                    Settings::TOPOLOGY,
+                )),
+        )
+        .arg(
+            Arg::with_name("bots")
+                .short("b")
+                .long("bots")
+                .value_name("NUMBER")
+                .help("Specifies amount of AI-controlled snakes filling empty slots. Default is 0"),
+        )
+        .arg(
+            Arg::with_name("bot_difficulty")
+                .long("bot-difficulty")
+                .value_name("DIFFICULTY")
+                .help(&format!(
+                    "Specifies difficulty of bots. Can be: random, greedy, survival. Default is {}",
+                    Difficulty::default(),
+                )),
+        )
+        .arg(
+            Arg::with_name("spectators")
+                .long("spectators")
+                .value_name("NUMBER")
+                .help(&format!(
+                    "Specifies maximum amount of spectators. Default is {}",
+                    server::SPECTATORS_LIMIT,
+                )),
+        )
+        .arg(
+            Arg::with_name("max_request_size")
+                .long("max-request-size")
+                .value_name("BYTES")
+                .help(&format!(
+                    "Specifies maximum size of a client request. Default is {}",
+                    server::framing::MAX_REQUEST_SIZE,
+                )),
+        )
+        .arg(
+            Arg::with_name("admin_port")
+                .long("admin-port")
+                .value_name("NUMBER")
+                .help("Port to listen on for remote admin commands. Remote administration is disabled by default"),
+        )
+        .arg(
+            Arg::with_name("admin_token")
+                .long("admin-token")
+                .value_name("TOKEN")
+                .help("Token remote admins have to send with every command"),
+        )
+        .arg(
+            Arg::with_name("websocket_port")
+                .long("websocket-port")
+                .value_name("NUMBER")
+                .help("Port to listen on for WebSocket clients. WebSocket connections are disabled by default"),
+        )
+        .arg(
+            Arg::with_name("udp_port")
+                .long("udp-port")
+                .value_name("NUMBER")
+                .help("Port to listen on for UDP packets of players on lossy links. UDP transport is disabled by default"),
+        )
         .get_matches()
 }
-
-/// Initialize all server settings. Return server port, grid size, game delay
-/// and game settings structure.
-fn init_settings(
-	matches: clap::ArgMatches,
-) -> (String, (usize, usize), Duration, Settings) {
-	(
-		matches.value_of("port").unwrap_or(DEFAULT_PORT).to_string(),
-		match matches.value_of("grid_size") {
-			Some(val) => {
-				let mut split = val.split('x').map(|x| {
-					x.parse::<usize>().expect("Parsing grid size argument")
-				});
-				(
-					split.next().expect(
-						"There should be two values separated with 'x'",
-					),
-					split.next().expect(
-						"There should be two values separated with 'x'",
-					),
-				)
-			}
-			None => GameData::GRID_SIZE,
-		},
-		match matches.value_of("game_delay") {
-			Some(val) => val
-				.parse::<humantime::Duration>()
-				.expect("Parsing delay argument")
-				.into(),
-			None => server::GAME_DELAY,
-		},
-		Settings {
-			snakes_amount: match matches.value_of("snakes") {
-				Some(val) => {
-					val.parse::<usize>().expect("Parsing snakes argument")
-				}
-				None => Settings::SNAKES_AMOUNT,
-			},
-			apples_amount: match matches.value_of("apples") {
-				Some(val) => {
-					val.parse::<usize>().expect("Parsing apples argument")
-				}
-				None => Settings::APPLES_AMOUNT,
-			},
-			snake_increment_size: match matches.value_of("snake_inc_size") {
-				Some(val) => val
-					.parse::<usize>()
-					.expect("Parsing snake increment size argument"),
-				None => Settings::SNAKE_INCREMENT_SIZE,
-			},
-			snake_length: match matches.value_of("snake_length") {
-				Some(val) => val
-					.parse::<SnakeLength>()
-					.expect("Parsing snake length argument"),
-				None => Settings::SNAKE_LENGTH,
-			},
-			snake_step: match matches.value_of("snake_step") {
-				Some(val) => {
-					val.parse::<i32>().expect("Parsing snake step argument")
-				}
-				None => Settings::SNAKE_STEP,
-			},
-			snake_direction: match matches.value_of("snake_direction") {
-				Some(val) => match val.parse::<Direction>() {
-					Ok(direction) => Some(direction),
-					Err(_) => {
-						if val == "random" {
-							None
-						} else {
-							panic!("Parsing snake direction argument")
-						}
-					}
-				},
-				None => Settings::SNAKE_DIRECTION,
-			},
-		},
-	)
-}
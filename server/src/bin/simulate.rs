@@ -0,0 +1,387 @@
+//! Headless simulation runner. Plays many games between bots in-process and
+//! prints aggregate statistics.
+
+use game::{
+	bot::{Bot, Difficulty},
+	prelude::*,
+	snake::DeathCause,
+};
+use logger::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Default amount of games to play.
+const GAMES: usize = 100;
+
+/// Default bots playing against each other.
+const BOTS: &str = "random,greedy,survival";
+
+/// Default maximum amount of ticks in one game.
+const MAX_TICKS: u64 = 1000;
+
+fn main() {
+	init_logger();
+	let matches = init_cli();
+	let args = match Args::parse(&matches) {
+		Ok(args) => args,
+		Err(e) => {
+			eprintln!("error: {}\n\n{}", e, matches.usage());
+			std::process::exit(1);
+		}
+	};
+	let Args {
+		games,
+		max_ticks,
+		seed,
+		apples,
+		grid_size,
+		bots,
+	} = args;
+
+	let settings = Settings {
+		snakes_amount: bots.len(),
+		apples_amount: apples,
+		snake_direction: None,
+		..Default::default()
+	};
+	if let Err(e) = settings.validate(grid_size) {
+		eprintln!("error: {}", e);
+		std::process::exit(1);
+	}
+
+	let mut stats: Vec<Stats> = bots
+		.iter()
+		.enumerate()
+		.map(|(i, &difficulty)| Stats::new(bot_name(i, difficulty), difficulty))
+		.collect();
+
+	for game in 0..games {
+		let mut gamedata = GameData::new(Some(grid_size), settings.clone());
+		gamedata.set_seed(seed + game as u64);
+
+		for (i, &difficulty) in bots.iter().enumerate() {
+			let bot = Bot::new(bot_name(i, difficulty), difficulty);
+			if let Err(e) = gamedata.add_bot(bot, None) {
+				eprintln!("error: can't add bots to game {}: {}", game, e);
+				std::process::exit(1);
+			}
+		}
+
+		let outcome = play(&mut gamedata, max_ticks);
+		debug!("Game {} is over: {:?}", game, outcome.winner);
+
+		for stats in &mut stats {
+			stats.record(&outcome);
+		}
+	}
+
+	match matches.value_of("format").unwrap_or("csv") {
+		"json" => println!(
+			"{}",
+			serde_json::to_string_pretty(&stats).expect("serializing stats")
+		),
+		_ => {
+			println!("{}", Stats::CSV_HEADER);
+			for stats in &stats {
+				println!("{}", stats.csv());
+			}
+		}
+	}
+}
+
+fn init_cli() -> clap::ArgMatches<'static> {
+	use clap::{App, Arg};
+
+	App::new("Snake Game Simulator by Mark")
+		.about("Plays games between bots and prints statistics")
+		.arg(
+			Arg::with_name("games")
+				.short("n")
+				.long("games")
+				.value_name("NUMBER")
+				.help(&format!("Amount of games to play. Default is {}", GAMES)),
+		)
+		.arg(
+			Arg::with_name("bots")
+				.short("b")
+				.long("bots")
+				.value_name("DIFFICULTIES")
+				.help(&format!(
+					"Comma-separated difficulties of bots playing in every game. Default is {}",
+					BOTS
+				)),
+		)
+		.arg(
+			Arg::with_name("grid_size")
+				.short("g")
+				.long("grid-size")
+				.value_name("SIZE")
+				.help(&format!(
+					"Specifies game grid size. Default is {}x{}",
+					GameData::GRID_SIZE.0,
+					GameData::GRID_SIZE.1
+				)),
+		)
+		.arg(
+			Arg::with_name("apples")
+				.short("a")
+				.long("apples")
+				.value_name("NUMBER")
+				.help(&format!(
+					"Specifies maximum amount of apples in the game. Default is {}",
+					Settings::APPLES_AMOUNT
+				)),
+		)
+		.arg(
+			Arg::with_name("max_ticks")
+				.short("t")
+				.long("max-ticks")
+				.value_name("NUMBER")
+				.help(&format!(
+					"Maximum amount of ticks in one game. Default is {}",
+					MAX_TICKS
+				)),
+		)
+		.arg(
+			Arg::with_name("seed")
+				.short("s")
+				.long("seed")
+				.value_name("NUMBER")
+				.help("Seed of the first game, every next game uses the next number. Default is 0"),
+		)
+		.arg(
+			Arg::with_name("format")
+				.short("f")
+				.long("format")
+				.value_name("FORMAT")
+				.possible_values(&["csv", "json"])
+				.help("Output format. Default is csv"),
+		)
+		.get_matches()
+}
+
+/// Parsed command line arguments.
+struct Args {
+	games: usize,
+	max_ticks: u64,
+	seed: u64,
+	apples: usize,
+	grid_size: (usize, usize),
+	bots: Vec<Difficulty>,
+}
+
+impl Args {
+	/// Parse arguments from the `matches`. Return error message if some of
+	/// them are invalid.
+	fn parse(matches: &clap::ArgMatches) -> Result<Self, String> {
+		let grid_size = match matches.value_of("grid_size") {
+			Some(val) => {
+				let invalid = || format!("invalid grid size \"{}\"", val);
+				let (width, height) =
+					val.split_once('x').ok_or_else(invalid)?;
+				(
+					width.trim().parse().map_err(|_| invalid())?,
+					height.trim().parse().map_err(|_| invalid())?,
+				)
+			}
+			None => GameData::GRID_SIZE,
+		};
+		let bots = matches
+			.value_of("bots")
+			.unwrap_or(BOTS)
+			.split(',')
+			.map(|val| {
+				val.trim()
+					.parse()
+					.map_err(|e| format!("invalid bot \"{}\": {}", val, e))
+			})
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self {
+			games: parse_or(matches, "games", GAMES)?,
+			max_ticks: parse_or(matches, "max_ticks", MAX_TICKS)?,
+			seed: parse_or(matches, "seed", 0)?,
+			apples: parse_or(matches, "apples", Settings::APPLES_AMOUNT)?,
+			grid_size,
+			bots,
+		})
+	}
+}
+
+/// Parse argument with `name` or return `default` if it's not specified.
+fn parse_or<T>(
+	matches: &clap::ArgMatches,
+	name: &str,
+	default: T,
+) -> Result<T, String>
+where
+	T: std::str::FromStr,
+	T::Err: std::fmt::Display,
+{
+	match matches.value_of(name) {
+		Some(val) => val
+			.parse()
+			.map_err(|e| format!("invalid {} \"{}\": {}", name, val, e)),
+		None => Ok(default),
+	}
+}
+
+/// Return name of the `i`th bot.
+fn bot_name(i: usize, difficulty: Difficulty) -> String {
+	format!("{} {}", difficulty, i + 1)
+}
+
+/// Play the game until there's at most one snake left or `max_ticks` ticks
+/// passed.
+fn play(gamedata: &mut GameData, max_ticks: u64) -> Outcome {
+	let mut outcome = Outcome::default();
+	let players = gamedata.snakes();
+
+	while gamedata.tick() < max_ticks {
+		let tick = gamedata.tick();
+		let killed = gamedata.advance().expect("advancing game");
+		for (snake, cause) in killed {
+			outcome.results.insert(
+				snake.name(),
+				PlayerResult {
+					length: snake.len(),
+					lifetime: tick,
+					cause: Some(cause),
+				},
+			);
+		}
+		if (gamedata.snakes() <= 1 && players > 1) || gamedata.snakes() == 0 {
+			break;
+		}
+	}
+
+	let scoreboard = gamedata.scoreboard();
+	for (name, length) in &scoreboard {
+		outcome.results.insert(
+			name.clone(),
+			PlayerResult {
+				length: *length,
+				lifetime: gamedata.tick(),
+				cause: None,
+			},
+		);
+	}
+
+	// The last survivor wins, otherwise the longest snake alive wins if it's
+	// the only one with such length.
+	let best = scoreboard.iter().map(|(_, length)| *length).max();
+	let mut leaders = scoreboard
+		.iter()
+		.filter(|(_, length)| Some(*length) == best);
+	outcome.winner = match (leaders.next(), leaders.next()) {
+		(Some((name, _)), None) => Some(name.clone()),
+		_ => None,
+	};
+
+	outcome
+}
+
+/// Result of one game.
+#[derive(Debug, Default)]
+struct Outcome {
+	/// Name of the winner or none if it's a draw.
+	winner: Option<String>,
+
+	/// Results of every player by their names.
+	results: HashMap<String, PlayerResult>,
+}
+
+/// Result of one player in a game.
+#[derive(Debug)]
+struct PlayerResult {
+	/// Snake length at the end of the game or at the moment of death.
+	length: usize,
+
+	/// Amount of ticks snake was alive.
+	lifetime: u64,
+
+	/// Cause of death or none if snake survived.
+	cause: Option<DeathCause>,
+}
+
+/// Aggregated statistics of one bot.
+#[derive(Debug, Serialize)]
+struct Stats {
+	bot: String,
+	difficulty: String,
+	games: usize,
+	wins: usize,
+	win_rate: f64,
+	average_length: f64,
+	average_lifetime: f64,
+	out_of_bounds: usize,
+	bit_itself: usize,
+	bumped: usize,
+	survived: usize,
+}
+
+impl Stats {
+	/// Header of the csv output.
+	const CSV_HEADER: &'static str = "bot,difficulty,games,wins,win_rate,average_length,average_lifetime,out_of_bounds,bit_itself,bumped,survived";
+
+	/// Return empty statistics of the bot.
+	fn new(bot: String, difficulty: Difficulty) -> Self {
+		Self {
+			bot,
+			difficulty: difficulty.to_string(),
+			games: 0,
+			wins: 0,
+			win_rate: 0.0,
+			average_length: 0.0,
+			average_lifetime: 0.0,
+			out_of_bounds: 0,
+			bit_itself: 0,
+			bumped: 0,
+			survived: 0,
+		}
+	}
+
+	/// Add game outcome to the statistics.
+	fn record(&mut self, outcome: &Outcome) {
+		let result = match outcome.results.get(&self.bot) {
+			Some(result) => result,
+			None => return,
+		};
+		let n = self.games as f64;
+
+		self.games += 1;
+		if outcome.winner.as_ref() == Some(&self.bot) {
+			self.wins += 1;
+		}
+		match result.cause {
+			Some(DeathCause::OutOfBounds) => self.out_of_bounds += 1,
+			Some(DeathCause::BitItself) => self.bit_itself += 1,
+			Some(DeathCause::Bumped(_)) => self.bumped += 1,
+			None => self.survived += 1,
+		}
+
+		self.win_rate = self.wins as f64 / self.games as f64;
+		self.average_length =
+			(self.average_length * n + result.length as f64) / (n + 1.0);
+		self.average_lifetime =
+			(self.average_lifetime * n + result.lifetime as f64) / (n + 1.0);
+	}
+
+	/// Return statistics as a csv row.
+	fn csv(&self) -> String {
+		format!(
+			"{},{},{},{},{:.3},{:.2},{:.2},{},{},{},{}",
+			self.bot,
+			self.difficulty,
+			self.games,
+			self.wins,
+			self.win_rate,
+			self.average_length,
+			self.average_lifetime,
+			self.out_of_bounds,
+			self.bit_itself,
+			self.bumped,
+			self.survived
+		)
+	}
+}
@@ -0,0 +1,172 @@
+//! In-game chat.
+//!
+//! Players send messages with [`Client::say`](crate::Client::say) and fetch
+//! them with [`Client::request_messages`](crate::Client::request_messages).
+//! Server posts system messages about joins, deaths and winners to the same
+//! chat.
+
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::VecDeque,
+	fmt,
+	time::{Duration, Instant},
+};
+
+/// Maximum amount of characters in a message.
+pub const MESSAGE_LENGTH_LIMIT: usize = 200;
+
+/// How many messages server keeps.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// How many messages server sends at a time, clients should request the rest
+/// again.
+pub const FETCH_LIMIT: usize = 16;
+
+/// How many messages client can send during [`RATE_LIMIT_PERIOD`].
+pub const RATE_LIMIT: usize = 5;
+
+/// Period of client's messages rate limiting.
+pub const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(10);
+
+/// Chat message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+	/// Identifier of the message, every next message has bigger one.
+	pub id: u64,
+
+	/// Name of the player sent the message, none for system messages.
+	pub author: Option<String>,
+
+	/// Text of the message.
+	pub text: String,
+}
+
+impl fmt::Display for Message {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.author {
+			Some(author) => write!(f, "{}: {}", author, self.text),
+			None => write!(f, "* {}", self.text),
+		}
+	}
+}
+
+/// History of chat messages.
+#[derive(Debug, Default)]
+pub struct Chat {
+	/// Last messages, the oldest one is the first.
+	messages: VecDeque<Message>,
+
+	/// Identifier of the last posted message.
+	last_id: u64,
+}
+
+impl Chat {
+	/// Add message with the `text` from the `author` to the history or a
+	/// system message if `author` is none.
+	pub fn post(&mut self, author: Option<String>, text: String) -> &Message {
+		if self.messages.len() >= HISTORY_LIMIT {
+			self.messages.pop_front();
+		}
+		self.last_id += 1;
+		self.messages.push_back(Message {
+			id: self.last_id,
+			author,
+			text,
+		});
+		self.messages.back().expect("message was just pushed")
+	}
+
+	/// Return up to [`FETCH_LIMIT`] oldest messages posted after the message
+	/// with the `since` identifier.
+	pub fn messages_since(&self, since: u64) -> Vec<Message> {
+		self.messages
+			.iter()
+			.filter(|message| message.id > since)
+			.take(FETCH_LIMIT)
+			.cloned()
+			.collect()
+	}
+
+	/// Return identifier of the last posted message, 0 if there're no ones.
+	pub fn last_id(&self) -> u64 {
+		self.last_id
+	}
+}
+
+/// Limiter of messages one client sends.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+	/// Times messages were sent at during the last [`RATE_LIMIT_PERIOD`].
+	sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+	/// Record message sent at the `now` time. Return false and don't record
+	/// it if client has already sent too many messages.
+	pub fn check(&mut self, now: Instant) -> bool {
+		while let Some(&time) = self.sent.front() {
+			if now.duration_since(time) < RATE_LIMIT_PERIOD {
+				break;
+			}
+			self.sent.pop_front();
+		}
+		if self.sent.len() >= RATE_LIMIT {
+			return false;
+		}
+		self.sent.push_back(now);
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn history() {
+		let mut chat = Chat::default();
+		assert_eq!(chat.last_id(), 0);
+		assert!(chat.messages_since(0).is_empty());
+		for i in 0..HISTORY_LIMIT + 10 {
+			chat.post(None, i.to_string());
+		}
+		assert_eq!(chat.last_id(), (HISTORY_LIMIT + 10) as u64);
+		assert_eq!(chat.messages.len(), HISTORY_LIMIT);
+		assert_eq!(chat.messages.front().unwrap().id, 11);
+	}
+
+	#[test]
+	fn messages_since() {
+		let mut chat = Chat::default();
+		for i in 0..FETCH_LIMIT + 4 {
+			chat.post(Some(String::from("Mark")), i.to_string());
+		}
+
+		let messages = chat.messages_since(0);
+		assert_eq!(messages.len(), FETCH_LIMIT);
+		assert_eq!(messages[0].id, 1);
+		assert_eq!(messages[0].text, "0");
+
+		let since = messages.last().unwrap().id;
+		let messages = chat.messages_since(since);
+		assert_eq!(messages.len(), 4);
+		assert_eq!(messages[0].id, since + 1);
+		assert!(chat.messages_since(chat.last_id()).is_empty());
+	}
+
+	#[test]
+	fn rate_limit() {
+		let mut limiter = RateLimiter::default();
+		let start = Instant::now();
+		for _ in 0..RATE_LIMIT {
+			assert!(limiter.check(start));
+		}
+		assert!(!limiter.check(start));
+		assert!(!limiter.check(start + RATE_LIMIT_PERIOD / 2));
+		let later = start + RATE_LIMIT_PERIOD;
+		for _ in 0..RATE_LIMIT {
+			assert!(limiter.check(later));
+		}
+		assert!(!limiter.check(later));
+	}
+}
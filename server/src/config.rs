@@ -0,0 +1,680 @@
+//! Server configuration.
+//!
+//! Configuration is collected from several sources. Values from command line
+//! arguments override values from environment variables, which override
+//! values from a configuration file, which override default ones.
+//!
+//! Configuration file is a TOML or, if its extension is `.json`, JSON table
+//! with the keys listed in [`Config::KEYS`]:
+//! ```toml
+//! address = "0.0.0.0"
+//! port = 8787
+//! grid_size = [30, 20] # or "30x20"
+//! delay = "70ms"
+//! snakes = 5
+//! apples = 1
+//! inc_size = 1
+//! snake_length = "3..=5"
+//! snake_step = 1
+//! snake_direction = "random"
+//! topology = "square"
+//! bots = 2
+//! bot_difficulty = "greedy"
+//! spectators = 10
+//! max_request_size = 65536
+//! admin_port = 8788
+//! admin_token = "secret"
+//! websocket_port = 8789
+//! udp_port = 8787
+//! ```
+//! Environment variables are named as the keys in upper case with the
+//! [`ENV_PREFIX`], e.g. `SNAKE_GRID_SIZE=30x20`.
+
+use game::{
+	bot::{Bot, Difficulty},
+	error::GameError,
+	prelude::*,
+};
+use std::{error, fmt, fs, io, path::Path, time::Duration};
+
+/// Prefix of environment variables overriding configuration values.
+pub const ENV_PREFIX: &str = "SNAKE_";
+
+/// Server configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// Address to listen on without port.
+	pub address: String,
+
+	/// Port to listen on.
+	pub port: u16,
+
+	/// Game grid size.
+	pub grid_size: (usize, usize),
+
+	/// Delay between game ticks.
+	pub delay: Duration,
+
+	/// Game settings.
+	pub settings: Settings,
+
+	/// Amount of AI-controlled snakes filling empty slots.
+	pub bots: usize,
+
+	/// Difficulty of bots.
+	pub bot_difficulty: Difficulty,
+
+	/// Maximum amount of spectators.
+	pub spectators: usize,
+
+	/// Maximum size of a request in bytes.
+	pub max_request_size: usize,
+
+	/// Port to listen on for admin commands, see [`admin`](crate::admin). If
+	/// it's none, remote administration is disabled.
+	pub admin_port: Option<u16>,
+
+	/// Token admins must send with every command.
+	pub admin_token: String,
+
+	/// Port to listen on for WebSocket connections, see
+	/// [`websocket`](crate::websocket). If it's none, only raw TCP clients
+	/// can connect.
+	pub websocket_port: Option<u16>,
+
+	/// Port to listen on for UDP packets, see [`udp`](crate::udp). It may be
+	/// the same as the game one. If it's none, UDP transport is disabled.
+	pub udp_port: Option<u16>,
+}
+
+impl Config {
+	/// Default address to listen on.
+	pub const ADDRESS: &'static str = "0.0.0.0";
+
+	/// Default port to listen on.
+	pub const PORT: u16 = 8787;
+
+	/// Keys which can be set in configuration sources.
+	pub const KEYS: [&'static str; 19] = [
+		"address",
+		"port",
+		"grid_size",
+		"delay",
+		"snakes",
+		"apples",
+		"inc_size",
+		"snake_length",
+		"snake_step",
+		"snake_direction",
+		"topology",
+		"bots",
+		"bot_difficulty",
+		"spectators",
+		"max_request_size",
+		"admin_port",
+		"admin_token",
+		"websocket_port",
+		"udp_port",
+	];
+
+	/// Return configuration read from the `file` if any, environment variables
+	/// and `args` key-value pairs in order of increasing priority. Returned
+	/// configuration is validated.
+	pub fn load<'a>(
+		file: Option<&Path>,
+		args: impl IntoIterator<Item = (&'a str, &'a str)>,
+	) -> Result<Self, ConfigError> {
+		let mut config = Self::default();
+
+		if let Some(file) = file {
+			for (key, value) in read_file(file)? {
+				config.set(&key, &value)?;
+			}
+		}
+		for key in Self::KEYS {
+			let var = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+			if let Ok(value) = std::env::var(var) {
+				config.set(key, &value)?;
+			}
+		}
+		for (key, value) in args {
+			config.set(key, value)?;
+		}
+
+		config.validate()?;
+		Ok(config)
+	}
+
+	/// Set value of the `key` parsing it from the `value` string.
+	pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+		let invalid = |reason: &dyn fmt::Display| ConfigError::InvalidValue {
+			key: key.to_string(),
+			value: value.to_string(),
+			reason: reason.to_string(),
+		};
+		let settings = &mut self.settings;
+
+		match key {
+			"address" => self.address = value.to_string(),
+			"port" => self.port = value.parse().map_err(|e| invalid(&e))?,
+			"grid_size" => {
+				let size = value
+					.split_once('x')
+					.ok_or_else(|| invalid(&"expected WIDTHxHEIGHT"))?;
+				self.grid_size = (
+					size.0.trim().parse().map_err(|e| invalid(&e))?,
+					size.1.trim().parse().map_err(|e| invalid(&e))?,
+				);
+			}
+			"delay" => {
+				self.delay = value
+					.parse::<humantime::Duration>()
+					.map_err(|e| invalid(&e))?
+					.into()
+			}
+			"snakes" => {
+				settings.snakes_amount =
+					value.parse().map_err(|e| invalid(&e))?
+			}
+			"apples" => {
+				settings.apples_amount =
+					value.parse().map_err(|e| invalid(&e))?
+			}
+			"inc_size" => {
+				settings.snake_increment_size =
+					value.parse().map_err(|e| invalid(&e))?
+			}
+			"snake_length" => {
+				settings.snake_length =
+					value.parse().map_err(|e| invalid(&e))?
+			}
+			"snake_step" => {
+				settings.snake_step = value.parse().map_err(|e| invalid(&e))?
+			}
+			"snake_direction" => {
+				settings.snake_direction = match value {
+					"random" => None,
+					_ => Some(value.parse().map_err(|e| invalid(&e))?),
+				}
+			}
+			"topology" => {
+				settings.topology = value.parse().map_err(|e| invalid(&e))?
+			}
+			"bots" => self.bots = value.parse().map_err(|e| invalid(&e))?,
+			"bot_difficulty" => {
+				self.bot_difficulty = value.parse().map_err(|e| invalid(&e))?
+			}
+			"spectators" => {
+				self.spectators = value.parse().map_err(|e| invalid(&e))?
+			}
+			"max_request_size" => {
+				self.max_request_size =
+					value.parse().map_err(|e| invalid(&e))?
+			}
+			"admin_port" => {
+				self.admin_port = match value {
+					"" => None,
+					_ => Some(value.parse().map_err(|e| invalid(&e))?),
+				}
+			}
+			"admin_token" => self.admin_token = value.to_string(),
+			"websocket_port" => {
+				self.websocket_port = match value {
+					"" => None,
+					_ => Some(value.parse().map_err(|e| invalid(&e))?),
+				}
+			}
+			"udp_port" => {
+				self.udp_port = match value {
+					"" => None,
+					_ => Some(value.parse().map_err(|e| invalid(&e))?),
+				}
+			}
+			_ => return Err(ConfigError::UnknownKey(key.to_string())),
+		}
+
+		Ok(())
+	}
+
+	/// Return value of the `key` in the same format [`set`](Self::set)
+	/// parses it or none if there's no such key.
+	pub fn get(&self, key: &str) -> Option<String> {
+		let settings = &self.settings;
+		let value = match key {
+			"address" => self.address.clone(),
+			"port" => self.port.to_string(),
+			"grid_size" => format!("{}x{}", self.grid_size.0, self.grid_size.1),
+			"delay" => humantime::format_duration(self.delay).to_string(),
+			"snakes" => settings.snakes_amount.to_string(),
+			"apples" => settings.apples_amount.to_string(),
+			"inc_size" => settings.snake_increment_size.to_string(),
+			"snake_length" => settings.snake_length.to_string(),
+			"snake_step" => settings.snake_step.to_string(),
+			"snake_direction" => match settings.snake_direction {
+				Some(direction) => direction.to_string(),
+				None => "random".into(),
+			},
+			"topology" => settings.topology.to_string(),
+			"bots" => self.bots.to_string(),
+			"bot_difficulty" => self.bot_difficulty.to_string(),
+			"spectators" => self.spectators.to_string(),
+			"max_request_size" => self.max_request_size.to_string(),
+			"admin_port" => self
+				.admin_port
+				.map(|port| port.to_string())
+				.unwrap_or_default(),
+			"admin_token" => self.admin_token.clone(),
+			"websocket_port" => self
+				.websocket_port
+				.map(|port| port.to_string())
+				.unwrap_or_default(),
+			"udp_port" => self
+				.udp_port
+				.map(|port| port.to_string())
+				.unwrap_or_default(),
+			_ => return None,
+		};
+		Some(value)
+	}
+
+	/// Convert configuration to TOML which can be loaded back. Admin token
+	/// isn't written to keep it out of saved files.
+	pub fn to_toml(&self) -> String {
+		Self::KEYS
+			.iter()
+			.filter(|&&key| key != "admin_token")
+			.filter_map(|&key| {
+				let value = toml::Value::String(self.get(key)?);
+				Some(format!("{} = {}\n", key, value))
+			})
+			.collect()
+	}
+
+	/// Check that server can be run with this configuration.
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		self.settings.validate(self.grid_size)?;
+		if self.admin_port.is_some() && self.admin_token.is_empty() {
+			return Err(ConfigError::InvalidValue {
+				key: "admin_token".into(),
+				value: String::new(),
+				reason: "token is required when admin port is set".into(),
+			});
+		}
+		if self.admin_port == Some(self.port) {
+			return Err(ConfigError::InvalidValue {
+				key: "admin_port".into(),
+				value: self.port.to_string(),
+				reason: "admin port must differ from the game one".into(),
+			});
+		}
+		if let Some(port) = self.websocket_port {
+			if port == self.port || self.admin_port == Some(port) {
+				return Err(ConfigError::InvalidValue {
+					key: "websocket_port".into(),
+					value: port.to_string(),
+					reason: "WebSocket port must differ from the game and \
+					         admin ones"
+						.into(),
+				});
+			}
+		}
+		let amount = self.settings.snakes_amount;
+		if amount != 0 && self.bots > amount {
+			return Err(GameError::InvalidSettings(format!(
+				"{} bots don't fit into {} snake slots",
+				self.bots, amount
+			))
+			.into());
+		}
+		Ok(())
+	}
+
+	/// Return keys of the values which differ in the `other` configuration
+	/// and can't be changed without restarting the server.
+	pub fn restart_keys(&self, other: &Self) -> Vec<&'static str> {
+		let changes = [
+			("address", self.address != other.address),
+			("port", self.port != other.port),
+			("grid_size", self.grid_size != other.grid_size),
+			(
+				"topology",
+				self.settings.topology != other.settings.topology,
+			),
+			("admin_port", self.admin_port != other.admin_port),
+			("admin_token", self.admin_token != other.admin_token),
+			(
+				"websocket_port",
+				self.websocket_port != other.websocket_port,
+			),
+			("udp_port", self.udp_port != other.udp_port),
+		];
+		changes
+			.iter()
+			.filter_map(|&(key, changed)| changed.then_some(key))
+			.collect()
+	}
+
+	/// Return bots the game should be played with.
+	pub fn new_bots(&self) -> Vec<Bot> {
+		(1..=self.bots)
+			.map(|i| Bot::new(format!("Bot {}", i), self.bot_difficulty))
+			.collect()
+	}
+
+	/// Return address with port to listen on.
+	pub fn listen_address(&self) -> String {
+		format!("{}:{}", self.address, self.port)
+	}
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			address: Self::ADDRESS.to_string(),
+			port: Self::PORT,
+			grid_size: GameData::GRID_SIZE,
+			delay: crate::GAME_DELAY,
+			settings: Settings::default(),
+			bots: 0,
+			bot_difficulty: Difficulty::default(),
+			spectators: crate::SPECTATORS_LIMIT,
+			max_request_size: crate::framing::MAX_REQUEST_SIZE,
+			admin_port: None,
+			admin_token: String::new(),
+			websocket_port: None,
+			udp_port: None,
+		}
+	}
+}
+
+/// Read key-value pairs from the configuration `file`.
+fn read_file(file: &Path) -> Result<Vec<(String, String)>, ConfigError> {
+	let string = fs::read_to_string(file)?;
+	let table: serde_json::Map<String, serde_json::Value> =
+		match file.extension().and_then(|e| e.to_str()) {
+			Some("json") => serde_json::from_str(&string)
+				.map_err(|e| ConfigError::Syntax(e.to_string()))?,
+			_ => toml::from_str(&string)
+				.map_err(|e| ConfigError::Syntax(e.to_string()))?,
+		};
+
+	table
+		.into_iter()
+		.map(|(key, value)| {
+			let value = match value {
+				serde_json::Value::String(string) => string,
+				// Grid size may be written as an array of width and height.
+				serde_json::Value::Array(values) => values
+					.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>()
+					.join("x"),
+				value => value.to_string(),
+			};
+			Ok((key, value))
+		})
+		.collect()
+}
+
+/// Error returned if configuration can't be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+	/// Configuration file can't be read.
+	Io(io::Error),
+
+	/// Configuration file isn't valid TOML or JSON.
+	Syntax(String),
+
+	/// Key specified in variant's argument isn't a configuration key.
+	UnknownKey(String),
+
+	/// Value of the key can't be parsed.
+	InvalidValue {
+		key: String,
+		value: String,
+		reason: String,
+	},
+
+	/// Game can't be played with configured settings.
+	Game(GameError),
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(_) => write!(f, "can't read configuration file"),
+			Self::Syntax(e) => write!(f, "invalid configuration file: {}", e),
+			Self::UnknownKey(key) => {
+				write!(f, "unknown configuration key \"{}\"", key)
+			}
+			Self::InvalidValue { key, value, reason } => write!(
+				f,
+				"invalid value \"{}\" of \"{}\" key: {}",
+				value, key, reason
+			),
+			Self::Game(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+impl error::Error for ConfigError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::Io(e) => Some(e),
+			// Game error is displayed as is, so it isn't a source.
+			Self::Game(e) => e.source(),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for ConfigError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<GameError> for ConfigError {
+	fn from(e: GameError) -> Self {
+		Self::Game(e)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::PathBuf;
+
+	/// Write the `contents` to a temporary file ending with the `name`.
+	fn write_file(name: &str, contents: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!(
+			"snake-config-{}-{}",
+			std::process::id(),
+			name
+		));
+		fs::write(&path, contents).unwrap();
+		path
+	}
+
+	#[test]
+	fn precedence() -> Result<(), ConfigError> {
+		let file = write_file(
+			"precedence.toml",
+			"port = 1000\nspectators = 1\nbots = 1\n",
+		);
+		// Only this test sets the environment variables, so they don't leak.
+		std::env::set_var("SNAKE_PORT", "2000");
+		std::env::set_var("SNAKE_SPECTATORS", "2");
+		let config = Config::load(Some(&file), [("port", "3000")]);
+		std::env::remove_var("SNAKE_PORT");
+		std::env::remove_var("SNAKE_SPECTATORS");
+		fs::remove_file(&file)?;
+		let config = config?;
+
+		assert_eq!(config.port, 3000);
+		assert_eq!(config.spectators, 2);
+		assert_eq!(config.bots, 1);
+		assert_eq!(config.delay, Config::default().delay);
+		Ok(())
+	}
+
+	#[test]
+	fn parse_files() -> Result<(), ConfigError> {
+		let toml = write_file(
+			"parse.toml",
+			r#"
+			grid_size = [30, 20]
+			delay = "70ms"
+			snake_length = "3..=5"
+			snake_direction = "up"
+			admin_port = 8788
+			admin_token = "secret"
+			"#,
+		);
+		let json = write_file(
+			"parse.json",
+			r#"{
+				"grid_size": "30x20",
+				"delay": "70ms",
+				"snake_length": "3..=5",
+				"snake_direction": "up",
+				"admin_port": 8788,
+				"admin_token": "secret"
+			}"#,
+		);
+		let mut configs = Vec::new();
+		for file in [&toml, &json] {
+			let mut config = Config::default();
+			for (key, value) in read_file(file)? {
+				config.set(&key, &value)?;
+			}
+			fs::remove_file(file)?;
+			configs.push(config);
+		}
+
+		for config in configs {
+			assert_eq!(config.grid_size, (30, 20));
+			assert_eq!(config.delay, Duration::from_millis(70));
+			let length = config.settings.snake_length;
+			assert!(matches!(length, SnakeLength::Random(r) if r == (3..6)));
+			assert_eq!(config.settings.snake_direction, Some(Direction::Up));
+			assert_eq!(config.admin_port, Some(8788));
+			assert_eq!(config.admin_token, "secret");
+		}
+
+		let invalid = write_file("invalid.json", "{ port = 1 }");
+		let error = read_file(&invalid).unwrap_err();
+		fs::remove_file(&invalid)?;
+		assert!(matches!(error, ConfigError::Syntax(_)));
+		let missing = write_file("missing.toml", "");
+		fs::remove_file(&missing)?;
+		assert!(matches!(read_file(&missing), Err(ConfigError::Io(_))));
+		Ok(())
+	}
+
+	#[test]
+	fn set_errors() {
+		let mut config = Config::default();
+		assert_eq!(
+			config.set("speed", "1").unwrap_err().to_string(),
+			"unknown configuration key \"speed\""
+		);
+		assert_eq!(
+			config.set("grid_size", "30").unwrap_err().to_string(),
+			"invalid value \"30\" of \"grid_size\" key: expected \
+			 WIDTHxHEIGHT"
+		);
+		assert!(config.set("port", "port").is_err());
+	}
+
+	#[test]
+	fn validation_errors() {
+		/// Change of the default configuration and resulting error message.
+		type Case = (fn(&mut Config), &'static str);
+		let cases: [Case; 12] = [
+			(
+				|c| c.grid_size = (usize::MAX, 2),
+				"invalid settings: grid size 18446744073709551615x2 is \
+				 larger than 1048576 cells",
+			),
+			(
+				|c| c.grid_size = (0, 10),
+				"invalid settings: grid size 0x10 has no cells",
+			),
+			(
+				|c| c.settings.apples_amount = 2000,
+				"invalid settings: 2000 apples don't fit into the grid of \
+				 1250 cells",
+			),
+			(
+				|c| c.settings.snake_increment_size = 2000,
+				"invalid settings: snake increment size 2000 is larger than \
+				 the grid of 1250 cells",
+			),
+			(
+				|c| c.settings.snake_step = 0,
+				"invalid settings: snake step is zero",
+			),
+			(
+				|c| c.settings.snake_length = SnakeLength::Fixed(0),
+				"invalid settings: snake length is zero",
+			),
+			(
+				|c| c.settings.snake_length = SnakeLength::Random(0..3),
+				"invalid settings: snake length range 0..3 starts at zero",
+			),
+			(
+				|c| {
+					#[allow(clippy::reversed_empty_ranges)]
+					let range = 5..3;
+					c.settings.snake_length = SnakeLength::Random(range);
+				},
+				"invalid settings: snake length range 5..3 is empty",
+			),
+			(
+				|c| {
+					c.settings.topology = Topology::Hex;
+					c.settings.snake_direction = Some(Direction::Up);
+				},
+				"invalid settings: snake can't go up on the hex grid",
+			),
+			(
+				|c| c.admin_port = Some(8788),
+				"invalid value \"\" of \"admin_token\" key: token is \
+				 required when admin port is set",
+			),
+			(
+				|c| {
+					c.admin_port = Some(c.port);
+					c.admin_token = "secret".into();
+				},
+				"invalid value \"8787\" of \"admin_port\" key: admin port \
+				 must differ from the game one",
+			),
+			(
+				|c| c.websocket_port = Some(c.port),
+				"invalid value \"8787\" of \"websocket_port\" key: \
+				 WebSocket port must differ from the game and admin ones",
+			),
+		];
+		for (change, message) in cases {
+			let mut config = Config::default();
+			change(&mut config);
+			assert_eq!(config.validate().unwrap_err().to_string(), message);
+		}
+
+		let config = Config {
+			bots: 10,
+			settings: Settings {
+				snakes_amount: 5,
+				..Default::default()
+			},
+			..Default::default()
+		};
+		assert_eq!(
+			config.validate().unwrap_err().to_string(),
+			"invalid settings: 10 bots don't fit into 5 snake slots"
+		);
+		assert!(Config::default().validate().is_ok());
+	}
+}
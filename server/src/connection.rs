@@ -0,0 +1,229 @@
+//! Client connections.
+//!
+//! Clients connect either with raw TCP exchanging length-prefixed frames, see
+//! [`framing`](crate::framing), or with WebSocket exchanging messages, see
+//! [`websocket`](crate::websocket). Both transports carry the same requests
+//! and responses, so [`Session`](crate::Session) doesn't care which one is
+//! used.
+//!
+//! Messages to a client are queued and written by a separate task, so
+//! neither request handling nor pushing updates waits for slow clients.
+//! Clients which don't read what's written to them are disconnected.
+
+use crate::{encoding::Encoding, framing, ErrorChain, Result, ServerError};
+use futures_util::{
+	stream::{SplitSink, SplitStream},
+	SinkExt, StreamExt,
+};
+use logger::*;
+use std::{io, sync::Arc};
+use tokio::{
+	io::AsyncWriteExt,
+	net::{
+		tcp::{OwnedReadHalf, OwnedWriteHalf},
+		TcpStream,
+	},
+	sync::{
+		mpsc::{self, error::TrySendError},
+		Notify,
+	},
+	time,
+};
+use tokio_tungstenite::{
+	tungstenite::protocol::{Message, WebSocketConfig},
+	WebSocketStream,
+};
+
+/// Maximum amount of messages waiting to be written to a client.
+const QUEUE_SIZE: usize = 64;
+
+/// Protocol clients connect with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+	/// Raw TCP.
+	Tcp,
+
+	/// WebSocket.
+	WebSocket,
+}
+
+/// Connection to a client, its clones share the same connection.
+#[derive(Debug, Clone)]
+pub struct Connection {
+	/// Messages with their encodings waiting to be written.
+	queue: mpsc::Sender<(Arc<[u8]>, Encoding)>,
+
+	/// Notified when the connection is closed.
+	closed: Arc<Notify>,
+}
+
+/// Reading half of a connection owned by client's handler.
+pub enum Reader {
+	/// Raw TCP stream messages are read from as frames.
+	Tcp(OwnedReadHalf),
+
+	/// Stream of WebSocket messages.
+	WebSocket(SplitStream<WebSocketStream<TcpStream>>),
+}
+
+/// Writing half of a connection owned by the writer task.
+enum Writer {
+	/// Raw TCP stream messages are written to as frames.
+	Tcp(OwnedWriteHalf),
+
+	/// Sink of WebSocket messages.
+	WebSocket(SplitSink<WebSocketStream<TcpStream>, Message>),
+}
+
+impl Connection {
+	/// Open connection with the `protocol` on the `stream` and start writing
+	/// messages to it. WebSocket messages and frames longer than
+	/// `max_message_size` bytes are refused while they're being received.
+	/// Return the connection and its reading half.
+	pub async fn open(
+		stream: TcpStream,
+		protocol: Protocol,
+		max_message_size: usize,
+	) -> Result<(Self, Reader)> {
+		let (reader, writer) = match protocol {
+			Protocol::Tcp => {
+				let (reader, writer) = stream.into_split();
+				(Reader::Tcp(reader), Writer::Tcp(writer))
+			}
+			Protocol::WebSocket => {
+				let config = WebSocketConfig {
+					max_message_size: Some(max_message_size),
+					max_frame_size: Some(max_message_size),
+					..Default::default()
+				};
+				let socket = tokio_tungstenite::accept_async_with_config(
+					stream,
+					Some(config),
+				)
+				.await?;
+				let (writer, reader) = socket.split();
+				(Reader::WebSocket(reader), Writer::WebSocket(writer))
+			}
+		};
+		let (queue, messages) = mpsc::channel(QUEUE_SIZE);
+		let closed = Arc::new(Notify::new());
+		tokio::spawn(writer.run(messages, Arc::clone(&closed)));
+		Ok((Self { queue, closed }, reader))
+	}
+
+	/// Queue the `message` in the `encoding` to be written. WebSocket
+	/// messages in json are sent as text and others as binary. Client is
+	/// disconnected if there're too many queued messages.
+	pub fn write(
+		&self,
+		message: impl Into<Arc<[u8]>>,
+		encoding: Encoding,
+	) -> Result<()> {
+		self.queue.try_send((message.into(), encoding)).map_err(
+			|e| match e {
+				TrySendError::Full(_) => {
+					self.shutdown();
+					io::Error::from(io::ErrorKind::WouldBlock)
+				}
+				TrySendError::Closed(_) => io::ErrorKind::BrokenPipe.into(),
+			},
+		)?;
+		Ok(())
+	}
+
+	/// Close the connection, its handler stops after that.
+	pub fn shutdown(&self) {
+		self.closed.notify_one();
+	}
+
+	/// Wait until the connection is closed.
+	pub async fn closed(&self) {
+		self.closed.notified().await
+	}
+}
+
+impl Reader {
+	/// Read the next message. Messages longer than `max_size` bytes aren't
+	/// read, the connection can't be used after that.
+	pub async fn read(&mut self, max_size: usize) -> Result<Vec<u8>> {
+		let stream = match self {
+			Self::Tcp(stream) => {
+				return framing::read_frame_async(stream, max_size).await
+			}
+			Self::WebSocket(stream) => stream,
+		};
+		loop {
+			let message = match stream.next().await {
+				Some(Ok(Message::Text(text))) => text.into_bytes(),
+				Some(Ok(Message::Binary(bytes))) => bytes,
+				Some(Ok(Message::Close(_))) | None => {
+					return Err(tungstenite::Error::ConnectionClosed.into())
+				}
+				Some(Ok(_)) => continue,
+				Some(Err(e)) => return Err(e.into()),
+			};
+			if message.len() > max_size {
+				return Err(ServerError::FrameTooLarge(
+					message.len(),
+					max_size,
+				));
+			}
+			return Ok(message);
+		}
+	}
+}
+
+impl Writer {
+	/// Write queued `messages` until all connection clones are dropped.
+	/// Connection is closed if writing fails or takes longer than
+	/// [`SERVER_WRITE_TIMEOUT`](crate::SERVER_WRITE_TIMEOUT).
+	async fn run(
+		mut self,
+		mut messages: mpsc::Receiver<(Arc<[u8]>, Encoding)>,
+		closed: Arc<Notify>,
+	) {
+		while let Some((message, encoding)) = messages.recv().await {
+			let write = self.write(message, encoding);
+			let result = time::timeout(crate::SERVER_WRITE_TIMEOUT, write)
+				.await
+				.unwrap_or_else(|_| {
+					Err(ServerError::Io(io::ErrorKind::TimedOut.into()))
+				});
+			if let Err(e) = result {
+				debug!("Failed to write to client: {}", ErrorChain(&e));
+				closed.notify_one();
+				break;
+			}
+		}
+		match &mut self {
+			Self::Tcp(stream) => {
+				let _ = stream.shutdown().await;
+			}
+			Self::WebSocket(sink) => {
+				let _ = sink.close().await;
+			}
+		}
+	}
+
+	/// Write the `message` in the `encoding`.
+	async fn write(
+		&mut self,
+		message: Arc<[u8]>,
+		encoding: Encoding,
+	) -> Result<()> {
+		match self {
+			Self::Tcp(stream) => {
+				framing::write_frame_async(stream, &message).await
+			}
+			Self::WebSocket(sink) => {
+				let message = match encoding {
+					Encoding::Json => Message::Text(
+						String::from_utf8_lossy(&message).into_owned(),
+					),
+					Encoding::MessagePack => Message::Binary(message.to_vec()),
+				};
+				Ok(sink.send(message).await?)
+			}
+		}
+	}
+}
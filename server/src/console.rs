@@ -0,0 +1,491 @@
+//! Admin console.
+//!
+//! Console executes text commands on a running server, see [`Command`] for
+//! the list of them.
+
+use crate::{config::Config, lock, Result, ServerError, ServerHandle};
+use game::prelude::*;
+use logger::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+	fmt, fs,
+	path::PathBuf,
+	str::FromStr,
+	sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Admin console command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+	/// Show connected clients and snakes in the game.
+	List,
+
+	/// Disconnect client with the name and kill its snake.
+	Kick(String),
+
+	/// Forbid connecting with the name or from the ip address.
+	Ban(String),
+
+	/// Add an apple with the coordinates to the game.
+	SpawnApple(Coordinates),
+
+	/// Change configuration value of the key, see [`Config::set`].
+	Set(String, String),
+
+	/// Stop advancing the game.
+	Pause,
+
+	/// Continue advancing the paused game.
+	Resume,
+
+	/// Pause the game and advance it on one tick.
+	Step,
+
+	/// Send the message to players.
+	Say(String),
+
+	/// Save configuration with the changes made by [`Set`](Self::Set)
+	/// commands to the file. Remote admins can't use it.
+	Save(PathBuf),
+
+	/// Show the game grid.
+	Snapshot,
+
+	/// Show available commands.
+	Help,
+}
+
+impl Command {
+	/// Description of all commands.
+	pub const USAGE: &'static str = "\
+list                 show connected clients and snakes
+kick <name>          disconnect client and kill its snake
+ban <name|ip>        forbid connecting with the name or from the ip address
+spawn-apple <x> <y>  add an apple to the game
+set <key> <value>    change configuration value
+pause                stop advancing the game
+resume               continue advancing the game
+step                 pause the game and advance it on one tick
+say <message>        send message to players
+save <file>          save configuration to the file
+snapshot             show the game grid
+help                 show this message";
+}
+
+impl FromStr for Command {
+	type Err = ServerError;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let s = s.trim();
+		let (command, args) = s.split_once(' ').unwrap_or((s, ""));
+		let args = args.trim();
+		let arg = |usage| match args.is_empty() {
+			true => Err(ServerError::InvalidCommand(usage)),
+			false => Ok(args.to_string()),
+		};
+
+		let command = match command {
+			"list" => Self::List,
+			"kick" => Self::Kick(arg("kick <name>")?),
+			"ban" => Self::Ban(arg("ban <name|ip>")?),
+			"spawn-apple" => {
+				let usage = "spawn-apple <x> <y>";
+				let coords: Vec<i32> = args
+					.split_whitespace()
+					.map(str::parse)
+					.collect::<std::result::Result<_, _>>()
+					.map_err(|_| ServerError::InvalidCommand(usage))?;
+				match coords[..] {
+					[x, y] => Self::SpawnApple(Coordinates::new(x, y)),
+					_ => return Err(ServerError::InvalidCommand(usage)),
+				}
+			}
+			"set" => {
+				let usage = "set <key> <value>";
+				match arg(usage)?.split_once(' ') {
+					Some((key, value)) => {
+						Self::Set(key.into(), value.trim().into())
+					}
+					None => return Err(ServerError::InvalidCommand(usage)),
+				}
+			}
+			"pause" => Self::Pause,
+			"resume" => Self::Resume,
+			"step" => Self::Step,
+			"say" => Self::Say(arg("say <message>")?),
+			"save" => Self::Save(arg("save <file>")?.into()),
+			"snapshot" => Self::Snapshot,
+			"help" => Self::Help,
+			_ => return Err(ServerError::UnknownCommand(command.to_string())),
+		};
+		Ok(command)
+	}
+}
+
+/// Output of a command.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Output {
+	/// Human-readable result of the command.
+	pub message: String,
+
+	/// Result of the command for programs, if the command returns some data.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub data: Option<Value>,
+}
+
+impl Output {
+	/// Return output with the `data`.
+	fn with_data(message: impl Into<String>, data: Value) -> Self {
+		Self {
+			message: message.into(),
+			data: Some(data),
+		}
+	}
+}
+
+impl From<String> for Output {
+	fn from(message: String) -> Self {
+		Self {
+			message,
+			data: None,
+		}
+	}
+}
+
+impl From<&str> for Output {
+	fn from(message: &str) -> Self {
+		message.to_string().into()
+	}
+}
+
+impl fmt::Display for Output {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.message)
+	}
+}
+
+/// Console executing commands on a running server.
+pub struct Console {
+	/// Handle of the running server.
+	handle: ServerHandle,
+
+	/// Configuration the server is running with, it's shared with watching
+	/// the configuration file.
+	config: Arc<Mutex<Config>>,
+}
+
+impl Console {
+	/// Return a new [`Console`] for the server running with the `config`.
+	pub fn new(handle: ServerHandle, config: Arc<Mutex<Config>>) -> Self {
+		Self { handle, config }
+	}
+
+	/// Return configuration the server is running with.
+	fn config(&self) -> MutexGuard<'_, Config> {
+		lock(&self.config)
+	}
+
+	/// Parse and execute the command `line`. Return output of the command.
+	pub fn execute(&mut self, line: &str) -> Result<Output> {
+		if line.trim().is_empty() {
+			return Ok(Output::default());
+		}
+		let output = match line.parse()? {
+			Command::List => self.list(),
+			Command::Kick(name) => {
+				self.handle.kick(&name)?;
+				format!("Kicked {}", name).into()
+			}
+			Command::Ban(target) => {
+				let kicked = self.handle.ban(&target);
+				Output::with_data(
+					format!(
+						"Banned {}, disconnected {} clients",
+						target, kicked
+					),
+					json!({ "disconnected": kicked }),
+				)
+			}
+			Command::SpawnApple(coords) => {
+				let mut gamedata = self.handle.gamedata();
+				let grid = gamedata.grid();
+				if !grid.contains(coords) {
+					return Err(ServerError::OutOfGrid(coords, grid.size));
+				}
+				if gamedata.is_occupied(coords) {
+					return Err(ServerError::CellOccupied(coords));
+				}
+				gamedata.spawn_apple(coords, None)?;
+				format!("Spawned apple at {}", coords).into()
+			}
+			Command::Set(key, value) => self.set(&key, &value)?,
+			Command::Pause => {
+				self.handle.pause();
+				"Game is paused".into()
+			}
+			Command::Resume => {
+				self.handle.resume();
+				"Game is resumed".into()
+			}
+			Command::Step => {
+				self.handle.step()?;
+				let tick = self.handle.gamedata().tick();
+				Output::with_data(
+					format!("Game is on tick {}", tick),
+					json!({ "tick": tick }),
+				)
+			}
+			Command::Say(message) => {
+				info!("Admin says: {}", message);
+				self.handle.post_message(None, message.clone());
+				format!("Said {:?}", message).into()
+			}
+			Command::Save(file) => {
+				fs::write(&file, self.config().to_toml())?;
+				format!("Saved configuration to {}", file.display()).into()
+			}
+			Command::Snapshot => {
+				let grid = self.handle.gamedata().grid();
+				Output::with_data(
+					grid.to_string(),
+					serde_json::to_value(&grid)?,
+				)
+			}
+			Command::Help => Command::USAGE.into(),
+		};
+		Ok(output)
+	}
+
+	/// Parse and execute the command `line` of a remote admin. Commands
+	/// writing files on the server are refused.
+	pub fn execute_remote(&mut self, line: &str) -> Result<Output> {
+		if let Ok(Command::Save(_)) = line.parse() {
+			return Err(ServerError::LocalCommand("save"));
+		}
+		self.execute(line)
+	}
+
+	/// Return description of connected clients and snakes.
+	fn list(&self) -> Output {
+		let clients = self.handle.clients();
+		let gamedata = self.handle.gamedata();
+		let tick = gamedata.grid().tick;
+		let paused = self.handle.is_paused();
+		let mut lines = vec![format!(
+			"Tick {}{}, {} clients, {} snakes:",
+			tick,
+			if paused { " (paused)" } else { "" },
+			clients.len(),
+			gamedata.snakes()
+		)];
+		let mut snakes = vec![];
+		for (name, length) in gamedata.scoreboard() {
+			let owner = match clients
+				.iter()
+				.find(|(_, client)| client.as_deref() == Some(name.as_str()))
+			{
+				Some((address, _)) => address.to_string(),
+				None if gamedata.is_bot(&name) => "bot".into(),
+				None => "local".into(),
+			};
+			lines.push(format!("  {} ({}) of length {}", name, owner, length));
+			snakes.push(
+				json!({ "name": name, "owner": owner, "length": length }),
+			);
+		}
+		for (address, _) in clients.iter().filter(|(_, name)| name.is_none()) {
+			lines.push(format!(
+				"  {} hasn't connected to the game yet",
+				address
+			));
+		}
+		let spectators = self.handle.spectators();
+		for (address, name, follow) in &spectators {
+			lines.push(match follow {
+				Some(player) => {
+					format!("  {} ({}) is watching {}", name, address, player)
+				}
+				None => format!("  {} ({}) is watching", name, address),
+			});
+		}
+		let spectators: Vec<Value> = spectators
+			.iter()
+			.map(|(address, name, follow)| {
+				json!({
+					"address": address.to_string(),
+					"name": name,
+					"follow": follow,
+				})
+			})
+			.collect();
+		let clients: Vec<Value> = clients
+			.iter()
+			.map(
+				|(address, name)| json!({ "address": address.to_string(), "name": name }),
+			)
+			.collect();
+
+		Output::with_data(
+			lines.join("\n"),
+			json!({
+				"tick": tick,
+				"paused": paused,
+				"clients": clients,
+				"snakes": snakes,
+				"spectators": spectators,
+			}),
+		)
+	}
+
+	/// Change configuration value and apply it to the running server.
+	fn set(&mut self, key: &str, value: &str) -> Result<Output> {
+		let mut current = self.config();
+		let mut config = current.clone();
+		config.set(key, value)?;
+		config.validate()?;
+		self.handle.reload(&config)?;
+
+		let restart_required = !current.restart_keys(&config).is_empty();
+		*current = config;
+		let message = match restart_required {
+			true => {
+				format!(
+					"Set {} to {}, restart the server to apply it",
+					key, value
+				)
+			}
+			false => format!("Set {} to {}", key, value),
+		};
+		Ok(Output::with_data(
+			message,
+			json!({ "restart_required": restart_required }),
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Server;
+
+	#[test]
+	fn parse() -> Result<()> {
+		assert_eq!(" list ".parse::<Command>()?, Command::List);
+		assert_eq!(
+			"spawn-apple 3 -4".parse::<Command>()?,
+			Command::SpawnApple(Coordinates::new(3, -4))
+		);
+		assert_eq!(
+			"set  apples   3".parse::<Command>()?,
+			Command::Set("apples".into(), "3".into())
+		);
+		assert_eq!(
+			"say hello there".parse::<Command>()?,
+			Command::Say("hello there".into())
+		);
+
+		let invalid = [
+			"spawn-apple 3",
+			"spawn-apple 3 4 5",
+			"spawn-apple x y",
+			"set",
+			"set apples",
+			"kick",
+			"say  ",
+		];
+		for line in invalid {
+			let result = line.parse::<Command>();
+			assert!(
+				matches!(result, Err(ServerError::InvalidCommand(_))),
+				"{:?} should be invalid",
+				line
+			);
+		}
+		assert!(matches!(
+			"fly away".parse::<Command>(),
+			Err(ServerError::UnknownCommand(command)) if command == "fly"
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn execute() -> Result<()> {
+		let gamedata = GameData::new(Some((20, 20)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let config = Arc::new(Mutex::new(Config::default()));
+		let mut console = Console::new(server.handle(), Arc::clone(&config));
+
+		assert!(console.execute("  ")?.message.is_empty());
+
+		let output = console.execute("spawn-apple 3 4")?;
+		assert_eq!(output.message, "Spawned apple at (3, 4)");
+
+		let output = console.execute("set apples 3")?;
+		assert_eq!(output.data, Some(json!({ "restart_required": false })));
+		assert_eq!(server.handle().gamedata().settings().apples_amount, 3);
+		assert_eq!(config.lock().unwrap().settings.apples_amount, 3);
+
+		let errors = [
+			("spawn-apple 3 4", "cell (3, 4) is occupied"),
+			("spawn-apple 21 4", "(21, 4) is out of the 20x20 grid"),
+			("spawn-apple 3 0", "(3, 0) is out of the 20x20 grid"),
+		];
+		for (command, message) in errors {
+			let error = console.execute(command).unwrap_err();
+			assert_eq!(error.to_string(), message);
+		}
+
+		let output = console.execute("set grid_size 30x30")?;
+		assert_eq!(output.data, Some(json!({ "restart_required": true })));
+
+		assert!(console.execute("set nothing 1").is_err());
+		assert!(console.execute("set apples many").is_err());
+		assert!(matches!(
+			console.execute("jump"),
+			Err(ServerError::UnknownCommand(_))
+		));
+		assert!(matches!(
+			console.execute_remote("save config.toml"),
+			Err(ServerError::LocalCommand("save"))
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn control_game() -> Result<()> {
+		let gamedata = GameData::new(Some((20, 20)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let handle = server.handle();
+		let config = Arc::new(Mutex::new(Config::default()));
+		let mut console = Console::new(handle.clone(), config);
+
+		console.execute("pause")?;
+		assert!(handle.is_paused());
+		console.execute("resume")?;
+		assert!(!handle.is_paused());
+		let output = console.execute("step")?;
+		assert_eq!(output.data, Some(json!({ "tick": 1 })));
+		assert!(handle.is_paused());
+
+		let list = console.execute("list")?.data.unwrap();
+		assert_eq!(list["tick"], 1);
+		assert_eq!(list["paused"], true);
+		assert_eq!(list["clients"], json!([]));
+
+		console.execute("say hello there")?;
+		let messages = handle.messages_since(0);
+		assert_eq!(messages.last().unwrap().text, "hello there");
+
+		let snapshot = console.execute("snapshot")?.data.unwrap();
+		assert_eq!(snapshot["size"], json!([20, 20]));
+		assert_eq!(console.execute("help")?.message, Command::USAGE);
+
+		let error = console.execute("kick Mark").unwrap_err();
+		assert_eq!(error.to_string(), "client Mark isn't connected");
+		let output = console.execute("ban Mark")?;
+		assert_eq!(output.data, Some(json!({ "disconnected": 0 })));
+		assert!(handle.is_banned("Mark"));
+		Ok(())
+	}
+}
@@ -0,0 +1,50 @@
+//! Encodings of requests and responses.
+//!
+//! Client and server agree on encoding with [`Hello`](crate::handshake::Hello)
+//! request, which itself is always sent in JSON. Clients which don't send it
+//! use JSON too, so simple clients don't have to care about encodings. Binary
+//! [MessagePack](https://msgpack.org) encoding is smaller and faster to
+//! convert, it's preferred by clients of this library.
+
+use crate::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+
+/// Encoding of requests and responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+	/// Text JSON.
+	#[serde(rename = "json")]
+	Json,
+
+	/// Binary MessagePack, structures are encoded as maps with field names.
+	#[serde(rename = "msgpack")]
+	MessagePack,
+}
+
+impl Encoding {
+	/// Convert the `value` to bytes.
+	pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>> {
+		Ok(match self {
+			Self::Json => serde_json::to_vec(value)?,
+			Self::MessagePack => rmp_serde::to_vec_named(value)?,
+		})
+	}
+
+	/// Convert the `bytes` to a value.
+	pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+		Ok(match self {
+			Self::Json => serde_json::from_slice(bytes)?,
+			Self::MessagePack => rmp_serde::from_slice(bytes)?,
+		})
+	}
+}
+
+impl fmt::Display for Encoding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Json => write!(f, "json"),
+			Self::MessagePack => write!(f, "msgpack"),
+		}
+	}
+}
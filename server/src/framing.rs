@@ -0,0 +1,174 @@
+//! Message framing.
+//!
+//! Every request and response is sent as a frame: length of the message in
+//! bytes as a 4-byte big-endian unsigned integer followed by the message
+//! itself. Frames are read exactly, so a message may be split into several
+//! reads and several messages may arrive in one read.
+
+use crate::{Result, ServerError};
+use std::{
+	convert::TryFrom,
+	io::{Read, Write},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Default maximum size of a request server reads.
+pub const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// Default maximum size of a response client reads.
+pub const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Size of the frame header containing message length.
+const HEADER_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Write the `message` to the `stream` as one frame.
+pub fn write_frame(stream: &mut impl Write, message: &[u8]) -> Result<()> {
+	stream.write_all(&frame(message)?)?;
+	Ok(())
+}
+
+/// Read one frame from the `stream` and return its message. Frames longer
+/// than `max_size` bytes aren't read, the stream can't be used after that.
+pub fn read_frame(stream: &mut impl Read, max_size: usize) -> Result<Vec<u8>> {
+	let mut header = [0; HEADER_SIZE];
+	stream.read_exact(&mut header)?;
+	let mut message = vec![0; message_length(header, max_size)?];
+	stream.read_exact(&mut message)?;
+	Ok(message)
+}
+
+/// Write the `message` to the asynchronous `stream` as one frame.
+pub async fn write_frame_async(
+	stream: &mut (impl AsyncWrite + Unpin),
+	message: &[u8],
+) -> Result<()> {
+	stream.write_all(&frame(message)?).await?;
+	Ok(())
+}
+
+/// Read one frame from the asynchronous `stream` like [`read_frame`] does.
+pub async fn read_frame_async(
+	stream: &mut (impl AsyncRead + Unpin),
+	max_size: usize,
+) -> Result<Vec<u8>> {
+	let mut header = [0; HEADER_SIZE];
+	stream.read_exact(&mut header).await?;
+	let mut message = vec![0; message_length(header, max_size)?];
+	stream.read_exact(&mut message).await?;
+	Ok(message)
+}
+
+/// Return the `message` prefixed with its length.
+fn frame(message: &[u8]) -> Result<Vec<u8>> {
+	let length = u32::try_from(message.len()).map_err(|_| {
+		ServerError::FrameTooLarge(message.len(), u32::MAX as usize)
+	})?;
+	let mut frame = Vec::with_capacity(HEADER_SIZE + message.len());
+	frame.extend_from_slice(&length.to_be_bytes());
+	frame.extend_from_slice(message);
+	Ok(frame)
+}
+
+/// Return length of the message written in the frame `header` or error if
+/// it's longer than `max_size` bytes.
+fn message_length(header: [u8; HEADER_SIZE], max_size: usize) -> Result<usize> {
+	let length = u32::from_be_bytes(header) as usize;
+	if length > max_size {
+		return Err(ServerError::FrameTooLarge(length, max_size));
+	}
+	Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::{self, Cursor};
+
+	/// Reader returning at most `chunk` bytes on every read.
+	struct Chunked<R> {
+		inner: R,
+		chunk: usize,
+	}
+
+	impl<R: Read> Read for Chunked<R> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let size = buf.len().min(self.chunk);
+			self.inner.read(&mut buf[..size])
+		}
+	}
+
+	/// Return frames of the `messages` written one after another.
+	fn frames(messages: &[&[u8]]) -> Vec<u8> {
+		let mut stream = vec![];
+		for message in messages {
+			write_frame(&mut stream, message).unwrap();
+		}
+		stream
+	}
+
+	#[test]
+	fn split_frame() -> Result<()> {
+		let stream = frames(&[b"hello world"]);
+		let mut reader = Chunked {
+			inner: Cursor::new(stream),
+			chunk: 3,
+		};
+		assert_eq!(read_frame(&mut reader, 64)?, b"hello world");
+
+		Ok(())
+	}
+
+	#[test]
+	fn frames_in_one_read() -> Result<()> {
+		let mut reader = Cursor::new(frames(&[b"first", b"", b"second"]));
+		assert_eq!(read_frame(&mut reader, 64)?, b"first");
+		assert_eq!(read_frame(&mut reader, 64)?, b"");
+		assert_eq!(read_frame(&mut reader, 64)?, b"second");
+
+		Ok(())
+	}
+
+	#[test]
+	fn oversized_header() {
+		let mut reader = Cursor::new(frames(&[&[0; 65]]));
+		assert!(matches!(
+			read_frame(&mut reader, 64),
+			Err(ServerError::FrameTooLarge(65, 64))
+		));
+		// Message isn't read after the header.
+		assert_eq!(reader.position(), HEADER_SIZE as u64);
+	}
+
+	#[test]
+	fn eof_mid_frame() {
+		let mut stream = frames(&[b"hello"]);
+		stream.truncate(stream.len() - 2);
+		let mut reader = Chunked {
+			inner: Cursor::new(stream),
+			chunk: 2,
+		};
+		assert!(matches!(
+			read_frame(&mut reader, 64),
+			Err(ServerError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+		));
+		assert!(matches!(
+			read_frame(&mut Cursor::new([0, 0]), 64),
+			Err(ServerError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+		));
+	}
+
+	#[tokio::test]
+	async fn async_frames() -> Result<()> {
+		let mut stream = vec![];
+		write_frame_async(&mut stream, b"first").await?;
+		write_frame_async(&mut stream, b"second").await?;
+		assert_eq!(stream, frames(&[b"first", b"second"]));
+
+		let mut reader = &stream[..];
+		assert_eq!(read_frame_async(&mut reader, 64).await?, b"first");
+		assert_eq!(read_frame_async(&mut reader, 64).await?, b"second");
+		assert!(read_frame_async(&mut reader, 64).await.is_err());
+
+		Ok(())
+	}
+}
@@ -0,0 +1,243 @@
+//! Protocol version handshake.
+//!
+//! Before connection request client may send [`Hello`] with its protocol
+//! version, encodings and features it understands. Server answers with
+//! [`HelloResponse`] containing negotiated [`Handshake`] or a reason of
+//! rejection. Clients which don't send [`Hello`] get [`Handshake::default`].
+//! [`Hello`] and the answer to it are always encoded in JSON, next requests
+//! and responses use negotiated [`Encoding`].
+
+use crate::encoding::Encoding;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Latest protocol version server and client understand.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Oldest protocol version server still understands.
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+/// Encodings server understands, the first one is used by clients which
+/// don't say what they understand.
+pub const ENCODINGS: [Encoding; 2] = [Encoding::Json, Encoding::MessagePack];
+
+/// Optional features server supports.
+pub const FEATURES: [Feature; 6] = [
+	Feature::Deltas,
+	Feature::Chat,
+	Feature::Spectate,
+	Feature::Pause,
+	Feature::Push,
+	Feature::Udp,
+];
+
+/// Optional protocol feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+	/// Requests to get grid updates instead of the whole grid.
+	Deltas,
+
+	/// Requests to send and get chat messages.
+	Chat,
+
+	/// Request to connect as a spectator.
+	Spectate,
+
+	/// Requests to get server status and vote for pausing the game.
+	Pause,
+
+	/// Requests to subscribe to updates server pushes after every tick.
+	Push,
+
+	/// Request to open UDP transport, see [`udp`](crate::udp).
+	Udp,
+
+	/// Feature this server doesn't know about.
+	#[serde(other)]
+	Unknown,
+}
+
+impl fmt::Display for Feature {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Deltas => write!(f, "deltas"),
+			Self::Chat => write!(f, "chat"),
+			Self::Spectate => write!(f, "spectate"),
+			Self::Pause => write!(f, "pause"),
+			Self::Push => write!(f, "push"),
+			Self::Udp => write!(f, "udp"),
+			Self::Unknown => write!(f, "unknown"),
+		}
+	}
+}
+
+/// What client understands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+	/// Latest protocol version client understands.
+	pub version: u32,
+
+	/// Encodings client understands in order of preference.
+	#[serde(default)]
+	pub encodings: Vec<String>,
+
+	/// Optional features client wants to use.
+	#[serde(default)]
+	pub features: Vec<Feature>,
+}
+
+impl Default for Hello {
+	/// Return hello of a client understanding everything this library does
+	/// and preferring binary encoding.
+	fn default() -> Self {
+		Self {
+			version: PROTOCOL_VERSION,
+			encodings: [Encoding::MessagePack, Encoding::Json]
+				.iter()
+				.map(|encoding| encoding.to_string())
+				.collect(),
+			features: FEATURES.to_vec(),
+		}
+	}
+}
+
+impl Hello {
+	/// Return handshake server and client agree on or a reason why they
+	/// can't communicate.
+	pub fn negotiate(&self) -> Result<Handshake, String> {
+		if self.version < MIN_PROTOCOL_VERSION {
+			return Err(format!(
+				"protocol version {} is too old, server supports versions \
+				 from {} to {}",
+				self.version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+			));
+		}
+		let encoding = match self.encodings.is_empty() {
+			true => ENCODINGS[0],
+			false => self
+				.encodings
+				.iter()
+				.find_map(|name| {
+					ENCODINGS
+						.iter()
+						.find(|encoding| encoding.to_string() == *name)
+				})
+				.copied()
+				.ok_or_else(|| {
+					let encodings: Vec<String> = ENCODINGS
+						.iter()
+						.map(|encoding| encoding.to_string())
+						.collect();
+					format!(
+						"no supported encodings, server supports {}",
+						encodings.join(", ")
+					)
+				})?,
+		};
+		let features = FEATURES
+			.iter()
+			.filter(|feature| self.features.contains(feature))
+			.copied()
+			.collect();
+
+		Ok(Handshake {
+			version: self.version.min(PROTOCOL_VERSION),
+			encoding,
+			features,
+		})
+	}
+}
+
+/// Protocol server and client agreed on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handshake {
+	/// Protocol version to communicate with.
+	pub version: u32,
+
+	/// Encoding of requests and responses.
+	pub encoding: Encoding,
+
+	/// Optional features both server and client support.
+	pub features: Vec<Feature>,
+}
+
+impl Handshake {
+	/// Return true if the `feature` is negotiated.
+	pub fn supports(&self, feature: Feature) -> bool {
+		self.features.contains(&feature)
+	}
+}
+
+impl Default for Handshake {
+	/// Return handshake of a client which hasn't sent [`Hello`].
+	fn default() -> Self {
+		Self {
+			version: MIN_PROTOCOL_VERSION,
+			encoding: ENCODINGS[0],
+			features: FEATURES.to_vec(),
+		}
+	}
+}
+
+/// Server's answer to [`Hello`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HelloResponse {
+	/// Server accepts the client.
+	Accepted(Handshake),
+
+	/// Server can't communicate with the client for the reason in variant's
+	/// argument and closes the connection.
+	Rejected(String),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn version() {
+		let hello = Hello {
+			version: MIN_PROTOCOL_VERSION - 1,
+			..Default::default()
+		};
+		assert!(hello.negotiate().is_err());
+
+		let hello = Hello {
+			version: PROTOCOL_VERSION + 1,
+			..Default::default()
+		};
+		assert_eq!(hello.negotiate().unwrap().version, PROTOCOL_VERSION);
+	}
+
+	#[test]
+	fn encodings() {
+		let hello = |encodings: &[&str]| Hello {
+			encodings: encodings.iter().map(|name| name.to_string()).collect(),
+			..Default::default()
+		};
+		let encoding =
+			|encodings| hello(encodings).negotiate().unwrap().encoding;
+
+		assert_eq!(encoding(&["msgpack", "json"]), Encoding::MessagePack);
+		assert_eq!(encoding(&["json", "msgpack"]), Encoding::Json);
+		assert_eq!(encoding(&["cbor", "msgpack"]), Encoding::MessagePack);
+		assert_eq!(encoding(&[]), ENCODINGS[0]);
+		assert!(hello(&["cbor"]).negotiate().is_err());
+	}
+
+	#[test]
+	fn unknown_features() {
+		let hello: Hello = serde_json::from_str(
+			r#"{"version": 2, "features": ["chat", "telepathy"]}"#,
+		)
+		.unwrap();
+		assert_eq!(hello.features, [Feature::Chat, Feature::Unknown]);
+		assert!(hello.encodings.is_empty());
+
+		let handshake = hello.negotiate().unwrap();
+		assert_eq!(handshake.features, [Feature::Chat]);
+		assert!(!handshake.supports(Feature::Unknown));
+	}
+}
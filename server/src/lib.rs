@@ -18,9 +18,12 @@
 //!
 //! # Specification for clients
 //! How every client should communicate with server in short:
+//! 0. Client may send hello request to negotiate protocol version and
+//!    features
 //! 1. Client writes connection request to server's stream
 //! 2. Client reads server's stream for its name in json format
 //! 3. Client sends requests to get game grid and reads server's stream for it
+//!    or subscribes to updates server pushes after every tick
 //! 4. Client may send requests to change snake direction
 //! 5. Client sends disconnection request to server's stream
 //!
@@ -42,10 +45,54 @@
 //! ### Request
 //! Request is a binary json data with a special format. Every request has a
 //! kind (connect, disconnect, get grid and so on) and unique identifier of a
-//! client which sends it.
+//! client which sends it. Clients may negotiate binary MessagePack encoding
+//! instead of json with hello request, see [`encoding`]. Examples below are
+//! in json.
 //!
-//! There should also be put four null bytes after every request to allow server
-//! splitting many requests in a read.
+//! Every request and response is prefixed with its length in bytes written as
+//! a 4-byte big-endian unsigned integer, see [`framing`]. Server doesn't read
+//! requests longer than 64 KiB by default.
+//!
+//! #### Request to say hello
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "hello": {
+//!             "version": 2,
+//!             "encodings": ["msgpack", "json"],
+//!             "features": ["deltas", "chat", "spectate", "pause"]
+//!         }
+//!     }
+//! }
+//! ```
+//! This request may be sent before the connection request to tell server the
+//! latest protocol version, encodings in order of preference and optional
+//! features client understands. Hello and the answer to it are always in json,
+//! requests and responses after that are in the agreed encoding. Server will
+//! answer with what it agrees on:
+//! ```json
+//! {
+//!     "accepted": {
+//!         "version": 2,
+//!         "encoding": "json",
+//!         "features": ["deltas", "chat"]
+//!     }
+//! }
+//! ```
+//! Requests of features which aren't agreed on are ignored. If server can't
+//! communicate with the client, it answers with the reason and closes the
+//! connection:
+//! ```json
+//! {
+//!     "rejected": "protocol version 1 is too old, ..."
+//! }
+//! ```
+//! Hello sent after the connection request is rejected the same way.
+//! Clients which don't send this request get protocol version 2, json
+//! encoding and all features server supports. Protocol version 1 separated
+//! requests with four null bytes instead of prefixing them with length, it
+//! isn't supported anymore.
 //!
 //! #### Request to connect
 //! ```json
@@ -56,6 +103,23 @@
 //! ```
 //! This request should be sent at first and only once to authorize a client.
 //!
+//! #### Request to spectate
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "spectate": "player to follow"
+//!     }
+//! }
+//! ```
+//! This request may be sent instead of the connection request to watch the
+//! game without spawning a snake. Player to follow is just a hint for clients
+//! and may be `null`. Spectators don't take snake slots, but there's a
+//! separate limit of them. If it's reached, server closes the connection.
+//! Server answers in the same way as on the connection request, the
+//! identifier is changed if a snake, a bot or another client has such name.
+//! Spectators can't send requests to control snakes or to vote.
+//!
 //! After this request client should read server's stream for json string
 //! containing its accepted identifier. Server will send something like this:
 //! ```json
@@ -71,6 +135,168 @@
 //! ```
 //! This request should be sent constantly to get game grid.
 //!
+//! #### Request to get game grid update
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "get_grid_update": 42
+//!     }
+//! }
+//! ```
+//! There 42 is a tick of the grid client already has (every grid contains its
+//! `tick`). Server will answer with changes made since that tick:
+//! ```json
+//! {
+//!     "delta": {
+//!         "since": 42,
+//!         "tick": 43,
+//!         "added": [grid points],
+//!         "removed": [grid points]
+//!     }
+//! }
+//! ```
+//! If client is too far behind, server will send the whole grid instead:
+//! ```json
+//! {
+//!     "full": {grid}
+//! }
+//! ```
+//! This request may be sent instead of requesting the whole grid to save
+//! bandwidth.
+//!
+//! #### Request to send a chat message
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "say": "Hello!"
+//!     }
+//! }
+//! ```
+//! Message is broadcasted to everyone in the game. It can't be longer than
+//! 200 characters and every client can send only 5 messages in 10 seconds,
+//! other messages are dropped.
+//!
+//! #### Request to get chat messages
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "get_messages": 42
+//!     }
+//! }
+//! ```
+//! There 42 is an identifier of the last message client has got, it should be
+//! 0 to get the whole history. Server will answer with up to 16 following
+//! messages, system ones have no author:
+//! ```json
+//! [
+//!     {"id": 43, "author": "Mark", "text": "Hello!"},
+//!     {"id": 44, "author": null, "text": "Mark bit itself"}
+//! ]
+//! ```
+//!
+//! #### Request to get server status
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": "get_status"
+//! }
+//! ```
+//! Server will answer with the state of the game:
+//! ```json
+//! {
+//!     "tick": 42,
+//!     "paused": false,
+//!     "players": 2,
+//!     "spectators": 1,
+//!     "pause_votes": 1,
+//!     "resume_votes": 0
+//! }
+//! ```
+//! Grid isn't changed while the game is paused.
+//!
+//! #### Request to get scoreboard
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": "get_scoreboard"
+//! }
+//! ```
+//! Server will answer with names and lengths of snakes:
+//! ```json
+//! [["Mark", 5], ["Bot 1", 3]]
+//! ```
+//!
+//! #### Request to subscribe to updates
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "subscribe": 42
+//!     }
+//! }
+//! ```
+//! There `42` is a tick of the grid client already has or `null` if it has
+//! no grid. Server doesn't answer this request, instead after every tick it
+//! pushes an update to the subscribed client:
+//! ```json
+//! {
+//!     "tick": 43,
+//!     "grid": { "delta": { ... } },
+//!     "status": { "tick": 43, "paused": false, ... },
+//!     "scoreboard": [["Mark", 5]],
+//!     "messages": []
+//! }
+//! ```
+//! There `grid` is the same as the answer to a request to get grid update,
+//! the first update contains the whole grid if subscription tick is `null`.
+//! `status` and `scoreboard` are the same as answers to their requests and
+//! `messages` are new chat messages. While the game is paused updates are
+//! pushed only when something changes. Subscribed clients shouldn't send
+//! requests expecting answers because answers would be mixed with updates.
+//!
+//! #### Request to unsubscribe from updates
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": "unsubscribe"
+//! }
+//! ```
+//! Updates pushed before server handles this request still have to be read.
+//!
+//! #### Request to open UDP transport
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": "open_udp"
+//! }
+//! ```
+//! Server will answer with a ticket to send UDP packets with or `null` if UDP
+//! transport is disabled:
+//! ```json
+//! {
+//!     "port": 8787,
+//!     "token": 1234567890
+//! }
+//! ```
+//! After that client requests the grid, acknowledges its tick over UDP and
+//! gets changes of it and may send direction changes over UDP, see [`udp`].
+//!
+//! #### Request to vote for pausing the game
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "vote_pause": true
+//!     }
+//! }
+//! ```
+//! There `true` is a vote to pause the game and `false` is a vote to resume
+//! it. The game is paused or resumed when every connected player votes the
+//! same way, after that votes are reset.
+//!
 //! ### Request to change snake's direction
 //! ```json
 //! {
@@ -82,6 +308,22 @@
 //! ```
 //! This request should be sent constantly to get game grid.
 //! There "change_direction" can have "up", "down", "left" or "right" values.
+//! On the hexagonal grid "left", "right", "up_left", "up_right", "down_left"
+//! and "down_right" values are used instead, see `topology` field of the grid.
+//!
+//! ### Request to turn snake
+//! ```json
+//! {
+//!     "client": "client identifier",
+//!     "kind": {
+//!         "turn": "left"
+//!     }
+//! }
+//! ```
+//! There "turn" can have "left" or "right" values. Server turns snake 90
+//! degrees (60 degrees on the hexagonal grid) relatively to its current
+//! direction, so clients don't have to track it. Both kinds of direction
+//! changing requests may be mixed.
 //!
 //! #### Request to connect
 //! ```json
@@ -103,79 +345,126 @@
 //! ### Session
 //! When client connects to the server, a session is started. Session is a
 //! structure which contains exchange pool and server options.
+//!
+//! Every session is handled by its own task on an asynchronous runtime, so
+//! idle clients cost neither threads nor wakeups. The game is advanced on its
+//! own thread, after every tick it broadcasts what has changed to sessions of
+//! subscribed clients. Update for clients which have got the previous one is
+//! encoded once and shared by their sessions, others build their own.
 
 #![allow(clippy::unused_io_amount)]
 #![allow(missing_docs)]
 #![allow(rustdoc::missing_doc_code_examples)]
 #![allow(dead_code)]
 
-/// This is an alias for standart [`Result`](std::result::Result) type which
-/// represents failure.
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub mod admin;
+pub mod chat;
+pub mod config;
+mod connection;
+pub mod console;
+pub mod encoding;
+pub mod framing;
+pub mod handshake;
+pub mod udp;
+pub mod websocket;
 
-use game::prelude::*;
+/// This is an alias for standart [`Result`](std::result::Result) type which
+/// represents failure with [`ServerError`].
+pub type Result<T> = std::result::Result<T, ServerError>;
+
+use chat::{Chat, RateLimiter};
+use connection::{Connection, Protocol, Reader};
+use encoding::Encoding;
+use game::{error::GameError, grid::GridUpdate, prelude::*};
+use handshake::{Feature, Handshake, Hello, HelloResponse};
 use logger::*;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
 	error,
 	fmt::{self, Debug},
 	io::{self, Read, Write},
-	net::{TcpListener, TcpStream, ToSocketAddrs},
-	sync::{Arc, Mutex},
+	net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, Mutex, MutexGuard, OnceLock, PoisonError,
+	},
 	thread,
-	time::Duration,
+	time::{Duration, Instant},
+};
+use tokio::{
+	runtime::{self, Runtime},
+	sync::broadcast,
 };
 
-/// How many bytes one character is.
-const CHAR: usize = std::mem::size_of::<char>();
-
-/// How many bytes client can read from a stream at a time.
-const READ_LIMIT: usize = 1024 * 4 * CHAR;
-
-/// How many bytes can have client id.
-const CLIENT_ID_LIMIT: usize = 64 * CHAR;
-
-/// Timeout to be set to client's connection stream.
+/// Timeout of waiting for server's response to be set to client's
+/// connection stream.
 ///
 /// If duration is zero then this constant must be set to None.
-const CLIENT_READ_TIMEOUT: Option<Duration> = Some(Duration::from_millis(50));
+const CLIENT_READ_TIMEOUT: Option<Duration> = Some(Duration::from_secs(5));
+
+/// Timeout of writing to client's stream, clients which don't read it are
+/// disconnected after that.
+const SERVER_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Amount of tick updates kept for sessions which haven't handled them yet.
+/// Sessions falling behind more skip to the latest one.
+const TICKS_CAPACITY: usize = 16;
 
 /// Default delay between every server response.
 pub const GAME_DELAY: Duration = Duration::from_millis(70);
 
+/// Default maximum amount of spectators.
+pub const SPECTATORS_LIMIT: usize = 10;
+
 /// Trait which should be implemented for client abstractions.
 pub trait Client {
 	/// Connect to the server with specified address. `client` is a name of the
 	/// snake. Return stream and client name taken from server connection response.
 	fn connect<A: ToSocketAddrs + Debug>(&mut self, address: A) -> Result<()> {
-		match TcpStream::connect(&address) {
-			Ok(stream) => {
-				stream.set_read_timeout(CLIENT_READ_TIMEOUT)?;
-				self.set_stream(Some(stream));
-				Request::new(self.id().unwrap(), RequestKind::Connect)
-					.write(self.stream().unwrap())
-					.expect("writing to the server stream");
-
-				self.read_client_id()?;
+		open_connection(self, address, RequestKind::Connect)
+	}
 
-				Ok(())
+	/// Send hello request to negotiate protocol with the server and return
+	/// what server agrees on. It's sent by [`connect`](Self::connect) and
+	/// [`spectate`](Self::spectate) with [`Hello::default`]. Agreed encoding
+	/// is used for next requests.
+	fn hello(&mut self, hello: Hello) -> Result<Handshake> {
+		let request = Request::new(client_id(self)?, RequestKind::Hello(hello));
+		let max_size = self.max_response_size();
+		// Hello is always sent in json.
+		let response =
+			request.send(client_stream(self)?, Encoding::Json, max_size)?;
+
+		match response {
+			HelloResponse::Accepted(handshake) => {
+				self.set_encoding(handshake.encoding);
+				Ok(handshake)
+			}
+			HelloResponse::Rejected(reason) => {
+				Err(ServerError::Rejected(reason))
 			}
-			Err(e) => Err(Box::new(e)),
 		}
 	}
 
+	/// Connect to the server with specified address as a spectator which
+	/// doesn't spawn a snake. `follow` is a name of the player to watch.
+	fn spectate<A: ToSocketAddrs + Debug>(
+		&mut self,
+		address: A,
+		follow: Option<String>,
+	) -> Result<()> {
+		open_connection(self, address, RequestKind::Spectate(follow))
+	}
+
 	/// Parse client id after reading stream after connection request.
 	///
 	/// This function should be used to parse returned by server client's id
 	/// value after connection request.
 	fn read_client_id(&mut self) -> Result<()> {
-		let mut buffer = [0; CLIENT_ID_LIMIT];
-		self.stream().unwrap().read(&mut buffer).unwrap();
-
-		let name = String::from_utf8_lossy(&buffer);
-		let trim_pattern: &[_] = &[char::from(0), '"'];
+		let max_size = self.max_response_size();
+		let frame = framing::read_frame(client_stream(self)?, max_size)?;
 
-		self.set_id(Some(name.trim_matches(trim_pattern).to_string()));
+		self.set_id(Some(self.encoding().decode(&frame)?));
 
 		Ok(())
 	}
@@ -183,53 +472,113 @@ pub trait Client {
 	/// Send request to get game grid to server's stream, read for it and return
 	/// read value.
 	fn request_grid(&mut self) -> Result<Grid> {
-		let id = self.id().unwrap();
-		let stream = self.stream().unwrap();
+		request_response(self, RequestKind::GetGrid)
+	}
+
+	/// Send request to get changes made since the tick `grid` was built on and
+	/// apply them to the `grid`. If they can't be applied, the whole grid is
+	/// requested instead.
+	fn request_grid_update(&mut self, grid: &mut Grid) -> Result<()> {
+		let update: GridUpdate =
+			request_response(self, RequestKind::GetGridUpdate(grid.tick))?;
+
+		if let Err(e) = grid.apply(update) {
+			warn!(
+				"Failed to apply grid update, requesting the grid: {}",
+				ErrorChain(&e)
+			);
+			*grid = self.request_grid()?;
+		}
+		Ok(())
+	}
 
-		Request::new(id.clone(), RequestKind::GetGrid).write(stream)?;
+	/// Send request to get names and lengths of snakes.
+	fn request_scoreboard(&mut self) -> Result<Vec<(String, usize)>> {
+		request_response(self, RequestKind::GetScoreboard)
+	}
 
-		let mut buffer = [0; READ_LIMIT];
+	/// Send request to broadcast chat message with the `text`.
+	fn say(&mut self, text: &str) -> Result<()> {
+		send_request(self, RequestKind::Say(text.to_string()))
+	}
 
-		while let Err(e) = stream.read(&mut buffer) {
-			// WouldBlock error returned if there's read timeout. There may be
-			// read timeout if both server and client are reading, so we must
-			// force someone (in our case, client) break infinitive waiting.
-			// Thus, we handle this timeout error to handle this waiting and
-			// repeat requesting, otherwise we return error not related to
-			// reading timeout.
-			if let io::ErrorKind::WouldBlock = e.kind() {
-				buffer = [0; READ_LIMIT];
-				Request::new(id.clone(), RequestKind::GetGrid).write(stream)?;
-			} else {
-				return Err(Box::new(e));
-			}
-		}
+	/// Send request to get chat messages posted after the message with the
+	/// `since` identifier.
+	fn request_messages(&mut self, since: u64) -> Result<Vec<chat::Message>> {
+		request_response(self, RequestKind::GetMessages(since))
+	}
+
+	/// Send request to get status of the server.
+	fn request_status(&mut self) -> Result<Status> {
+		request_response(self, RequestKind::GetStatus)
+	}
+
+	/// Send request to push [`Update`]s after every game tick. `since` is a
+	/// tick of the grid client already has, if it's none, the first update
+	/// contains the whole grid. Updates should be read with
+	/// [`read_update`](Self::read_update), requests expecting responses
+	/// shouldn't be sent after subscribing.
+	fn subscribe(&mut self, since: Option<u64>) -> Result<()> {
+		send_request(self, RequestKind::Subscribe(since))
+	}
+
+	/// Send request to stop pushing updates. Already pushed ones should still
+	/// be read.
+	fn unsubscribe(&mut self) -> Result<()> {
+		send_request(self, RequestKind::Unsubscribe)
+	}
+
+	/// Read the next update pushed by the server after
+	/// [`subscribe`](Self::subscribe).
+	fn read_update(&mut self) -> Result<Update> {
+		let encoding = self.encoding();
+		let max_size = self.max_response_size();
+		Update::read(client_stream(self)?, encoding, max_size)
+	}
 
-		let string = String::from_utf8_lossy(&buffer);
+	/// Send request to open UDP transport and return client receiving
+	/// snapshots and sending direction changes over it, see [`udp`].
+	fn open_udp(&mut self) -> Result<udp::UdpClient> {
+		let ticket: Option<udp::UdpTicket> =
+			request_response(self, RequestKind::OpenUdp)?;
+		let ticket = ticket.ok_or(ServerError::UdpDisabled)?;
+		let ip = client_stream(self)?.peer_addr()?.ip();
+
+		udp::UdpClient::connect(
+			SocketAddr::new(ip, ticket.port),
+			ticket.token,
+			self.encoding(),
+		)
+	}
 
-		Grid::from_string(&string.trim_matches(char::from(0)))
+	/// Send request to vote for pausing the game if `pause` is true or for
+	/// resuming it otherwise.
+	fn vote_pause(&mut self, pause: bool) -> Result<()> {
+		send_request(self, RequestKind::VotePause(pause))
 	}
 
 	/// Send request to disconnect from the server.
 	fn disconnect(&mut self) -> Result<()> {
-		let id = self.id().unwrap();
-		let stream = self.stream().unwrap();
+		send_request(self, RequestKind::Disconnect)?;
 
-		Request::new(id, RequestKind::Disconnect).write(stream)?;
-
-		stream.flush()?;
+		client_stream(self)?.flush()?;
 
 		Ok(())
 	}
 
 	/// Send request to change snake's direction.
 	fn change_direction(&mut self, direction: Direction) -> Result<()> {
-		Request::new(
-			self.id().unwrap(),
-			RequestKind::ChangeDirection(direction),
-		)
-		.write(self.stream().unwrap())?;
-		Ok(())
+		send_request(self, RequestKind::ChangeDirection(direction))
+	}
+
+	/// Send request to turn snake relatively to its current direction.
+	fn turn(&mut self, turn: Turn) -> Result<()> {
+		send_request(self, RequestKind::Turn(turn))
+	}
+
+	/// Return maximum size of a response client reads.
+	fn max_response_size(&self) -> usize {
+		framing::MAX_RESPONSE_SIZE
 	}
 
 	/// Set client's stream.
@@ -238,14 +587,77 @@ pub trait Client {
 	/// Return mutable reference to [`server stream`](TcpStream).
 	fn stream(&mut self) -> Option<&mut TcpStream>;
 
-	/// Return cloned [`server stream`](TcpStream).
-	fn stream_clone(&self) -> Option<TcpStream>;
+	/// Return cloned [`server stream`](TcpStream). Return error if client
+	/// isn't connected or the stream can't be cloned.
+	fn stream_clone(&self) -> Result<TcpStream>;
 
 	/// Set client's identifier.
 	fn set_id(&mut self, id: Option<String>);
 
 	/// Return client's identifier.
 	fn id(&self) -> Option<String>;
+
+	/// Set encoding of requests and responses agreed on with the server.
+	fn set_encoding(&mut self, encoding: Encoding);
+
+	/// Return encoding of requests and responses.
+	fn encoding(&self) -> Encoding;
+}
+
+/// Connect the `client` to the server with the `address` sending the
+/// connection request of the `kind` and read its identifier.
+fn open_connection<C: Client + ?Sized, A: ToSocketAddrs>(
+	client: &mut C,
+	address: A,
+	kind: RequestKind,
+) -> Result<()> {
+	let stream = TcpStream::connect(&address)?;
+	stream.set_read_timeout(CLIENT_READ_TIMEOUT)?;
+	client.set_stream(Some(stream));
+	client.hello(Hello::default())?;
+	send_request(client, kind)?;
+
+	client.read_client_id()
+}
+
+/// Send request of the `kind` from the `client` without reading a response.
+fn send_request<C: Client + ?Sized>(
+	client: &mut C,
+	kind: RequestKind,
+) -> Result<()> {
+	let encoding = client.encoding();
+	Request::new(client_id(client)?, kind)
+		.write(client_stream(client)?, encoding)
+}
+
+/// Send request of the `kind` from the `client` and return its response.
+fn request_response<C: Client + ?Sized, T: DeserializeOwned>(
+	client: &mut C,
+	kind: RequestKind,
+) -> Result<T> {
+	let encoding = client.encoding();
+	let max_size = client.max_response_size();
+	Request::new(client_id(client)?, kind).send(
+		client_stream(client)?,
+		encoding,
+		max_size,
+	)
+}
+
+/// Return identifier of the `client` or error if it has no one.
+fn client_id<C: Client + ?Sized>(client: &C) -> Result<String> {
+	client.id().ok_or(ServerError::NoClientId)
+}
+
+/// Return stream of the `client` or error if it isn't connected.
+fn client_stream<C: Client + ?Sized>(client: &mut C) -> Result<&mut TcpStream> {
+	client.stream().ok_or(ServerError::NoStream)
+}
+
+/// Lock the `mutex` even if a thread has panicked holding it, so one failed
+/// request doesn't stop the whole server.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 /// Run server with specified address and [`GameData`].
@@ -256,67 +668,994 @@ pub fn run<A: ToSocketAddrs>(
 	gamedata: GameData,
 	game_delay: Option<Duration>,
 ) -> Result<()> {
-	let listener = TcpListener::bind(address)?;
-	let gamedata = Arc::new(Mutex::new(gamedata));
-	let game_delay = game_delay.map_or(GAME_DELAY, |d| d);
-
-	// Update GameData in a separate thread
-	let gamedata_clone = gamedata.clone();
-	thread::Builder::new()
-		.name("GameData handler".into())
-		.spawn(move || {
-			let gamedata =
-				|| gamedata_clone.lock().expect("acquiring mutex lock");
-			loop {
-				gamedata().kill_dead_snakes();
-				gamedata().check_apples().expect("checking apples");
-				gamedata().update_grid().expect("updating game grid");
-				thread::sleep(game_delay);
-			}
-		})?;
-
-	// Start handling connections
+	Server::bind(address, gamedata, game_delay)?.run()
+}
+
+/// Server bound to an address but not running yet.
+pub struct Server {
+	/// Listener accepting client connections.
+	listener: TcpListener,
+
+	/// Runtime handling clients.
+	runtime: Runtime,
+
+	/// Handle to change the game while the server is running.
+	handle: ServerHandle,
+}
+
+impl Server {
+	/// Bind a new server to the `address`. Arguments are the same as in
+	/// [`run`].
+	pub fn bind<A: ToSocketAddrs>(
+		address: A,
+		gamedata: GameData,
+		game_delay: Option<Duration>,
+	) -> Result<Self> {
+		let runtime = runtime::Builder::new_multi_thread()
+			.thread_name("Client handler")
+			.enable_all()
+			.build()?;
+		let listener = TcpListener::bind(address)?;
+		listener.set_nonblocking(true)?;
+		Ok(Self {
+			listener,
+			handle: ServerHandle {
+				runtime: runtime.handle().clone(),
+				gamedata: Arc::new(Mutex::new(gamedata)),
+				game_delay: Arc::new(Mutex::new(
+					game_delay.unwrap_or(GAME_DELAY),
+				)),
+				paused: Arc::new(AtomicBool::new(false)),
+				spectators_limit: Arc::new(AtomicUsize::new(SPECTATORS_LIMIT)),
+				max_request_size: Arc::new(AtomicUsize::new(
+					framing::MAX_REQUEST_SIZE,
+				)),
+				clients: Arc::new(Mutex::new(vec![])),
+				bans: Arc::new(Mutex::new(vec![])),
+				chat: Arc::new(Mutex::new(Chat::default())),
+				udp: Arc::new(Mutex::new(None)),
+				ticks: broadcast::channel(TICKS_CAPACITY).0,
+			},
+			runtime,
+		})
+	}
+
+	/// Return address the server is bound to.
+	pub fn local_addr(&self) -> Result<SocketAddr> {
+		Ok(self.listener.local_addr()?)
+	}
+
+	/// Return handle to change the game while the server is running.
+	pub fn handle(&self) -> ServerHandle {
+		self.handle.clone()
+	}
+
+	/// Run the server. This function returns only on error.
+	pub fn run(self) -> Result<()> {
+		let Self {
+			listener,
+			runtime,
+			handle,
+		} = self;
+		let game = handle.clone();
+		thread::Builder::new()
+			.name("Game".to_string())
+			.spawn(move || run_game(game))?;
+		runtime.block_on(accept(listener, handle, Protocol::Tcp))
+	}
+}
+
+/// Advance the game of the server with the `handle` and broadcast what has
+/// changed after every tick. It's run on its own thread, so ticks neither
+/// block nor wait for client handlers.
+fn run_game(handle: ServerHandle) {
+	let mut previous: Option<Arc<TickUpdate>> = None;
+	loop {
+		if !handle.is_paused() {
+			if let Err(e) = handle.tick() {
+				error!("Failed to advance the game: {}", ErrorChain(&e));
+			}
+		}
+		if let Some(update) = handle.broadcast_tick(previous.as_deref()) {
+			previous = Some(update);
+		}
+		handle.push_snapshots();
+		thread::sleep(handle.game_delay());
+	}
+}
+
+/// Accept connections of clients using the `protocol` on the `listener` and
+/// handle every one in its own task.
+async fn accept(
+	listener: TcpListener,
+	handle: ServerHandle,
+	protocol: Protocol,
+) -> Result<()> {
+	let listener = tokio::net::TcpListener::from_std(listener)?;
 	loop {
-		let (socket, address) = match listener.accept() {
+		let (socket, address) = match listener.accept().await {
 			Ok(val) => val,
 			Err(e) => {
 				error!("Failed to accept incoming connection: {}", e);
 				continue;
 			}
 		};
-		// Handle client in a separate thread
-		let gamedata = gamedata.clone();
-		thread::Builder::new()
-			.name(format!("{}'s handler", address))
-			.spawn(move || match handle_client(socket, gamedata) {
-				Ok(_) => info!("Successfully handled client {}", address),
+		spawn_handler(socket, address, &handle, protocol);
+	}
+}
+
+/// Handle to change the game of a running [`Server`].
+#[derive(Debug, Clone)]
+pub struct ServerHandle {
+	/// Runtime of the server handling clients.
+	runtime: runtime::Handle,
+	/// Game shared with client handlers.
+	gamedata: Arc<Mutex<GameData>>,
+
+	/// Delay between game ticks.
+	game_delay: Arc<Mutex<Duration>>,
+
+	/// Is the game paused or not.
+	paused: Arc<AtomicBool>,
+
+	/// Maximum amount of spectators.
+	spectators_limit: Arc<AtomicUsize>,
+
+	/// Maximum size of a request in bytes.
+	max_request_size: Arc<AtomicUsize>,
+
+	/// Connected clients.
+	clients: Arc<Mutex<Vec<ClientInfo>>>,
+
+	/// Banned client names and ip addresses.
+	bans: Arc<Mutex<Vec<String>>>,
+
+	/// Chat shared with client handlers.
+	chat: Arc<Mutex<Chat>>,
+
+	/// Socket snapshots are sent from, none if UDP transport is disabled.
+	udp: Arc<Mutex<Option<UdpSocket>>>,
+
+	/// Sender of changes made every tick to sessions of subscribed clients.
+	ticks: broadcast::Sender<Arc<TickUpdate>>,
+}
+
+impl ServerHandle {
+	/// Lock and return the game.
+	pub fn gamedata(&self) -> MutexGuard<'_, GameData> {
+		lock(&self.gamedata)
+	}
+
+	/// Return delay between game ticks.
+	pub fn game_delay(&self) -> Duration {
+		*lock(&self.game_delay)
+	}
+
+	/// Set delay between game ticks. It's used since the next tick.
+	pub fn set_game_delay(&self, delay: Duration) {
+		*lock(&self.game_delay) = delay;
+	}
+
+	/// Apply game settings, bots and delay of the `config` to the running game.
+	/// Values which can't be changed live are ignored, see
+	/// [`Config::restart_keys`](config::Config::restart_keys).
+	pub fn reload(&self, config: &config::Config) -> Result<()> {
+		let mut gamedata = self.gamedata();
+		let settings = Settings {
+			topology: gamedata.settings().topology,
+			..config.settings.clone()
+		};
+		let bots = config.new_bots();
+		// Everything is checked before applying, so the game isn't changed
+		// partially if some value can't be applied.
+		gamedata.check_settings(&settings)?;
+		gamedata.check_bots(&bots)?;
+		gamedata.set_bots(bots)?;
+		gamedata.set_settings(settings)?;
+		if gamedata.is_overfull() {
+			warn!(
+				"{} snakes are in the game while {} are allowed, no new ones \
+				 join until some die",
+				gamedata.snakes(),
+				config.settings.snakes_amount
+			);
+		}
+		drop(gamedata);
+		self.set_game_delay(config.delay);
+		self.set_spectators_limit(config.spectators);
+		self.set_max_request_size(config.max_request_size);
+		Ok(())
+	}
+
+	/// Set maximum amount of spectators. Already connected ones aren't
+	/// disconnected if there're more of them.
+	pub fn set_spectators_limit(&self, limit: usize) {
+		self.spectators_limit.store(limit, Ordering::SeqCst);
+	}
+
+	/// Set maximum size of a request in bytes. Clients sending larger ones
+	/// are disconnected.
+	pub fn set_max_request_size(&self, size: usize) {
+		self.max_request_size.store(size, Ordering::SeqCst);
+	}
+
+	/// Return maximum size of a request in bytes.
+	pub fn max_request_size(&self) -> usize {
+		self.max_request_size.load(Ordering::SeqCst)
+	}
+
+	/// Advance the game on one tick and respawn bots.
+	pub fn tick(&self) -> Result<()> {
+		let mut gamedata = self.gamedata();
+		let killed = gamedata.advance()?;
+		for (snake, cause) in &killed {
+			debug!("Snake {} {}", snake.name(), cause);
+			self.post_message(None, format!("{} {}", snake.name(), cause));
+		}
+		if !killed.is_empty() && gamedata.snakes() == 1 {
+			if let Some((winner, _)) = gamedata.scoreboard().pop() {
+				self.post_message(
+					None,
+					format!("{} is the last snake alive", winner),
+				);
+			}
+		}
+		gamedata.respawn_bots()?;
+		Ok(())
+	}
+
+	/// Add chat message with the `text` from the `author` or a system message
+	/// if `author` is none.
+	pub fn post_message(&self, author: Option<String>, text: String) {
+		let mut chat = lock(&self.chat);
+		let message = chat.post(author, text);
+		debug!("Chat: {}", message);
+	}
+
+	/// Return chat messages posted after the message with the `since`
+	/// identifier.
+	pub fn messages_since(&self, since: u64) -> Vec<chat::Message> {
+		lock(&self.chat).messages_since(since)
+	}
+
+	/// Pause the game if it isn't paused and advance it on one tick.
+	pub fn step(&self) -> Result<()> {
+		self.pause();
+		self.tick()
+	}
+
+	/// Stop advancing the game until it's resumed. Players' votes are reset.
+	pub fn pause(&self) {
+		self.set_paused(true);
+	}
+
+	/// Continue advancing the paused game. Players' votes are reset.
+	pub fn resume(&self) {
+		self.set_paused(false);
+	}
+
+	/// Pause or resume the game and reset players' votes.
+	fn set_paused(&self, paused: bool) {
+		for client in self.lock_clients().iter_mut() {
+			client.pause_vote = None;
+		}
+		self.paused.store(paused, Ordering::SeqCst);
+	}
+
+	/// Return true if the game is paused.
+	pub fn is_paused(&self) -> bool {
+		self.paused.load(Ordering::SeqCst)
+	}
+
+	/// Return status of the game reported to clients.
+	pub fn status(&self) -> Status {
+		let tick = self.gamedata().tick();
+		let clients = self.lock_clients();
+		let players = clients.iter().filter(|client| client.is_player());
+		let spectators = clients.iter().filter(|client| client.spectator);
+		let votes = |vote| {
+			clients
+				.iter()
+				.filter(|client| client.pause_vote == Some(vote))
+				.count()
+		};
+		Status {
+			tick,
+			paused: self.is_paused(),
+			players: players.count(),
+			spectators: spectators.count(),
+			pause_votes: votes(true),
+			resume_votes: votes(false),
+		}
+	}
+
+	/// Record vote of the client connected from the `address` for pausing
+	/// the game if `pause` is true or for resuming it otherwise.
+	fn vote_pause(&self, address: SocketAddr, pause: bool) {
+		if let Some(client) = self
+			.lock_clients()
+			.iter_mut()
+			.find(|client| client.address == address)
+		{
+			client.pause_vote = Some(pause);
+		}
+		self.apply_votes();
+	}
+
+	/// Pause or resume the game if every player has voted for it.
+	fn apply_votes(&self) {
+		let vote = {
+			let clients = self.lock_clients();
+			let mut votes = clients
+				.iter()
+				.filter(|client| client.is_player())
+				.map(|client| client.pause_vote);
+			match votes.next() {
+				Some(Some(vote)) if votes.all(|other| other == Some(vote)) => {
+					vote
+				}
+				_ => return,
+			}
+		};
+		match vote {
+			true => info!("Players have voted to pause the game"),
+			false => info!("Players have voted to resume the game"),
+		}
+		self.set_paused(vote);
+	}
+
+	/// Broadcast [`TickUpdate`] to sessions of subscribed clients if the
+	/// game has changed since the `previous` one. Return the broadcasted
+	/// update.
+	fn broadcast_tick(
+		&self,
+		previous: Option<&TickUpdate>,
+	) -> Option<Arc<TickUpdate>> {
+		let update = self.tick_update(previous);
+		if let Some(previous) = previous {
+			if previous.update.tick == update.update.tick
+				&& previous.update.status == update.update.status
+				&& previous.message == update.message
+			{
+				return None;
+			}
+		}
+		let update = Arc::new(update);
+		// It's fine if nobody is subscribed.
+		let _ = self.ticks.send(Arc::clone(&update));
+		Some(update)
+	}
+
+	/// Return the current state of the game with changes made since the
+	/// `previous` update, no changes if it's none.
+	fn tick_update(&self, previous: Option<&TickUpdate>) -> TickUpdate {
+		let status = self.status();
+		let gamedata = self.gamedata();
+		let tick = gamedata.tick();
+		let chat = lock(&self.chat);
+		let (since, messages_since) = match previous {
+			Some(previous) => (previous.update.tick, previous.message),
+			None => (tick, chat.last_id()),
+		};
+		let grid = gamedata.grid_update(since);
+		let messages = chat.messages_since(messages_since);
+		// Messages exceeding the fetch limit are sent with the next update.
+		let message = messages.last().map_or(messages_since, |m| m.id);
+		TickUpdate {
+			update: Update {
+				tick: grid.tick(),
+				grid,
+				status,
+				scoreboard: gamedata.scoreboard(),
+				messages,
+			},
+			since,
+			messages_since,
+			message,
+			json: OnceLock::new(),
+			msgpack: OnceLock::new(),
+		}
+	}
+
+	/// Send [`Snapshot`](udp::Snapshot)s to clients using UDP transport which
+	/// haven't acknowledged the current tick yet.
+	pub fn push_snapshots(&self) {
+		let socket = lock(&self.udp);
+		let socket = match socket.as_ref() {
+			Some(socket) => socket,
+			None => return,
+		};
+		let snapshots: Vec<_> = {
+			let gamedata = self.gamedata();
+			let mut clients = self.lock_clients();
+			let tick = gamedata.tick();
+			clients
+				.iter_mut()
+				.filter_map(|client| {
+					let peer = client.udp.as_mut()?;
+					let address = peer.address?;
+					// Client without grid requests it over TCP.
+					let since = peer.ack?;
+					if since == tick {
+						return None;
+					}
+					let grid = gamedata.grid_delta(since);
+					peer.sequence += 1;
+					let snapshot = udp::Snapshot {
+						sequence: peer.sequence,
+						tick,
+						input: peer.input,
+						grid,
+					};
+					Some((address, peer.encoding, snapshot))
+				})
+				.collect()
+		};
+		for (address, encoding, snapshot) in snapshots {
+			if let Err(e) = snapshot.send(socket, address, encoding) {
+				debug!(
+					"Failed to send snapshot to {}: {}",
+					address,
+					ErrorChain(&e)
+				);
+			}
+		}
+	}
+
+	/// Set socket snapshots are sent from, see [`udp::UdpServer`].
+	pub(crate) fn set_udp_socket(&self, socket: UdpSocket) {
+		*lock(&self.udp) = Some(socket);
+	}
+
+	/// Let the client connected from the `address` use UDP transport with
+	/// the `encoding`. Return none if it's disabled.
+	fn open_udp(
+		&self,
+		address: SocketAddr,
+		encoding: Encoding,
+	) -> Option<udp::UdpTicket> {
+		let port = {
+			let socket = lock(&self.udp);
+			socket.as_ref()?.local_addr().ok()?.port()
+		};
+		let token = rand::random();
+		let mut clients = self.lock_clients();
+		let client = clients
+			.iter_mut()
+			.find(|client| client.address == address)?;
+		client.udp = Some(UdpPeer {
+			token,
+			address: None,
+			ack: None,
+			sequence: 0,
+			input: 0,
+			encoding,
+		});
+		Some(udp::UdpTicket { port, token })
+	}
+
+	/// Handle UDP `packet` received from the `address`: bind the address to
+	/// the client with packet's token, record acknowledged tick and apply
+	/// direction changes which haven't been applied yet.
+	fn receive_input(&self, packet: &[u8], address: SocketAddr) -> Result<()> {
+		let (token, input) =
+			udp::split_token(packet).ok_or(ServerError::UnknownToken)?;
+		let (name, commands) = {
+			let mut clients = self.lock_clients();
+			let client = clients
+				.iter_mut()
+				.find(|client| {
+					client.udp.as_ref().map(|peer| peer.token) == Some(token)
+				})
+				.ok_or(ServerError::UnknownToken)?;
+			let name = client.name.clone().filter(|_| !client.spectator);
+			let peer = client.udp.as_mut().ok_or(ServerError::UnknownToken)?;
+			let input: udp::Input = peer.encoding.decode(input)?;
+			peer.address = Some(address);
+			peer.ack = peer.ack.max(input.ack);
+			let mut commands: Vec<_> = input
+				.commands
+				.into_iter()
+				.filter(|command| command.sequence > peer.input)
+				.collect();
+			commands.sort_by_key(|command| command.sequence);
+			if let Some(command) = commands.last() {
+				peer.input = command.sequence;
+			}
+			match name {
+				Some(name) => (name, commands),
+				// Spectators have no snakes to control.
+				None => return Ok(()),
+			}
+		};
+		let mut gamedata = self.gamedata();
+		let tick = gamedata.tick();
+		for command in commands {
+			let result = gamedata
+				.snake_mut(name.clone())
+				.and_then(|snake| snake.change_direction(command.direction));
+			match result {
+				Ok(()) => debug!(
+					"{} changed direction to {} on tick {} over UDP, it's \
+					 applied on tick {}",
+					name, command.direction, command.tick, tick
+				),
 				Err(e) => {
-					error!("Failed to handle client \"{}\": {}", address, e)
+					debug!(
+						"Failed to change direction of {}: {}",
+						name,
+						ErrorChain(&e)
+					)
 				}
-			})?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Return addresses of connected clients with names of their snakes if
+	/// they have already sent connection request. Spectators aren't
+	/// included, see [`spectators`](Self::spectators).
+	pub fn clients(&self) -> Vec<(SocketAddr, Option<String>)> {
+		self.lock_clients()
+			.iter()
+			.filter(|client| !client.spectator)
+			.map(|client| (client.address, client.name.clone()))
+			.collect()
 	}
+
+	/// Return addresses, names and followed players of connected spectators.
+	pub fn spectators(&self) -> Vec<(SocketAddr, String, Option<String>)> {
+		self.lock_clients()
+			.iter()
+			.filter(|client| client.spectator)
+			.filter_map(|client| {
+				let name = client.name.clone()?;
+				Some((client.address, name, client.follow.clone()))
+			})
+			.collect()
+	}
+
+	/// Disconnect client with the `name` and kill its snake.
+	pub fn kick(&self, name: &str) -> Result<()> {
+		let is_player = self
+			.lock_clients()
+			.iter()
+			.any(|client| client.is_player() && client.matches(name));
+		if self.disconnect(|client| client.name.as_deref() == Some(name)) == 0 {
+			return Err(ServerError::ClientNotFound(name.to_string()));
+		}
+		let mut gamedata = self.gamedata();
+		if is_player && gamedata.find_snake(name) {
+			gamedata.kill_snake(name)?;
+		}
+		Ok(())
+	}
+
+	/// Forbid connecting with the `target` name or from the `target` ip
+	/// address and disconnect matching clients. Return amount of disconnected
+	/// clients.
+	pub fn ban(&self, target: &str) -> usize {
+		lock(&self.bans).push(target.to_string());
+		let names: Vec<String> = self
+			.lock_clients()
+			.iter()
+			.filter(|client| client.is_player() && client.matches(target))
+			.filter_map(|client| client.name.clone())
+			.collect();
+		let kicked = self.disconnect(|client| client.matches(target));
+		let mut gamedata = self.gamedata();
+		for name in names {
+			if gamedata.find_snake(&name) {
+				let _ = gamedata.kill_snake(name);
+			}
+		}
+		kicked
+	}
+
+	/// Return true if client name or ip address `target` is banned.
+	pub fn is_banned(&self, target: &str) -> bool {
+		lock(&self.bans).iter().any(|ban| ban == target)
+	}
+
+	/// Shut down streams of clients matching the `predicate`. Their handlers
+	/// stop after that. Return amount of disconnected clients.
+	fn disconnect(&self, predicate: impl Fn(&ClientInfo) -> bool) -> usize {
+		let clients = self.lock_clients();
+		let matching = clients.iter().filter(|client| predicate(client));
+		let mut count = 0;
+		for client in matching {
+			client.connection.shutdown();
+			count += 1;
+		}
+		count
+	}
+
+	/// Add client connected from the `address` with the `connection` to the
+	/// list of clients.
+	fn register(&self, address: SocketAddr, connection: Connection) {
+		self.lock_clients().push(ClientInfo {
+			address,
+			name: None,
+			connection,
+			pause_vote: None,
+			spectator: false,
+			follow: None,
+			udp: None,
+		});
+	}
+
+	/// Set snake name of the client connected from the `address`.
+	fn set_client_name(&self, address: SocketAddr, name: String) {
+		let mut clients = self.lock_clients();
+		if let Some(client) =
+			clients.iter_mut().find(|client| client.address == address)
+		{
+			client.name = Some(name);
+		}
+	}
+
+	/// Return name of the client connected from the `address`.
+	fn client_name(&self, address: SocketAddr) -> Option<String> {
+		self.lock_clients()
+			.iter()
+			.find(|client| client.address == address)
+			.and_then(|client| client.name.clone())
+	}
+
+	/// Return the `name` if no snake, bot or client has it, or the `name` with
+	/// a number appended otherwise.
+	fn unique_name(&self, gamedata: &GameData, name: &str) -> String {
+		let clients = self.lock_clients();
+		let is_taken = |name: &str| {
+			gamedata.find_snake(name)
+				|| gamedata.is_bot(name)
+				|| clients
+					.iter()
+					.any(|client| client.name.as_deref() == Some(name))
+		};
+		let mut unique = name.to_string();
+		let mut number = gamedata.snakes();
+		while is_taken(&unique) {
+			unique = format!("{} ({})", name, number);
+			number += 1;
+		}
+		unique
+	}
+
+	/// Make client connected from the `address` a spectator with the `name`
+	/// following the `follow` player. Return false if there're too many
+	/// spectators already.
+	fn add_spectator(
+		&self,
+		address: SocketAddr,
+		name: String,
+		follow: Option<String>,
+	) -> bool {
+		let mut clients = self.lock_clients();
+		let spectators =
+			clients.iter().filter(|client| client.spectator).count();
+		if spectators >= self.spectators_limit.load(Ordering::SeqCst) {
+			return false;
+		}
+		if let Some(client) =
+			clients.iter_mut().find(|client| client.address == address)
+		{
+			client.name = Some(name);
+			client.spectator = true;
+			client.follow = follow;
+		}
+		true
+	}
+
+	/// Remove client connected from the `address` from the list of clients.
+	/// Remaining players may agree on pausing the game after that.
+	fn unregister(&self, address: SocketAddr) {
+		let left = {
+			let mut clients = self.lock_clients();
+			let left = clients
+				.iter()
+				.find(|client| client.address == address && client.is_player())
+				.and_then(|client| client.name.clone());
+			clients.retain(|client| client.address != address);
+			left
+		};
+		if let Some(name) = left {
+			self.post_message(None, format!("{} left the game", name));
+		}
+		self.apply_votes();
+	}
+
+	/// Lock and return connected clients.
+	fn lock_clients(&self) -> MutexGuard<'_, Vec<ClientInfo>> {
+		lock(&self.clients)
+	}
+}
+
+/// Client connected to the server.
+#[derive(Debug)]
+struct ClientInfo {
+	/// Address client is connected from.
+	address: SocketAddr,
+
+	/// Name of client's snake, none until connection request is handled.
+	name: Option<String>,
+
+	/// Connection shared with client's handler, it's used to push updates
+	/// and to disconnect client.
+	connection: Connection,
+
+	/// Whether client wants to pause or resume the game, none if it hasn't
+	/// voted.
+	pause_vote: Option<bool>,
+
+	/// Is client a spectator or not.
+	spectator: bool,
+
+	/// Name of the player spectator follows.
+	follow: Option<String>,
+
+	/// UDP transport of client, none if it isn't opened.
+	udp: Option<UdpPeer>,
+}
+
+/// What subscribed client has already got.
+#[derive(Debug)]
+struct Subscription {
+	/// Tick of client's grid, none if it has no grid.
+	tick: Option<u64>,
+
+	/// Last status pushed to client.
+	status: Option<Status>,
+
+	/// Identifier of the last chat message pushed to client.
+	message: u64,
+
+	/// Receiver of changes made every tick.
+	ticks: broadcast::Receiver<Arc<TickUpdate>>,
+}
+
+/// Changes of the game broadcasted to sessions of subscribed clients.
+#[derive(Debug)]
+struct TickUpdate {
+	/// Update for clients which have got the previous one.
+	update: Update,
+
+	/// Tick the grid changes are made since.
+	since: u64,
+
+	/// Identifier of the chat message the update's messages are posted after.
+	messages_since: u64,
+
+	/// Identifier of the last chat message included in the update or
+	/// `messages_since` if it includes none.
+	message: u64,
+
+	/// The update encoded in json, it's encoded once when it's needed.
+	json: OnceLock<Arc<[u8]>>,
+
+	/// The update encoded in MessagePack, it's encoded once when it's needed.
+	msgpack: OnceLock<Arc<[u8]>>,
+}
+
+impl TickUpdate {
+	/// Return the update encoded in the `encoding`, it's shared by all
+	/// sessions.
+	fn encoded(&self, encoding: Encoding) -> Result<Arc<[u8]>> {
+		let cell = match encoding {
+			Encoding::Json => &self.json,
+			Encoding::MessagePack => &self.msgpack,
+		};
+		if let Some(bytes) = cell.get() {
+			return Ok(Arc::clone(bytes));
+		}
+		let bytes = encoding.encode(&self.update)?.into();
+		Ok(Arc::clone(cell.get_or_init(|| bytes)))
+	}
+}
+
+/// What client using UDP transport has sent and got.
+#[derive(Debug)]
+struct UdpPeer {
+	/// Token client's inputs are prefixed with.
+	token: u64,
+
+	/// Address client sends inputs from, none until the first one arrives.
+	address: Option<SocketAddr>,
+
+	/// Tick of client's grid, none if it has no grid.
+	ack: Option<u64>,
+
+	/// Sequence number of the last snapshot sent to client.
+	sequence: u64,
+
+	/// Sequence number of the last direction change applied.
+	input: u64,
+
+	/// Encoding of snapshots and inputs.
+	encoding: Encoding,
+}
+
+/// State of the game reported to clients, see [`Client::request_status`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+	/// Current game tick.
+	pub tick: u64,
+
+	/// Is the game paused or not.
+	pub paused: bool,
+
+	/// Amount of connected players.
+	pub players: usize,
+
+	/// Amount of connected spectators.
+	pub spectators: usize,
+
+	/// Amount of players voted to pause the game.
+	pub pause_votes: usize,
+
+	/// Amount of players voted to resume the game.
+	pub resume_votes: usize,
+}
+
+/// State of the game pushed to subscribed clients after every tick, see
+/// [`Client::subscribe`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Update {
+	/// Game tick the update is made on.
+	pub tick: u64,
+
+	/// Changes of the grid since the previous update.
+	pub grid: GridUpdate,
+
+	/// Status of the game.
+	pub status: Status,
+
+	/// Names and lengths of snakes.
+	pub scoreboard: Vec<(String, usize)>,
+
+	/// Chat messages posted since the previous update.
+	pub messages: Vec<chat::Message>,
+}
+
+impl Update {
+	/// Write the update in the `encoding` to the `connection`.
+	fn write(&self, connection: &Connection, encoding: Encoding) -> Result<()> {
+		connection.write(encoding.encode(self)?, encoding)
+	}
+
+	/// Read an update in the `encoding` from the `stream`. Updates longer
+	/// than `max_size` bytes aren't read.
+	pub fn read(
+		stream: &mut impl Read,
+		encoding: Encoding,
+		max_size: usize,
+	) -> Result<Self> {
+		let frame = framing::read_frame(stream, max_size)?;
+		encoding.decode(&frame)
+	}
+}
+
+impl ClientInfo {
+	/// Return true if client's name or ip address is the `target`.
+	fn matches(&self, target: &str) -> bool {
+		self.name.as_deref() == Some(target)
+			|| self.address.ip().to_string() == target
+	}
+
+	/// Return true if client has connected to the game as a player.
+	fn is_player(&self) -> bool {
+		self.name.is_some() && !self.spectator
+	}
+}
+
+/// Handle client connected to server from the `address` in a separate task.
+/// Connection with the `protocol` is opened on the `socket` in that task.
+fn spawn_handler(
+	socket: tokio::net::TcpStream,
+	address: SocketAddr,
+	handle: &ServerHandle,
+	protocol: Protocol,
+) {
+	if handle.is_banned(&address.ip().to_string()) {
+		info!("Refused connection of banned client {}", address);
+		return;
+	}
+	let handle = handle.clone();
+	// The task must be Send, so it can't hold guards of std mutexes across
+	// awaits: they're locked only inside synchronous session methods.
+	tokio::spawn(async move {
+		let max_size = handle.max_request_size();
+		let result = match Connection::open(socket, protocol, max_size).await {
+			Ok((connection, reader)) => {
+				handle_client(connection, reader, address, handle).await
+			}
+			Err(e) => Err(e),
+		};
+		match result {
+			Ok(_) => info!("Successfully handled client {}", address),
+			Err(e) => error!(
+				"Failed to handle client \"{}\": {}",
+				address,
+				ErrorChain(&e)
+			),
+		}
+	});
 }
 
-/// Handle client connected to server.
-/// `delay` is a delay between every request, it may be used to slow down the
-/// game.
-fn handle_client(
-	stream: TcpStream,
-	gamedata: Arc<Mutex<GameData>>,
+/// Handle client connected to server from the `address`, its requests are
+/// read from the `reader`.
+async fn handle_client(
+	connection: Connection,
+	reader: Reader,
+	address: SocketAddr,
+	handle: ServerHandle,
 ) -> Result<()> {
-	let mut session = Session::new(stream, gamedata.clone());
+	handle.register(address, connection.clone());
+	let mut session = Session::new(connection.clone(), address, handle.clone());
+	// Reading is kept between iterations to not lose partially read frames.
+	let mut read = Box::pin(read_request(reader, handle.max_request_size()));
 
 	loop {
-		if session.wait().is_err() {
-			continue;
+		let frame = tokio::select! {
+			(reader, frame) = &mut read => {
+				read = Box::pin(read_request(reader, handle.max_request_size()));
+				frame
+			}
+			Some(update) = session.next_tick() => {
+				if let Err(e) = session.push_update(&update) {
+					warn!(
+						"Failed to push update to client {}: {}",
+						address,
+						ErrorChain(&e)
+					);
+					break;
+				}
+				continue;
+			}
+			_ = connection.closed() => break,
+		};
+
+		match frame.and_then(|frame| session.receive(&frame)) {
+			// Connection is closed by client or kicked by admin.
+			Err(ServerError::Io(_)) => break,
+			Err(e @ ServerError::WebSocket(_)) => {
+				warn!(
+					"Disconnected WebSocket client {}: {}",
+					address,
+					ErrorChain(&e)
+				);
+				break;
+			}
+			// Next frame can't be found after a skipped one.
+			Err(e @ ServerError::FrameTooLarge(..)) => {
+				warn!("Disconnected client {}: {}", address, ErrorChain(&e));
+				break;
+			}
+			Err(_) => continue,
+			Ok(_) => (),
 		}
 
-		if let Err(e) = session.handle_requests() {
-			debug!(
-				"{:?} {e} - discard handling",
-				session.client().unwrap_or_default()
-			);
-			session.discard_exchanges();
+		match session.handle_requests() {
+			Err(ServerError::Banned(name)) => {
+				info!("Refused connection of banned client {}", name);
+				break;
+			}
+			Err(ServerError::TooManySpectators) => {
+				info!("Refused spectator {}, the limit is reached", address);
+				break;
+			}
+			Err(ServerError::Rejected(reason)) => {
+				info!("Rejected client {}: {}", address, reason);
+				break;
+			}
+			Err(e) => {
+				debug!(
+					"{:?} {e} - discard handling",
+					session.client().unwrap_or_default()
+				);
+				session.discard_exchanges();
+			}
+			Ok(_) => (),
 		}
 
 		if session.is_disconnected() {
@@ -324,10 +1663,10 @@ fn handle_client(
 		}
 	}
 
-	let mut gamedata = gamedata.lock().expect("acquiring gamedata mutex");
+	handle.unregister(address);
+	let mut gamedata = handle.gamedata();
 
-	if let Some(exchange) = session.exchanges().first() {
-		let name = exchange.request().client;
+	if let Some(name) = session.client().filter(|_| !session.is_spectator()) {
 		if gamedata.find_snake(&name) {
 			gamedata.kill_snake(name)?;
 		}
@@ -336,55 +1675,87 @@ fn handle_client(
 	Ok(())
 }
 
+/// Read the next request frame up to `max_size` bytes from the `reader` and
+/// return the reader back with the result.
+async fn read_request(
+	mut reader: Reader,
+	max_size: usize,
+) -> (Reader, Result<Vec<u8>>) {
+	let frame = reader.read(max_size).await;
+	(reader, frame)
+}
+
 /// Struct which represents responses stack with some connection-handling data
-/// and server stream.
+/// and client connection.
 struct Session {
-	/// Server stream.
-	stream: TcpStream,
+	/// Connection to the client, it's shared with pushing updates.
+	connection: Connection,
 
-	/// Client name.
+	/// Client address.
+	address: SocketAddr,
+
+	/// Name assigned to client on connecting.
 	client: Option<String>,
 
-	/// GameData.
-	gamedata: Arc<Mutex<GameData>>,
+	/// Handle of the server.
+	handle: ServerHandle,
 
 	/// Is client connected to server or not.
 	connected: bool,
 
+	/// Is client a spectator or not.
+	spectator: bool,
+
 	/// `exchanges` is just a vector of server requests linked with responses.
 	exchanges: Vec<Exchange>,
+
+	/// Limiter of client's chat messages.
+	rate_limiter: RateLimiter,
+
+	/// Protocol negotiated with the client.
+	handshake: Handshake,
+
+	/// Updates client is subscribed to, none if it isn't subscribed.
+	subscription: Option<Subscription>,
 }
 
 impl Session {
 	/// Return a new empty [`Session`].
-	fn new(stream: TcpStream, gamedata: Arc<Mutex<GameData>>) -> Self {
+	fn new(
+		connection: Connection,
+		address: SocketAddr,
+		handle: ServerHandle,
+	) -> Self {
 		Self {
-			stream,
-			gamedata,
+			connection,
+			address,
+			handle,
 			client: None,
 			connected: false,
+			spectator: false,
+			rate_limiter: RateLimiter::default(),
+			handshake: Handshake::default(),
 			exchanges: vec![],
+			subscription: None,
 		}
 	}
 
-	/// Wait for requests and push them to the stack.
-	fn wait(&mut self) -> Result<()> {
-		let mut buffer = [0; 1024];
-
-		self.stream.read(&mut buffer)?;
-
-		if String::from_utf8_lossy(&buffer).trim_matches(char::from(0)) == "" {
-			return Err(Box::new(ServerError::EmptyRequestString));
-		}
-
-		match Request::from_bytes(&buffer) {
-			Ok(requests) => {
-				for request in requests {
-					self.exchanges_mut().push(Exchange(request.clone(), None));
-				}
-			}
+	/// Convert the request `frame` and push it to the stack.
+	fn receive(&mut self, frame: &[u8]) -> Result<()> {
+		// Hello is always sent in json, even after an encoding is agreed on.
+		let request =
+			Request::decode(frame, self.handshake.encoding).or_else(|e| {
+				Request::decode(frame, Encoding::Json)
+					.ok()
+					.filter(|request| {
+						matches!(request.kind, RequestKind::Hello(_))
+					})
+					.ok_or(e)
+			});
+		match request {
+			Ok(request) => self.exchanges_mut().push(Exchange(request, None)),
 			Err(e) => {
-				error!("Failed to convert request: {}", e);
+				error!("Failed to convert request: {}", ErrorChain(&e));
 				return Err(e);
 			}
 		};
@@ -395,8 +1766,11 @@ impl Session {
 	/// Handle all uncompleted requests.
 	fn handle_requests(&mut self) -> Result<()> {
 		let mut is_connection_request = false;
-		let mut stream = self.stream.try_clone()?;
-		let gamedata = self.gamedata.clone();
+		let mut subscribed = false;
+		let mut spectator = self.spectator;
+		let connection = self.connection.clone();
+		let handle = self.handle.clone();
+		let address = self.address;
 		let last_direction = self
 			.exchanges()
 			.iter()
@@ -410,39 +1784,162 @@ impl Session {
 		}
 
 		let first_request = self.exchanges().first().unwrap().request();
-		if !self.connected() && first_request.kind != RequestKind::Connect {
-			return Err(Box::new(ServerError::IsNotConnected));
+		if !self.connected()
+			&& !matches!(
+				first_request.kind,
+				RequestKind::Hello(_)
+					| RequestKind::Connect
+					| RequestKind::Spectate(_)
+			) {
+			return Err(ServerError::IsNotConnected);
 		}
 
-		for exchange in self.exchanges_mut() {
+		for exchange in self.exchanges.iter_mut() {
 			if exchange.response().is_some() {
 				continue;
 			}
 
 			let mut request = exchange.request();
+			// Requests are made on behalf of the name assigned on connecting,
+			// it may differ from the one client sends.
+			if let Some(client) = &self.client {
+				request.client = client.clone();
+			}
 
 			// Lazily acquire gamedata mutex to work with it on a fly without
 			// boilerplate code.
-			let gamedata =
-				|| gamedata.lock().expect("acquiring gamedata mutex");
+			let gamedata = || handle.gamedata();
+
+			// Spectators have no snakes to control.
+			if spectator
+				&& matches!(
+					request.kind,
+					RequestKind::ChangeDirection(_)
+						| RequestKind::Turn(_)
+						| RequestKind::VotePause(_)
+				) {
+				return Err(ServerError::IsSpectator);
+			}
+			if let Some(feature) = request.kind.feature() {
+				if !self.handshake.supports(feature) {
+					return Err(ServerError::FeatureNotNegotiated(feature));
+				}
+			}
+
+			let response = match request.kind.clone() {
+				RequestKind::Hello(_)
+					if self.connected || is_connection_request =>
+				{
+					let reason = String::from("hello is sent after connecting");
+					let response = HelloResponse::Rejected(reason.clone());
+					connection.write(
+						serde_json::to_vec(&response)?,
+						Encoding::Json,
+					)?;
+					return Err(ServerError::Rejected(reason));
+				}
+				RequestKind::Hello(hello) => {
+					let response = match hello.negotiate() {
+						Ok(handshake) => {
+							self.handshake = handshake.clone();
+							HelloResponse::Accepted(handshake)
+						}
+						Err(reason) => HelloResponse::Rejected(reason),
+					};
+					// Answer to hello is always in json.
+					connection.write(
+						serde_json::to_vec(&response)?,
+						Encoding::Json,
+					)?;
+					if let HelloResponse::Rejected(reason) = response {
+						return Err(ServerError::Rejected(reason));
+					}
+					Response::new(request.clone(), Ok(()))
+				}
+				RequestKind::Spectate(follow) => {
+					is_connection_request = true;
+					let name = request.client();
 
-			let response = match request.kind {
+					if handle.is_banned(&name) {
+						return Err(ServerError::Banned(name));
+					}
+					// Spectators share names with players in chat and
+					// commands, so they get unique names the same way.
+					let name = {
+						let gamedata = gamedata();
+						let name = handle.unique_name(&gamedata, &name);
+						if !handle.add_spectator(address, name.clone(), follow)
+						{
+							return Err(ServerError::TooManySpectators);
+						}
+						name
+					};
+					request.client = name.clone();
+					self.client = Some(name.clone());
+					spectator = true;
+					handle.post_message(
+						None,
+						format!("{} is watching the game", name),
+					);
+
+					Response::new(request.clone(), Ok(()))
+				}
 				RequestKind::Connect => {
 					is_connection_request = true;
-					let mut name = request.client;
+					let name = request.client;
 
-					// Check whether there is already a snake with such name and
-					// if yes, change it to uniquely-generated one.
-					if gamedata().find_snake(name.clone()) {
-						name.push_str(&format!(" ({})", gamedata().snakes()));
+					if handle.is_banned(&name) {
+						return Err(ServerError::Banned(name));
 					}
 
-					request.client = name.clone();
+					// Other sessions must not take the name or the slot
+					// until the snake is spawned.
+					let mut gamedata = gamedata();
 
-					Response::new(
-						request.clone(),
-						gamedata().spawn_snake(name, None, None, None),
-					)
+					// Players and bots must be told apart by names.
+					let name = handle.unique_name(&gamedata, &name);
+
+					// Bots only fill empty slots, so give player a slot of
+					// some bot if the game is full.
+					if gamedata.is_full() {
+						if let Some(bot) = gamedata.evict_bot() {
+							debug!(
+								"Evicted bot {} to make room for {}",
+								bot, name
+							);
+						}
+					}
+
+					request.client = name.clone();
+					self.client = Some(name.clone());
+					handle.set_client_name(address, name.clone());
+
+					let result =
+						gamedata.spawn_snake(name.clone(), None, None, None);
+					drop(gamedata);
+					if result.is_ok() {
+						handle.post_message(
+							None,
+							format!("{} joined the game", name),
+						);
+					}
+					Response::new(request.clone(), result)
+				}
+				RequestKind::Say(text) => {
+					let text = text.trim();
+					if text.chars().count() > chat::MESSAGE_LENGTH_LIMIT {
+						return Err(ServerError::MessageTooLong);
+					}
+					if !text.is_empty() {
+						if !self.rate_limiter.check(Instant::now()) {
+							return Err(ServerError::TooManyMessages);
+						}
+						let author = handle
+							.client_name(address)
+							.unwrap_or_else(|| request.client());
+						handle.post_message(Some(author), text.to_string());
+					}
+					Response::new(request.clone(), Ok(()))
 				}
 				RequestKind::ChangeDirection(direction) => {
 					if let Some(RequestKind::ChangeDirection(
@@ -450,9 +1947,7 @@ impl Session {
 					)) = last_direction
 					{
 						if last_request_direction == direction {
-							return Err(Box::new(
-								ServerError::IndenticalRequests,
-							));
+							return Err(ServerError::IndenticalRequests);
 						}
 					}
 
@@ -469,7 +1964,50 @@ impl Session {
 						}
 					}
 				}
-				RequestKind::GetGrid => Response::new(request.clone(), Ok(())),
+				RequestKind::Turn(turn) => {
+					let mut gamedata = gamedata();
+					let snake = gamedata.snake_mut(request.client.clone());
+
+					match snake {
+						Ok(snake) => {
+							Response::new(request.clone(), snake.turn(turn))
+						}
+						Err(_) => {
+							Response::new(request.clone(), snake.map(|_| ()))
+						}
+					}
+				}
+				RequestKind::GetGrid
+				| RequestKind::GetGridUpdate(_)
+				| RequestKind::GetStatus
+				| RequestKind::GetScoreboard
+				| RequestKind::GetMessages(_)
+				| RequestKind::OpenUdp => Response::new(request.clone(), Ok(())),
+				RequestKind::VotePause(pause) => {
+					handle.vote_pause(address, pause);
+					Response::new(request.clone(), Ok(()))
+				}
+				RequestKind::Subscribe(since) => {
+					let message = self
+						.subscription
+						.as_ref()
+						.map_or(0, |subscription| subscription.message);
+					self.subscription = Some(Subscription {
+						tick: since,
+						status: None,
+						message,
+						ticks: handle.ticks.subscribe(),
+					});
+					subscribed = true;
+					Response::new(request.clone(), Ok(()))
+				}
+				RequestKind::Unsubscribe => {
+					self.subscription = None;
+					Response::new(request.clone(), Ok(()))
+				}
+				RequestKind::Disconnect if spectator => {
+					Response::new(request.clone(), Ok(()))
+				}
 				RequestKind::Disconnect => Response::new(
 					request.clone(),
 					gamedata().kill_snake(request.client()).map(|_| ()),
@@ -478,20 +2016,55 @@ impl Session {
 
 			exchange.assign_response(response);
 
+			let encoding = self.handshake.encoding;
 			match request.kind {
-				RequestKind::Connect => {
-					let buffer = serde_json::to_string(&request.client())?;
-					stream.write(buffer.as_bytes())?;
+				RequestKind::Connect | RequestKind::Spectate(_) => {
+					connection
+						.write(encoding.encode(&request.client)?, encoding)?;
 				}
 				RequestKind::GetGrid => {
-					let buffer = match gamedata().grid().as_bytes() {
+					let buffer = match encoding.encode(&gamedata().grid()) {
+						Ok(val) => val,
+						Err(e) => {
+							error!(
+								"Failed to convert gamedata: {}",
+								ErrorChain(&e)
+							);
+							return Err(e);
+						}
+					};
+					connection.write(buffer, encoding)?;
+				}
+				RequestKind::GetGridUpdate(since) => {
+					let update = gamedata().grid_update(since);
+					let buffer = match encoding.encode(&update) {
 						Ok(val) => val,
 						Err(e) => {
-							error!("Failed to convert gamedata: {}", e);
+							error!(
+								"Failed to convert grid update: {}",
+								ErrorChain(&e)
+							);
 							return Err(e);
 						}
 					};
-					stream.write(&buffer)?;
+					connection.write(buffer, encoding)?;
+				}
+				RequestKind::GetStatus => {
+					connection
+						.write(encoding.encode(&handle.status())?, encoding)?;
+				}
+				RequestKind::GetScoreboard => {
+					let scoreboard = gamedata().scoreboard();
+					connection
+						.write(encoding.encode(&scoreboard)?, encoding)?;
+				}
+				RequestKind::GetMessages(since) => {
+					let messages = handle.messages_since(since);
+					connection.write(encoding.encode(&messages)?, encoding)?;
+				}
+				RequestKind::OpenUdp => {
+					let ticket = handle.open_udp(address, encoding);
+					connection.write(encoding.encode(&ticket)?, encoding)?;
 				}
 				RequestKind::Disconnect => break,
 				_ => (),
@@ -501,10 +2074,80 @@ impl Session {
 		if !self.connected && is_connection_request {
 			self.connected = true
 		}
+		self.spectator = spectator;
+
+		// The first update isn't delayed until the game changes.
+		if subscribed {
+			self.push_update(&handle.tick_update(None))?;
+		}
 
 		Ok(())
 	}
 
+	/// Wait for the next [`TickUpdate`] if client is subscribed to updates
+	/// or forever otherwise.
+	async fn next_tick(&mut self) -> Option<Arc<TickUpdate>> {
+		let subscription = match self.subscription.as_mut() {
+			Some(subscription) => subscription,
+			None => return std::future::pending().await,
+		};
+		loop {
+			match subscription.ticks.recv().await {
+				Ok(update) => return Some(update),
+				// Update made of the next one contains skipped changes.
+				Err(broadcast::error::RecvError::Lagged(_)) => continue,
+				Err(broadcast::error::RecvError::Closed) => return None,
+			}
+		}
+	}
+
+	/// Push [`Update`] made of the `tick` update to subscribed client if it
+	/// hasn't got the current state of the game yet.
+	fn push_update(&mut self, tick: &TickUpdate) -> Result<()> {
+		let subscription = match self.subscription.as_mut() {
+			Some(subscription) => subscription,
+			None => return Ok(()),
+		};
+		let status = &tick.update.status;
+		if subscription.tick == Some(tick.since)
+			&& subscription.message == tick.messages_since
+		{
+			subscription.tick = Some(tick.update.tick);
+			subscription.status = Some(status.clone());
+			subscription.message = tick.message;
+			let encoding = self.handshake.encoding;
+			return self.connection.write(tick.encoded(encoding)?, encoding);
+		}
+		// Client has missed some updates or has subscribed just now.
+		let messages = match tick.message > subscription.message {
+			true => self.handle.messages_since(subscription.message),
+			false => vec![],
+		};
+		if subscription.tick == Some(tick.update.tick)
+			&& subscription.status.as_ref() == Some(status)
+			&& messages.is_empty()
+		{
+			return Ok(());
+		}
+		let grid = match subscription.tick {
+			Some(since) => self.handle.gamedata().grid_update(since),
+			None => GridUpdate::Full(self.handle.gamedata().grid()),
+		};
+		subscription.tick = Some(grid.tick());
+		subscription.status = Some(status.clone());
+		if let Some(message) = messages.last() {
+			subscription.message = message.id;
+		}
+		let update = Update {
+			tick: grid.tick(),
+			grid,
+			status: status.clone(),
+			scoreboard: tick.update.scoreboard.clone(),
+			messages,
+		};
+		update.write(&self.connection, self.handshake.encoding)
+	}
+
 	/// Return true if some of sent requests have
 	/// [`disconnect kind`](RequestKind::Disconnect).
 	fn is_disconnected(&self) -> bool {
@@ -516,6 +2159,11 @@ impl Session {
 		false
 	}
 
+	/// Return true if client is a spectator.
+	fn is_spectator(&self) -> bool {
+		self.spectator
+	}
+
 	/// Return true if client is connected or false otherwise.
 	fn connected(&self) -> bool {
 		self.connected
@@ -531,11 +2179,10 @@ impl Session {
 		&mut self.exchanges
 	}
 
-	/// Return client name.
+	/// Return name assigned to client on connecting, none if it hasn't
+	/// connected yet.
 	fn client(&self) -> Option<String> {
-		self.exchanges()
-			.first()
-			.map(|exchange| exchange.request().client())
+		self.client.clone()
 	}
 
 	/// Remove uncompleted exchanges from stack.
@@ -570,45 +2217,31 @@ impl Request {
 		}
 	}
 
-	/// Convert [`Request`] to bytes.
-	fn as_bytes(&self) -> Result<Vec<u8>> {
-		Ok(self.to_string()?.as_bytes().to_vec())
-	}
-
-	/// Convert bytes to [`Vec<Request>`].
-	fn from_bytes(b: &[u8]) -> Result<Vec<Self>> {
-		let mut requests = vec![];
-		let string = String::from_utf8_lossy(b);
-		let string = string.trim_matches(char::from(0));
-		let separator = &String::from_utf8_lossy(&[0; 4]).to_string();
-		for slice in string.split(separator) {
-			if !slice.is_empty() {
-				requests.push(Self::from_string(slice)?);
-			}
-		}
-		Ok(requests)
-	}
-
-	/// Convert [`Request`] to json string.
-	fn to_string(&self) -> Result<String> {
-		Ok(serde_json::to_string(self)?)
-	}
-
-	/// Convert json string to [`Request`].
-	fn from_string<T: AsRef<str>>(string: T) -> Result<Self> {
-		Ok(serde_json::from_str(
-			string.as_ref().trim_matches(char::from(0)),
-		)?)
+	/// Convert bytes in the `encoding` to [`Request`].
+	fn decode(bytes: &[u8], encoding: Encoding) -> Result<Self> {
+		encoding.decode(bytes)
 	}
 
 	/// Send request to server.
 	///
-	/// Write request to [`TcpStream`] after writing four null characters to
-	/// make splitting multiple json requests possible.
-	fn write(&self, stream: &mut TcpStream) -> Result<()> {
-		stream.write(&self.as_bytes()?)?;
-		stream.write(&[0; 4])?;
-		Ok(())
+	/// Write request in the `encoding` to [`TcpStream`] as one frame, see
+	/// [`framing`].
+	fn write(&self, stream: &mut TcpStream, encoding: Encoding) -> Result<()> {
+		framing::write_frame(stream, &encoding.encode(self)?)
+	}
+
+	/// Send request to server and return read response. Both are in the
+	/// `encoding`, responses longer than `max_size` bytes aren't read.
+	fn send<T: DeserializeOwned>(
+		&self,
+		stream: &mut TcpStream,
+		encoding: Encoding,
+		max_size: usize,
+	) -> Result<T> {
+		self.write(stream, encoding)?;
+		let frame = framing::read_frame(stream, max_size)?;
+
+		encoding.decode(&frame)
 	}
 
 	/// Return client name.
@@ -618,31 +2251,111 @@ impl Request {
 }
 
 /// Enum of server request kinds.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 enum RequestKind {
+	/// Request to negotiate protocol with server.
+	Hello(Hello),
+
 	/// Request to connect to server.
 	Connect,
 
+	/// Request to connect to server as a spectator following the provided
+	/// player.
+	Spectate(Option<String>),
+
 	/// Request to disconnect from server.
 	Disconnect,
 
 	/// Request to get game grid.
 	GetGrid,
 
+	/// Request to get changes of game grid made since the provided tick.
+	GetGridUpdate(u64),
+
 	/// Request to change snake direction on the provided one.
 	ChangeDirection(Direction),
+
+	/// Request to turn snake relatively to its current direction.
+	Turn(Turn),
+
+	/// Request to get status of the server.
+	GetStatus,
+
+	/// Request to get names and lengths of snakes.
+	GetScoreboard,
+
+	/// Request to broadcast the provided chat message.
+	Say(String),
+
+	/// Request to get chat messages posted after the message with the
+	/// provided identifier.
+	GetMessages(u64),
+
+	/// Request to vote for pausing the game if the value is true or for
+	/// resuming it otherwise.
+	VotePause(bool),
+
+	/// Request to push updates after every tick to the client having grid
+	/// of the provided tick.
+	Subscribe(Option<u64>),
+
+	/// Request to stop pushing updates.
+	Unsubscribe,
+
+	/// Request to open UDP transport.
+	OpenUdp,
+}
+
+impl RequestKind {
+	/// Return optional feature the request belongs to if any.
+	fn feature(&self) -> Option<Feature> {
+		match self {
+			Self::GetGridUpdate(_) => Some(Feature::Deltas),
+			Self::Say(_) | Self::GetMessages(_) => Some(Feature::Chat),
+			Self::Spectate(_) => Some(Feature::Spectate),
+			Self::GetStatus | Self::VotePause(_) => Some(Feature::Pause),
+			Self::Subscribe(_) | Self::Unsubscribe => Some(Feature::Push),
+			Self::OpenUdp => Some(Feature::Udp),
+			_ => None,
+		}
+	}
 }
 
 impl fmt::Display for RequestKind {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
+			Self::Hello(hello) => {
+				write!(f, "say hello with protocol version {}", hello.version)
+			}
 			Self::Connect => write!(f, "connect to the server"),
+			Self::Spectate(Some(player)) => {
+				write!(f, "spectate the server following {}", player)
+			}
+			Self::Spectate(None) => write!(f, "spectate the server"),
 			Self::Disconnect => write!(f, "disconnect from the server"),
 			Self::GetGrid => write!(f, "get game grid"),
+			Self::GetGridUpdate(since) => {
+				write!(f, "get game grid update since tick {}", since)
+			}
 			Self::ChangeDirection(direction) => {
 				write!(f, "change snake direction to {}", direction)
 			}
+			Self::Turn(turn) => write!(f, "turn snake {}", turn),
+			Self::GetStatus => write!(f, "get server status"),
+			Self::GetScoreboard => write!(f, "get scoreboard"),
+			Self::Say(text) => write!(f, "say {:?}", text),
+			Self::GetMessages(since) => {
+				write!(f, "get chat messages since {}", since)
+			}
+			Self::VotePause(true) => write!(f, "vote for pausing the game"),
+			Self::VotePause(false) => write!(f, "vote for resuming the game"),
+			Self::Subscribe(Some(since)) => {
+				write!(f, "subscribe to updates since tick {}", since)
+			}
+			Self::Subscribe(None) => write!(f, "subscribe to updates"),
+			Self::Unsubscribe => write!(f, "unsubscribe from updates"),
+			Self::OpenUdp => write!(f, "open UDP transport"),
 		}
 	}
 }
@@ -654,12 +2367,12 @@ struct Response {
 	request: Request,
 
 	/// Result of some game function.
-	response: Result<()>,
+	response: game::Result<()>,
 }
 
 impl Response {
 	/// Return new [`Response`].
-	fn new(request: Request, response: Result<()>) -> Self {
+	fn new(request: Request, response: game::Result<()>) -> Self {
 		Self { request, response }
 	}
 
@@ -725,7 +2438,7 @@ impl Exchange {
 }
 
 /// Error type returned by crate's functions.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum ServerError {
 	/// Client is trying to be handled without being authorized.
 	///
@@ -733,12 +2446,106 @@ pub enum ServerError {
 	/// authorized by server.
 	IsNotConnected,
 
-	/// Client is sending nothing besides null characters.
-	EmptyRequestString,
+	/// Frame of the size specified in variant's first argument is larger than
+	/// the limit specified in the second one.
+	FrameTooLarge(usize, usize),
 
 	/// Client sent two indentical requests. Requests to get some information
 	/// are exceptions.
 	IndenticalRequests,
+
+	/// Client with name specified in variant's argument is banned.
+	Banned(String),
+
+	/// Client can't spectate because the limit of spectators is reached.
+	TooManySpectators,
+
+	/// Spectator is trying to control a snake.
+	IsSpectator,
+
+	/// Server can't communicate with the client for the reason specified in
+	/// variant's argument.
+	Rejected(String),
+
+	/// Client sent request of the feature specified in variant's argument
+	/// without negotiating it.
+	FeatureNotNegotiated(Feature),
+
+	/// Chat message is longer than
+	/// [`MESSAGE_LENGTH_LIMIT`](chat::MESSAGE_LENGTH_LIMIT).
+	MessageTooLong,
+
+	/// Client has sent more than [`RATE_LIMIT`](chat::RATE_LIMIT) chat
+	/// messages during [`RATE_LIMIT_PERIOD`](chat::RATE_LIMIT_PERIOD).
+	TooManyMessages,
+
+	/// Client with name specified in variant's argument isn't connected.
+	ClientNotFound(String),
+
+	/// Admin console command specified in variant's argument is unknown.
+	UnknownCommand(String),
+
+	/// Admin console command is used wrong, variant's argument is its usage.
+	InvalidCommand(&'static str),
+
+	/// Admin console command specified in variant's argument can be executed
+	/// only in the local console.
+	LocalCommand(&'static str),
+
+	/// Coordinates specified in variant's argument are out of the grid of
+	/// the size.
+	OutOfGrid(Coordinates, (usize, usize)),
+
+	/// Cell with coordinates specified in variant's argument is occupied.
+	CellOccupied(Coordinates),
+
+	/// Admin has sent the wrong token.
+	Unauthorized,
+
+	/// Admin request line is longer than
+	/// [`MAX_LINE_SIZE`](admin::MAX_LINE_SIZE) bytes.
+	LineTooLong,
+
+	/// Admin can't connect because [`MAX_CONNECTIONS`](admin::MAX_CONNECTIONS)
+	/// admins are already connected.
+	TooManyAdmins,
+
+	/// Configuration can't be changed.
+	Config(config::ConfigError),
+
+	/// Client has no identifier to send requests with.
+	NoClientId,
+
+	/// Client has no stream because it isn't connected to the server.
+	NoStream,
+
+	/// Server doesn't accept UDP transport.
+	UdpDisabled,
+
+	/// UDP packet has no token of a client using UDP transport.
+	UnknownToken,
+
+	/// Grid of a client using UDP transport is too old to get changes of it,
+	/// the grid has to be requested over TCP.
+	GridOutdated,
+
+	/// Game function has failed.
+	Game(GameError),
+
+	/// Reading from or writing to a stream has failed.
+	Io(io::Error),
+
+	/// WebSocket protocol is violated.
+	WebSocket(Box<tungstenite::Error>),
+
+	/// Request can't be converted from or to json.
+	Json(serde_json::Error),
+
+	/// Request can't be converted to MessagePack.
+	MessagePackEncode(rmp_serde::encode::Error),
+
+	/// Request can't be converted from MessagePack.
+	MessagePackDecode(rmp_serde::decode::Error),
 }
 
 impl fmt::Display for ServerError {
@@ -747,14 +2554,369 @@ impl fmt::Display for ServerError {
 			Self::IsNotConnected => {
 				write!(f, "client wants to be handled without being authorized")
 			}
-			Self::EmptyRequestString => {
-				write!(f, "client sent nothing besides null chars")
-			}
+			Self::FrameTooLarge(size, limit) => write!(
+				f,
+				"frame of {} bytes is larger than {} bytes limit",
+				size, limit
+			),
 			Self::IndenticalRequests => {
 				write!(f, "client sent two indentical requests")
 			}
+			Self::Banned(name) => write!(f, "client {} is banned", name),
+			Self::TooManySpectators => write!(f, "too many spectators"),
+			Self::IsSpectator => {
+				write!(f, "spectator can't control snakes or vote")
+			}
+			Self::Rejected(reason) => {
+				write!(f, "server rejected the client: {}", reason)
+			}
+			Self::FeatureNotNegotiated(feature) => {
+				write!(f, "feature {} isn't negotiated", feature)
+			}
+			Self::MessageTooLong => write!(f, "chat message is too long"),
+			Self::TooManyMessages => {
+				write!(f, "client sends chat messages too often")
+			}
+			Self::ClientNotFound(name) => {
+				write!(f, "client {} isn't connected", name)
+			}
+			Self::UnknownCommand(command) => {
+				write!(f, "unknown command \"{}\", try \"help\"", command)
+			}
+			Self::InvalidCommand(usage) => write!(f, "usage: {}", usage),
+			Self::LocalCommand(command) => {
+				write!(f, "{} can be used only in the local console", command)
+			}
+			Self::OutOfGrid(coords, size) => {
+				write!(f, "{} is out of the {}x{} grid", coords, size.0, size.1)
+			}
+			Self::CellOccupied(coords) => {
+				write!(f, "cell {} is occupied", coords)
+			}
+			Self::Unauthorized => write!(f, "wrong admin token"),
+			Self::LineTooLong => write!(
+				f,
+				"request line is longer than {} bytes",
+				admin::MAX_LINE_SIZE
+			),
+			Self::TooManyAdmins => write!(f, "too many admin connections"),
+			Self::Config(e) => write!(f, "{}", e),
+			Self::NoClientId => write!(f, "client has no identifier"),
+			Self::NoStream => {
+				write!(f, "client isn't connected to the server")
+			}
+			Self::UdpDisabled => write!(f, "server doesn't accept UDP"),
+			Self::UnknownToken => write!(f, "unknown UDP token"),
+			Self::GridOutdated => write!(f, "grid is too old to be updated"),
+			Self::Game(_) => write!(f, "game error"),
+			Self::Io(_) => write!(f, "i/o error"),
+			Self::WebSocket(_) => write!(f, "websocket error"),
+			Self::Json(_) => write!(f, "can't convert json"),
+			Self::MessagePackEncode(_) => write!(f, "can't convert to msgpack"),
+			Self::MessagePackDecode(_) => {
+				write!(f, "can't convert from msgpack")
+			}
+		}
+	}
+}
+
+impl error::Error for ServerError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::Game(e) => Some(e),
+			Self::Io(e) => Some(e),
+			Self::WebSocket(e) => Some(e),
+			Self::Json(e) => Some(e),
+			Self::MessagePackEncode(e) => Some(e),
+			Self::MessagePackDecode(e) => Some(e),
+			// Configuration error is displayed as is, so it isn't a source.
+			Self::Config(e) => e.source(),
+			_ => None,
+		}
+	}
+}
+
+/// Error displayed with messages of all its sources separated by colons, like
+/// "i/o error: connection refused".
+pub struct ErrorChain<'a>(pub &'a dyn error::Error);
+
+impl fmt::Display for ErrorChain<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)?;
+		let mut source = self.0.source();
+		while let Some(e) = source {
+			write!(f, ": {}", e)?;
+			source = e.source();
+		}
+		Ok(())
+	}
+}
+
+impl From<config::ConfigError> for ServerError {
+	fn from(e: config::ConfigError) -> Self {
+		Self::Config(e)
+	}
+}
+
+impl From<GameError> for ServerError {
+	fn from(e: GameError) -> Self {
+		Self::Game(e)
+	}
+}
+
+impl From<io::Error> for ServerError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<tungstenite::Error> for ServerError {
+	/// Closed WebSocket connections are converted to i/o errors like closed
+	/// TCP ones.
+	fn from(e: tungstenite::Error) -> Self {
+		match e {
+			tungstenite::Error::Io(e) => Self::Io(e),
+			tungstenite::Error::ConnectionClosed
+			| tungstenite::Error::AlreadyClosed => {
+				Self::Io(io::ErrorKind::ConnectionAborted.into())
+			}
+			e => Self::WebSocket(Box::new(e)),
 		}
 	}
 }
 
-impl error::Error for ServerError {}
+impl From<serde_json::Error> for ServerError {
+	fn from(e: serde_json::Error) -> Self {
+		Self::Json(e)
+	}
+}
+
+impl From<rmp_serde::encode::Error> for ServerError {
+	fn from(e: rmp_serde::encode::Error) -> Self {
+		Self::MessagePackEncode(e)
+	}
+}
+
+impl From<rmp_serde::decode::Error> for ServerError {
+	fn from(e: rmp_serde::decode::Error) -> Self {
+		Self::MessagePackDecode(e)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::error::Error;
+
+	#[test]
+	fn error_chain() {
+		let e = ServerError::from(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			"connection refused",
+		));
+		assert_eq!(e.to_string(), "i/o error");
+		assert!(e.source().unwrap().downcast_ref::<io::Error>().is_some());
+		assert_eq!(ErrorChain(&e).to_string(), "i/o error: connection refused");
+
+		let json = serde_json::from_str::<Grid>("{").unwrap_err();
+		let e = ServerError::from(GameError::from(json));
+		let chain = ErrorChain(&e).to_string();
+		assert!(chain.starts_with("game error: can't convert json: "));
+	}
+
+	#[test]
+	fn post_message() -> Result<()> {
+		let gamedata = GameData::new(Some((20, 20)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let handle = server.handle();
+		handle.post_message(Some("Mark".into()), "hi".into());
+		let messages = handle.messages_since(0);
+		assert_eq!(messages.len(), 1);
+		assert_eq!(messages[0].text, "hi");
+		Ok(())
+	}
+
+	/// Connect to the server at the `address` as "Mark" like a client which
+	/// sends this name in every request ignoring the assigned one. Return
+	/// stream, encoding and the assigned name.
+	fn connect_mark(
+		address: SocketAddr,
+	) -> Result<(TcpStream, Encoding, String)> {
+		let mut stream = TcpStream::connect(address)?;
+		stream.set_read_timeout(CLIENT_READ_TIMEOUT)?;
+		let max_size = framing::MAX_RESPONSE_SIZE;
+		let hello = Request::new("Mark", RequestKind::Hello(Hello::default()));
+		let encoding =
+			match hello.send(&mut stream, Encoding::Json, max_size)? {
+				HelloResponse::Accepted(handshake) => handshake.encoding,
+				HelloResponse::Rejected(reason) => {
+					return Err(ServerError::Rejected(reason))
+				}
+			};
+		let connect = Request::new("Mark", RequestKind::Connect);
+		let name = connect.send(&mut stream, encoding, max_size)?;
+		Ok((stream, encoding, name))
+	}
+
+	#[test]
+	fn same_names() -> Result<()> {
+		let gamedata = GameData::new(Some((30, 30)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let address = server.local_addr()?;
+		let handle = server.handle();
+		handle.pause();
+		thread::spawn(move || server.run());
+		let is_alive = |name: &str, alive: bool| {
+			(0..200).any(|_| {
+				thread::sleep(Duration::from_millis(10));
+				handle.gamedata().find_snake(name) == alive
+			})
+		};
+
+		let (first, _, first_name) = connect_mark(address)?;
+		let (mut second, encoding, second_name) = connect_mark(address)?;
+		assert_eq!(first_name, "Mark");
+		assert_ne!(second_name, "Mark");
+		assert!(is_alive(&second_name, true));
+
+		// Requests are made on behalf of the assigned name.
+		let disconnect = Request::new("Mark", RequestKind::Disconnect);
+		disconnect.write(&mut second, encoding)?;
+		assert!(is_alive(&second_name, false));
+		assert!(handle.gamedata().find_snake("Mark"));
+
+		// Snake of the client is killed when it drops the connection.
+		let (third, _, third_name) = connect_mark(address)?;
+		drop(third);
+		assert!(is_alive(&third_name, false));
+		assert!(handle.gamedata().find_snake("Mark"));
+		drop(first);
+		assert!(is_alive("Mark", false));
+		Ok(())
+	}
+
+	/// Write the `request` in the `encoding` to the `stream`.
+	async fn send_async(
+		stream: &mut tokio::net::TcpStream,
+		request: Request,
+		encoding: Encoding,
+	) -> Result<()> {
+		framing::write_frame_async(stream, &encoding.encode(&request)?).await
+	}
+
+	/// Read a response in the `encoding` from the `stream`.
+	async fn read_async<T: DeserializeOwned>(
+		stream: &mut tokio::net::TcpStream,
+		encoding: Encoding,
+	) -> Result<T> {
+		let max_size = framing::MAX_RESPONSE_SIZE;
+		let read = framing::read_frame_async(stream, max_size);
+		let frame = tokio::time::timeout(Duration::from_secs(5), read)
+			.await
+			.map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+		encoding.decode(&frame)
+	}
+
+	#[tokio::test]
+	async fn push_ticks() -> Result<()> {
+		let gamedata = GameData::new(Some((30, 30)), Settings::default());
+		let delay = Some(Duration::from_millis(10));
+		let server = Server::bind("127.0.0.1:0", gamedata, delay)?;
+		let address = server.local_addr()?;
+		let handle = server.handle();
+		handle.pause();
+		thread::spawn(move || server.run());
+
+		let mut stream = tokio::net::TcpStream::connect(address).await?;
+		let hello = Request::new("Mark", RequestKind::Hello(Hello::default()));
+		send_async(&mut stream, hello, Encoding::Json).await?;
+		let encoding = match read_async(&mut stream, Encoding::Json).await? {
+			HelloResponse::Accepted(handshake) => handshake.encoding,
+			HelloResponse::Rejected(reason) => {
+				return Err(ServerError::Rejected(reason))
+			}
+		};
+		let connect = Request::new("Mark", RequestKind::Connect);
+		send_async(&mut stream, connect, encoding).await?;
+		let name: String = read_async(&mut stream, encoding).await?;
+		assert_eq!(name, "Mark");
+		assert!(handle.gamedata().find_snake("Mark"));
+
+		let subscribe = Request::new("Mark", RequestKind::Subscribe(None));
+		send_async(&mut stream, subscribe, encoding).await?;
+		let first: Update = read_async(&mut stream, encoding).await?;
+		assert!(matches!(first.grid, GridUpdate::Full(_)));
+
+		// Every tick is pushed without requests.
+		let mut tick = first.tick;
+		for _ in 0..3 {
+			handle.step()?;
+			let update: Update = read_async(&mut stream, encoding).await?;
+			assert_eq!(update.tick, tick + 1);
+			assert!(matches!(update.grid, GridUpdate::Delta(_)));
+			tick = update.tick;
+		}
+
+		let disconnect = Request::new("Mark", RequestKind::Disconnect);
+		send_async(&mut stream, disconnect, encoding).await?;
+		// Server closes the connection, updates may be pushed until that.
+		while read_async::<Update>(&mut stream, encoding).await.is_ok() {}
+		assert!(!handle.gamedata().find_snake("Mark"));
+		assert!(handle.clients().is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn reload() -> Result<()> {
+		let mut gamedata = GameData::new(Some((20, 20)), Settings::default());
+		gamedata.spawn_snake("Bot 1", None, None, None)?;
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let handle = server.handle();
+		let mut config = config::Config {
+			delay: Duration::from_millis(10),
+			bots: 1,
+			..Default::default()
+		};
+		config.settings.apples_amount = 5;
+
+		// Bot's name is taken by a client, so nothing is applied.
+		assert!(handle.reload(&config).is_err());
+		let apples = handle.gamedata().settings().apples_amount;
+		assert_eq!(apples, Settings::default().apples_amount);
+		assert_eq!(handle.game_delay(), GAME_DELAY);
+
+		handle.gamedata().kill_snake("Bot 1")?;
+		handle.reload(&config)?;
+		assert_eq!(handle.gamedata().settings().apples_amount, 5);
+		assert!(handle.gamedata().is_bot("Bot 1"));
+		assert_eq!(handle.game_delay(), config.delay);
+		Ok(())
+	}
+
+	#[test]
+	fn tick_update_messages() -> Result<()> {
+		let gamedata = GameData::new(Some((20, 20)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let handle = server.handle();
+		let first = handle.tick_update(None);
+		for i in 0..chat::FETCH_LIMIT + 4 {
+			handle.post_message(None, i.to_string());
+		}
+
+		let update = handle.tick_update(Some(&first));
+		assert_eq!(update.messages_since, 0);
+		assert_eq!(update.update.messages.len(), chat::FETCH_LIMIT);
+		assert_eq!(update.message, chat::FETCH_LIMIT as u64);
+
+		let next = handle.tick_update(Some(&update));
+		assert_eq!(next.messages_since, chat::FETCH_LIMIT as u64);
+		assert_eq!(next.update.messages.len(), 4);
+		assert_eq!(next.update.messages[0].text, chat::FETCH_LIMIT.to_string());
+		assert_eq!(next.message, lock(&handle.chat).last_id());
+
+		let last = handle.tick_update(Some(&next));
+		assert!(last.update.messages.is_empty());
+		assert_eq!(last.message, next.message);
+		Ok(())
+	}
+}
@@ -0,0 +1,467 @@
+//! UDP transport.
+//!
+//! Lost TCP packet delays everything sent after it, so players on lossy links
+//! may get the game over UDP besides their TCP connection. Client asks for a
+//! [`UdpTicket`] with a request to open UDP transport, see
+//! [`Client::open_udp`](crate::Client::open_udp), and sends [`Input`] packets
+//! with the ticket token to the UDP port of the server. The first one binds
+//! the address it's sent from to the client.
+//!
+//! Whole grid doesn't fit into a datagram, so client requests it over TCP and
+//! acknowledges its tick first, see [`UdpClient::acknowledge`]. Server sends
+//! a [`Snapshot`] after every tick to every client which hasn't acknowledged
+//! the current tick yet. Snapshots are numbered, so client drops ones
+//! arriving out of order, and contain changes since the last tick client has
+//! acknowledged, so any of them can be applied. If that tick is too old to
+//! make changes since it, snapshot has no changes and client requests the
+//! grid over TCP again. Inputs carry the last
+//! [`INPUT_REDUNDANCY`] direction changes server hasn't acknowledged, so a
+//! lost input is covered by the next one.
+//!
+//! Snapshots and inputs are in the encoding negotiated over TCP, inputs are
+//! prefixed with the token written as an 8-byte big-endian unsigned integer.
+
+use crate::{
+	encoding::Encoding, ErrorChain, Result, ServerError, ServerHandle,
+};
+use game::{
+	error::GameError,
+	grid::{GridDelta, GridUpdate},
+	prelude::*,
+};
+use logger::*;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::VecDeque,
+	convert::TryInto,
+	io,
+	net::{SocketAddr, UdpSocket},
+	time::Duration,
+};
+
+/// Maximum size of a UDP packet, larger snapshots aren't sent.
+pub const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// Amount of the last direction changes every input carries.
+pub const INPUT_REDUNDANCY: usize = 4;
+
+/// Size of the token prefixing inputs.
+const TOKEN_SIZE: usize = 8;
+
+/// Server's answer to a request to open UDP transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UdpTicket {
+	/// UDP port of the server.
+	pub port: u16,
+
+	/// Token client prefixes its inputs with.
+	pub token: u64,
+}
+
+/// State of the grid server sends over UDP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+	/// Number of the snapshot, it's increased with every one sent to client.
+	pub sequence: u64,
+
+	/// Game tick the snapshot is made on.
+	pub tick: u64,
+
+	/// Sequence number of the last direction change server has applied.
+	pub input: u64,
+
+	/// Changes of the grid since the tick client has acknowledged, none if
+	/// that tick is too old.
+	pub grid: Option<GridDelta>,
+}
+
+impl Snapshot {
+	/// Send the snapshot in the `encoding` from the `socket` to the
+	/// `address`.
+	pub(crate) fn send(
+		&self,
+		socket: &UdpSocket,
+		address: SocketAddr,
+		encoding: Encoding,
+	) -> Result<()> {
+		let packet = encoding.encode(self)?;
+		if packet.len() > MAX_DATAGRAM_SIZE {
+			return Err(ServerError::FrameTooLarge(
+				packet.len(),
+				MAX_DATAGRAM_SIZE,
+			));
+		}
+		socket.send_to(&packet, address)?;
+		Ok(())
+	}
+}
+
+/// Packet client sends over UDP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+	/// Tick of the grid client has, none if it has no grid.
+	pub ack: Option<u64>,
+
+	/// Direction changes in order they're made.
+	pub commands: Vec<Command>,
+}
+
+impl Input {
+	/// Convert the input to a packet in the `encoding` prefixed with the
+	/// `token`.
+	pub fn to_packet(&self, token: u64, encoding: Encoding) -> Result<Vec<u8>> {
+		let mut packet = token.to_be_bytes().to_vec();
+		packet.extend(encoding.encode(self)?);
+		Ok(packet)
+	}
+}
+
+/// Direction change made by client.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Command {
+	/// Number of the change, it's increased with every one.
+	pub sequence: u64,
+
+	/// Tick of the grid client had when it has changed direction.
+	pub tick: u64,
+
+	/// New direction of the snake.
+	pub direction: Direction,
+}
+
+/// Split the `packet` into the token and the input, return none if it's too
+/// short.
+pub(crate) fn split_token(packet: &[u8]) -> Option<(u64, &[u8])> {
+	if packet.len() < TOKEN_SIZE {
+		return None;
+	}
+	let (token, input) = packet.split_at(TOKEN_SIZE);
+	Some((u64::from_be_bytes(token.try_into().ok()?), input))
+}
+
+/// Receiver of inputs of clients playing the game of a running server.
+pub struct UdpServer {
+	socket: UdpSocket,
+	handle: ServerHandle,
+}
+
+impl UdpServer {
+	/// Bind UDP socket to the `address`. Clients play the game of the server
+	/// with the `handle`, it sends snapshots from the socket after that.
+	pub fn bind(address: &str, handle: ServerHandle) -> Result<Self> {
+		let socket = UdpSocket::bind(address)?;
+		handle.set_udp_socket(socket.try_clone()?);
+		Ok(Self { socket, handle })
+	}
+
+	/// Return address the socket is bound to.
+	pub fn local_addr(&self) -> Result<SocketAddr> {
+		Ok(self.socket.local_addr()?)
+	}
+
+	/// Receive inputs and apply them to the game.
+	pub fn run(self) -> Result<()> {
+		let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+		loop {
+			let (size, address) = match self.socket.recv_from(&mut buffer) {
+				Ok(val) => val,
+				Err(e) => {
+					debug!("Failed to receive UDP packet: {}", e);
+					continue;
+				}
+			};
+			if let Err(e) = self.handle.receive_input(&buffer[..size], address)
+			{
+				debug!(
+					"Dropped UDP packet from {}: {}",
+					address,
+					ErrorChain(&e)
+				);
+			}
+		}
+	}
+}
+
+/// Client side of UDP transport, see [`Client::open_udp`](crate::Client::open_udp).
+#[derive(Debug)]
+pub struct UdpClient {
+	/// Socket connected to the server.
+	socket: UdpSocket,
+
+	/// Token inputs are prefixed with.
+	token: u64,
+
+	/// Encoding of snapshots and inputs.
+	encoding: Encoding,
+
+	/// Sequence number of the last received snapshot.
+	sequence: Option<u64>,
+
+	/// Tick of client's grid.
+	ack: Option<u64>,
+
+	/// Direction changes server hasn't acknowledged yet.
+	commands: VecDeque<Command>,
+
+	/// Sequence number of the last direction change.
+	command: u64,
+
+	/// Share of sent and received packets dropped on purpose.
+	loss: f64,
+}
+
+impl UdpClient {
+	/// Connect to the UDP socket of the server with the `address` and bind
+	/// client's address to the `token` with the first input.
+	pub fn connect(
+		address: SocketAddr,
+		token: u64,
+		encoding: Encoding,
+	) -> Result<Self> {
+		let local = match address {
+			SocketAddr::V4(_) => "0.0.0.0:0",
+			SocketAddr::V6(_) => "[::]:0",
+		};
+		let socket = UdpSocket::bind(local)?;
+		socket.connect(address)?;
+		socket.set_read_timeout(crate::CLIENT_READ_TIMEOUT)?;
+		let client = Self {
+			socket,
+			token,
+			encoding,
+			sequence: None,
+			ack: None,
+			commands: VecDeque::with_capacity(INPUT_REDUNDANCY),
+			command: 0,
+			loss: 0.0,
+		};
+		client.send_input()?;
+		Ok(client)
+	}
+
+	/// Set timeout of waiting for a snapshot in [`update`](Self::update).
+	pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+		Ok(self.socket.set_read_timeout(timeout)?)
+	}
+
+	/// Drop the `loss` share of sent and received packets to simulate lossy
+	/// link, e.g. 0.3 drops about 30% of them.
+	pub fn set_loss(&mut self, loss: f64) {
+		self.loss = loss;
+	}
+
+	/// Change snake's direction to the `direction`. `tick` is a tick of the
+	/// grid client has.
+	pub fn change_direction(
+		&mut self,
+		tick: u64,
+		direction: Direction,
+	) -> Result<()> {
+		self.command += 1;
+		if self.commands.len() == INPUT_REDUNDANCY {
+			self.commands.pop_front();
+		}
+		self.commands.push_back(Command {
+			sequence: self.command,
+			tick,
+			direction,
+		});
+		self.send_input()
+	}
+
+	/// Acknowledge the `tick` of the grid requested over TCP, server sends
+	/// changes since it after that.
+	pub fn acknowledge(&mut self, tick: u64) -> Result<()> {
+		self.ack = Some(tick);
+		self.send_input()
+	}
+
+	/// Wait for the next snapshot and apply it to the `grid`, then
+	/// acknowledge its tick. Return false if there's no snapshot during read
+	/// timeout or it can't be applied. Unacknowledged direction changes and
+	/// inputs sent before the first snapshot are sent again if there's no
+	/// snapshot. Return [`ServerError::GridOutdated`] if the grid has to be
+	/// requested over TCP again.
+	pub fn update(&mut self, grid: &mut Grid) -> Result<bool> {
+		let snapshot = match self.receive()? {
+			Some(snapshot) => snapshot,
+			None => {
+				if !self.commands.is_empty() || self.sequence.is_none() {
+					self.send_input()?;
+				}
+				return Ok(false);
+			}
+		};
+		self.commands
+			.retain(|command| command.sequence > snapshot.input);
+		let delta = snapshot.grid.ok_or(ServerError::GridOutdated)?;
+		match grid.apply(GridUpdate::Delta(delta)) {
+			Ok(()) => (),
+			// Snapshot made before the previous acknowledgement arrived.
+			Err(GameError::DeltaBaseMismatch(..)) => return Ok(false),
+			Err(e) => return Err(e.into()),
+		}
+		self.ack = Some(grid.tick);
+		self.send_input()?;
+		Ok(true)
+	}
+
+	/// Receive the next snapshot, return none if there's no one during read
+	/// timeout or it's older than the previous one.
+	fn receive(&mut self) -> Result<Option<Snapshot>> {
+		let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+		let size = match self.socket.recv(&mut buffer) {
+			Ok(size) => size,
+			Err(e)
+				if matches!(
+					e.kind(),
+					io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+				) =>
+			{
+				return Ok(None)
+			}
+			Err(e) => return Err(e.into()),
+		};
+		if self.is_lost() {
+			return Ok(None);
+		}
+		let snapshot: Snapshot = self.encoding.decode(&buffer[..size])?;
+		if self.sequence >= Some(snapshot.sequence) {
+			return Ok(None);
+		}
+		self.sequence = Some(snapshot.sequence);
+		Ok(Some(snapshot))
+	}
+
+	/// Send acknowledged tick and unacknowledged direction changes.
+	fn send_input(&self) -> Result<()> {
+		if self.is_lost() {
+			return Ok(());
+		}
+		let input = Input {
+			ack: self.ack,
+			commands: self.commands.iter().copied().collect(),
+		};
+		self.socket
+			.send(&input.to_packet(self.token, self.encoding)?)?;
+		Ok(())
+	}
+
+	/// Return true if the packet should be dropped to simulate loss.
+	fn is_lost(&self) -> bool {
+		self.loss > 0.0 && rand::random::<f64>() < self.loss
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Client, Server};
+	use std::{net::TcpStream, thread};
+
+	struct TestClient {
+		stream: Option<TcpStream>,
+		id: Option<String>,
+		encoding: Encoding,
+	}
+
+	impl Client for TestClient {
+		fn set_stream(&mut self, stream: Option<TcpStream>) {
+			self.stream = stream;
+		}
+
+		fn stream(&mut self) -> Option<&mut TcpStream> {
+			self.stream.as_mut()
+		}
+
+		fn stream_clone(&self) -> Result<TcpStream> {
+			let stream = self.stream.as_ref().ok_or(ServerError::NoStream)?;
+			Ok(stream.try_clone()?)
+		}
+
+		fn set_id(&mut self, id: Option<String>) {
+			self.id = id;
+		}
+
+		fn id(&self) -> Option<String> {
+			self.id.clone()
+		}
+
+		fn set_encoding(&mut self, encoding: Encoding) {
+			self.encoding = encoding;
+		}
+
+		fn encoding(&self) -> Encoding {
+			self.encoding
+		}
+	}
+
+	#[test]
+	fn lossy_loopback() -> Result<()> {
+		let gamedata = GameData::new(Some((30, 30)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let address = server.local_addr()?;
+		let handle = server.handle();
+		handle.pause();
+		let udp = UdpServer::bind("127.0.0.1:0", handle.clone())?;
+		thread::spawn(move || udp.run());
+		thread::spawn(move || server.run());
+
+		let mut client = TestClient {
+			stream: None,
+			id: Some("Mark".into()),
+			encoding: Encoding::Json,
+		};
+		client.connect(address)?;
+		let name = client.id().unwrap();
+		let mut udp = client.open_udp()?;
+		udp.set_loss(0.3);
+		udp.set_read_timeout(Some(Duration::from_millis(20)))?;
+		let mut grid = client.request_grid()?;
+		udp.acknowledge(grid.tick)?;
+
+		let direction = match handle.gamedata().snake(&name)?.direction() {
+			Direction::Up | Direction::Down => Direction::Left,
+			_ => Direction::Up,
+		};
+		udp.change_direction(grid.tick, direction)?;
+		let changed = (0..500).any(|_| {
+			udp.update(&mut grid).expect("updating grid");
+			handle.gamedata().snake(&name).unwrap().direction() == direction
+		});
+		assert!(changed, "direction change is lost");
+
+		for _ in 0..3 {
+			handle.step()?;
+		}
+		let tick = handle.gamedata().tick();
+		let synced = (0..500).any(|_| {
+			udp.update(&mut grid).expect("updating grid");
+			grid.tick == tick
+		});
+		assert!(synced, "grid isn't synced");
+		assert_eq!(grid.len(), handle.gamedata().grid().len());
+
+		// Changes can't be made since ticks older than the grid history.
+		udp.set_loss(0.0);
+		for _ in 0..40 {
+			handle.step()?;
+		}
+		let outdated = (0..500).any(|_| {
+			matches!(udp.update(&mut grid), Err(ServerError::GridOutdated))
+		});
+		assert!(outdated, "outdated grid isn't reported");
+		grid = client.request_grid()?;
+		udp.acknowledge(grid.tick)?;
+		handle.step()?;
+		let tick = handle.gamedata().tick();
+		let synced = (0..500).any(|_| {
+			// Snapshots sent before the acknowledgement may still arrive.
+			let _ = udp.update(&mut grid);
+			grid.tick == tick
+		});
+		assert!(synced, "requested grid isn't updated");
+
+		Ok(())
+	}
+}
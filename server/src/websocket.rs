@@ -0,0 +1,86 @@
+//! WebSocket transport.
+//!
+//! WebSocket listener accepts connections on its own port, so browser-based
+//! clients can connect to the server without a proxy. Requests and responses
+//! are the same as with raw TCP, but every one is sent as a separate
+//! WebSocket message instead of being prefixed with its length. Server sends
+//! json responses as text messages and MessagePack ones as binary messages.
+//!
+//! For example, [websocat](https://github.com/vi/websocat) can be used to
+//! talk to the server:
+//! ```sh
+//! websocat ws://localhost:8789
+//! {"client": "Mark", "kind": "connect"}
+//! ```
+
+use crate::{accept, connection::Protocol, Result, ServerHandle};
+use std::net::{SocketAddr, TcpListener};
+
+/// Listener of WebSocket clients playing the game of a running server.
+pub struct WebSocketListener {
+	listener: TcpListener,
+	handle: ServerHandle,
+}
+
+impl WebSocketListener {
+	/// Bind listener to the `address`. Clients play the game of the server
+	/// with the `handle`.
+	pub fn bind(address: &str, handle: ServerHandle) -> Result<Self> {
+		let listener = TcpListener::bind(address)?;
+		listener.set_nonblocking(true)?;
+		Ok(Self { listener, handle })
+	}
+
+	/// Return address the listener is bound to.
+	pub fn local_addr(&self) -> Result<SocketAddr> {
+		Ok(self.listener.local_addr()?)
+	}
+
+	/// Accept connections and handle every one in its own task on the
+	/// runtime of the server. This function returns only on error.
+	pub fn run(self) -> Result<()> {
+		let runtime = self.handle.runtime.clone();
+		runtime.block_on(accept(
+			self.listener,
+			self.handle,
+			Protocol::WebSocket,
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Server;
+	use game::prelude::*;
+	use std::thread;
+	use tungstenite::Message;
+
+	#[test]
+	fn loopback() -> Result<()> {
+		let gamedata = GameData::new(Some((30, 30)), Settings::default());
+		let server = Server::bind("127.0.0.1:0", gamedata, None)?;
+		let handle = server.handle();
+		handle.pause();
+		handle.set_max_request_size(64);
+		let listener = WebSocketListener::bind("127.0.0.1:0", handle)?;
+		let address = listener.local_addr()?;
+		thread::spawn(move || listener.run());
+		thread::spawn(move || server.run());
+
+		let (mut socket, _) =
+			tungstenite::connect(format!("ws://{}", address))?;
+		let request = r#"{"client": "Mark", "kind": "connect"}"#;
+		socket.send(Message::Text(request.into()))?;
+		assert_eq!(socket.read()?, Message::Text(r#""Mark""#.into()));
+
+		// Messages larger than requests are refused.
+		socket.send(Message::Text("x".repeat(65)))?;
+		assert!(!matches!(
+			socket.read(),
+			Ok(Message::Text(_)) | Ok(Message::Binary(_))
+		));
+
+		Ok(())
+	}
+}